@@ -0,0 +1,186 @@
+use std::io;
+
+use rusqlite::Result as SqlResult;
+
+use crate::Db;
+
+/// Fichero con la ruta de la base de datos, que no puede guardarse en ella
+pub const RUTA_CONFIGURACION: &str = "pixi.conf";
+pub const RUTA_DB_POR_DEFECTO: &str = "tareas.db";
+
+const CLAVE_TEMA: &str = "tema";
+const CLAVE_ESCALA_FUENTE: &str = "escala_fuente";
+const CLAVE_COLOR_TEMPORIZADOR: &str = "color_temporizador";
+const CLAVE_COLOR_ARRASTRE: &str = "color_arrastre";
+const CLAVE_CONFIRMAR_BORRADO: &str = "confirmar_borrado";
+const CLAVE_DIAS_PAPELERA: &str = "dias_papelera";
+const CLAVE_COPIAS_CONSERVAR: &str = "copias_conservar";
+const CLAVE_INTERVALO_COPIAS: &str = "intervalo_copias";
+const CLAVE_LIMITE_GANCHOS: &str = "limite_ganchos";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tema {
+    #[default]
+    Oscuro,
+    Claro,
+    /// El que indique el sistema operativo
+    Sistema,
+}
+
+impl Tema {
+    pub const TODOS: [Tema; 3] = [Tema::Oscuro, Tema::Claro, Tema::Sistema];
+
+    pub fn codigo(self) -> &'static str {
+        match self {
+            Tema::Oscuro => "oscuro",
+            Tema::Claro => "claro",
+            Tema::Sistema => "sistema",
+        }
+    }
+
+    pub fn desde_codigo(codigo: &str) -> Option<Self> {
+        Self::TODOS.into_iter().find(|t| t.codigo() == codigo)
+    }
+}
+
+/// Preferencias de apariencia y comportamiento guardadas en la tabla `ajustes`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ajustes {
+    pub tema: Tema,
+    /// Factor aplicado a los tamaños de letra base
+    pub escala_fuente: f32,
+    /// Borde de las tareas con el temporizador en marcha (RGB)
+    pub color_temporizador: [u8; 3],
+    /// Borde de la tarea que se está arrastrando (RGB)
+    pub color_arrastre: [u8; 3],
+    pub confirmar_borrado: bool,
+    /// Días que pasa una tarea en la papelera antes de borrarse (0 = nunca)
+    pub dias_papelera: u32,
+    /// Copias de seguridad automáticas que se guardan (0 = ninguna)
+    pub copias_conservar: u32,
+    /// Minutos entre copias mientras la aplicación está abierta (0 = solo
+    /// al abrir y al salir)
+    pub intervalo_copias: u32,
+    /// Segundos que puede tardar un gancho antes de matarlo
+    pub limite_ganchos: u32,
+}
+
+impl Default for Ajustes {
+    fn default() -> Self {
+        Self {
+            tema: Tema::Oscuro,
+            escala_fuente: 1.0,
+            color_temporizador: [100, 200, 100],
+            color_arrastre: [100, 200, 255],
+            confirmar_borrado: false,
+            dias_papelera: 30,
+            copias_conservar: 5,
+            intervalo_copias: 60,
+            limite_ganchos: 10,
+        }
+    }
+}
+
+impl Ajustes {
+    pub const ESCALA_MINIMA: f32 = 0.5;
+    pub const ESCALA_MAXIMA: f32 = 2.0;
+}
+
+fn color_a_hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn color_desde_hex(hex: &str) -> Option<[u8; 3]> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let canal = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([canal(0)?, canal(2)?, canal(4)?])
+}
+
+impl Db {
+    /// Ajustes guardados; los que falten o no se entiendan toman su valor por defecto
+    pub fn cargar_ajustes(&self) -> SqlResult<Ajustes> {
+        let defecto = Ajustes::default();
+        Ok(Ajustes {
+            tema: self
+                .obtener_ajuste(CLAVE_TEMA)?
+                .and_then(|v| Tema::desde_codigo(&v))
+                .unwrap_or(defecto.tema),
+            escala_fuente: self
+                .obtener_ajuste(CLAVE_ESCALA_FUENTE)?
+                .and_then(|v| v.parse::<f32>().ok())
+                .filter(|e| (Ajustes::ESCALA_MINIMA..=Ajustes::ESCALA_MAXIMA).contains(e))
+                .unwrap_or(defecto.escala_fuente),
+            color_temporizador: self
+                .obtener_ajuste(CLAVE_COLOR_TEMPORIZADOR)?
+                .and_then(|v| color_desde_hex(&v))
+                .unwrap_or(defecto.color_temporizador),
+            color_arrastre: self
+                .obtener_ajuste(CLAVE_COLOR_ARRASTRE)?
+                .and_then(|v| color_desde_hex(&v))
+                .unwrap_or(defecto.color_arrastre),
+            confirmar_borrado: self
+                .obtener_ajuste(CLAVE_CONFIRMAR_BORRADO)?
+                .map_or(defecto.confirmar_borrado, |v| v == "1"),
+            dias_papelera: self
+                .obtener_ajuste(CLAVE_DIAS_PAPELERA)?
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defecto.dias_papelera),
+            copias_conservar: self
+                .obtener_ajuste(CLAVE_COPIAS_CONSERVAR)?
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defecto.copias_conservar),
+            intervalo_copias: self
+                .obtener_ajuste(CLAVE_INTERVALO_COPIAS)?
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defecto.intervalo_copias),
+            limite_ganchos: self
+                .obtener_ajuste(CLAVE_LIMITE_GANCHOS)?
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defecto.limite_ganchos),
+        })
+    }
+
+    pub fn guardar_ajustes(&self, ajustes: &Ajustes) -> SqlResult<()> {
+        self.guardar_ajuste(CLAVE_TEMA, ajustes.tema.codigo())?;
+        self.guardar_ajuste(CLAVE_ESCALA_FUENTE, &ajustes.escala_fuente.to_string())?;
+        self.guardar_ajuste(
+            CLAVE_COLOR_TEMPORIZADOR,
+            &color_a_hex(ajustes.color_temporizador),
+        )?;
+        self.guardar_ajuste(CLAVE_COLOR_ARRASTRE, &color_a_hex(ajustes.color_arrastre))?;
+        self.guardar_ajuste(
+            CLAVE_CONFIRMAR_BORRADO,
+            if ajustes.confirmar_borrado { "1" } else { "0" },
+        )?;
+        self.guardar_ajuste(CLAVE_DIAS_PAPELERA, &ajustes.dias_papelera.to_string())?;
+        self.guardar_ajuste(
+            CLAVE_COPIAS_CONSERVAR,
+            &ajustes.copias_conservar.to_string(),
+        )?;
+        self.guardar_ajuste(
+            CLAVE_INTERVALO_COPIAS,
+            &ajustes.intervalo_copias.to_string(),
+        )?;
+        self.guardar_ajuste(CLAVE_LIMITE_GANCHOS, &ajustes.limite_ganchos.to_string())
+    }
+}
+
+/// Ruta de la base de datos indicada en [`RUTA_CONFIGURACION`] (línea `db = …`)
+pub fn ruta_db() -> String {
+    std::fs::read_to_string(RUTA_CONFIGURACION)
+        .ok()
+        .and_then(|contenido| {
+            contenido.lines().find_map(|linea| {
+                let (clave, valor) = linea.split_once('=')?;
+                (clave.trim() == "db" && !valor.trim().is_empty()).then(|| valor.trim().to_string())
+            })
+        })
+        .unwrap_or_else(|| RUTA_DB_POR_DEFECTO.to_string())
+}
+
+pub fn guardar_ruta_db(ruta: &str) -> io::Result<()> {
+    std::fs::write(RUTA_CONFIGURACION, format!("db = {}\n", ruta))
+}
@@ -0,0 +1,366 @@
+//! API HTTP/JSON local (`pixi serve`) para usar pixi desde scripts y otras
+//! herramientas.
+//!
+//! Cada petición lleva la cabecera `Authorization: Bearer <token>`. Las
+//! rutas son:
+//! - `GET /tareas`, `POST /tareas` con `{"descripcion": …}`
+//! - `GET`, `PATCH` y `DELETE /tareas/<id>`. `PATCH` acepta `descripcion`,
+//!   `notas`, `prioridad` (0–4), `estimacion` (segundos o `null`) y
+//!   `completada`.
+//! - `POST /tareas/<id>/temporizador` lo inicia; `DELETE`, lo para y guarda.
+//! - `GET /estadisticas`
+//!
+//! Las tareas se devuelven como en [`crate::exportar::a_json`], y los errores
+//! como `{"error": …}`.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use rusqlite::Result as SqlResult;
+use serde_json::{Map, Value, json};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::exportar::a_json;
+use crate::{Db, Edicion, Periodo, Prioridad, ResultadoEdicion, ResumenDia, ServicioTareas};
+
+/// Solo se escucha en el propio equipo salvo que se pida otra dirección
+pub const DIRECCION_POR_DEFECTO: &str = "127.0.0.1:7878";
+
+const CLAVE_TOKEN: &str = "api_token";
+
+/// Tamaño máximo del cuerpo de una petición
+const MAX_CUERPO: u64 = 1024 * 1024;
+
+impl Db {
+    /// Token de la API guardado, si ya se ha creado
+    pub fn token_api(&self) -> SqlResult<Option<String>> {
+        self.obtener_ajuste(CLAVE_TOKEN)
+    }
+
+    /// Crea y guarda un token nuevo, con 128 bits al azar del sistema
+    /// operativo; el anterior deja de valer
+    pub fn crear_token_api(&self) -> io::Result<String> {
+        let mut bytes = [0u8; 16];
+        getrandom::fill(&mut bytes)?;
+        let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        self.guardar_ajuste(CLAVE_TOKEN, &token)
+            .map_err(io::Error::other)?;
+        Ok(token)
+    }
+}
+
+// Error de una petición, con el estado HTTP que le corresponde
+#[derive(Debug)]
+enum ErrorApi {
+    NoAutorizado,
+    NoEncontrado,
+    Peticion(String),
+    /// Otra instancia editó la tarea a la vez
+    Conflicto,
+    Sql(rusqlite::Error),
+}
+
+impl ErrorApi {
+    fn estado(&self) -> u16 {
+        match self {
+            ErrorApi::NoAutorizado => 401,
+            ErrorApi::NoEncontrado => 404,
+            ErrorApi::Peticion(_) => 400,
+            ErrorApi::Conflicto => 409,
+            ErrorApi::Sql(_) => 500,
+        }
+    }
+}
+
+impl fmt::Display for ErrorApi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorApi::NoAutorizado => write!(f, "missing or wrong token"),
+            ErrorApi::NoEncontrado => write!(f, "not found"),
+            ErrorApi::Peticion(e) => write!(f, "{}", e),
+            ErrorApi::Conflicto => write!(f, "the task was edited elsewhere; reload it"),
+            ErrorApi::Sql(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ErrorApi {
+    fn from(e: rusqlite::Error) -> Self {
+        ErrorApi::Sql(e)
+    }
+}
+
+/// Servidor de la API sobre un [`ServicioTareas`] propio, con sus
+/// temporizadores en marcha
+pub struct ServidorApi {
+    servidor: Server,
+    servicio: ServicioTareas,
+    token: String,
+}
+
+impl ServidorApi {
+    /// Escucha en `direccion`; el puerto 0 elige uno libre
+    pub fn new(db: Db, direccion: &str, token: &str) -> io::Result<Self> {
+        let servidor = Server::http(direccion).map_err(io::Error::other)?;
+        let mut servicio = ServicioTareas::new(db);
+        servicio.cargar_todo();
+        Ok(Self {
+            servidor,
+            servicio,
+            token: token.to_string(),
+        })
+    }
+
+    pub fn direccion(&self) -> Option<SocketAddr> {
+        self.servidor.server_addr().to_ip()
+    }
+
+    /// Atiende peticiones, una detrás de otra, hasta que se cierra el
+    /// proceso. Los temporizadores en marcha se guardan al pararlos.
+    pub fn atender(&mut self) {
+        while self.atender_siguiente() {}
+    }
+
+    /// Espera a la siguiente petición y la responde; `false` si el servidor
+    /// ya no puede recibir más
+    pub fn atender_siguiente(&mut self) -> bool {
+        match self.servidor.recv() {
+            Ok(peticion) => {
+                self.responder(peticion);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Como [`ServidorApi::atender_siguiente`], pero esperando como mucho
+    /// `espera`, para poder mirar entre petición y petición si hay que parar
+    pub fn atender_durante(&mut self, espera: Duration) -> bool {
+        match self.servidor.recv_timeout(espera) {
+            Ok(Some(peticion)) => {
+                self.responder(peticion);
+                true
+            }
+            Ok(None) => true,
+            Err(_) => false,
+        }
+    }
+
+    /// Para y guarda los temporizadores en marcha; también se hace al
+    /// destruir el servidor
+    pub fn pausar_temporizadores(&mut self) {
+        self.servicio.pausar_todos();
+    }
+
+    pub fn db(&self) -> &Db {
+        self.servicio.db()
+    }
+
+    fn responder(&mut self, mut peticion: Request) {
+        let (estado, cuerpo) = match self.procesar(&mut peticion) {
+            Ok((estado, cuerpo)) => (estado, cuerpo),
+            Err(e) => (e.estado(), Some(json!({ "error": e.to_string() }))),
+        };
+        let tipo = Header::from_bytes("Content-Type", "application/json").unwrap();
+        let respuesta = match cuerpo {
+            Some(cuerpo) => Response::from_string(cuerpo.to_string()).with_header(tipo),
+            None => Response::from_string(""),
+        };
+        // Si el cliente ya se ha ido no hay a quién avisar
+        let _ = peticion.respond(respuesta.with_status_code(estado));
+    }
+
+    fn procesar(&mut self, peticion: &mut Request) -> Result<(u16, Option<Value>), ErrorApi> {
+        if !self.autorizada(peticion) {
+            return Err(ErrorApi::NoAutorizado);
+        }
+        // Cambios guardados por la ventana u otra instancia
+        if self.servicio.hay_cambios_externos() {
+            self.servicio.recargar();
+        }
+
+        let ruta = peticion.url().split('?').next().unwrap_or_default();
+        let partes: Vec<&str> = ruta.split('/').filter(|p| !p.is_empty()).collect();
+        let metodo = peticion.method().clone();
+        match (&metodo, partes.as_slice()) {
+            (Method::Get, ["tareas"]) => {
+                let tareas = self.servicio.tareas().iter().map(a_json).collect();
+                Ok((200, Some(Value::Array(tareas))))
+            }
+            (Method::Post, ["tareas"]) => {
+                let cuerpo = leer_cuerpo(peticion)?;
+                let descripcion = cuerpo
+                    .get("descripcion")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| ErrorApi::Peticion("missing \"descripcion\"".to_string()))?;
+                let id = self
+                    .servicio
+                    .agregar(descripcion)?
+                    .ok_or_else(|| ErrorApi::Peticion("empty \"descripcion\"".to_string()))?;
+                Ok((201, Some(self.tarea(id)?)))
+            }
+            (Method::Get, ["tareas", id]) => Ok((200, Some(self.tarea(parsear_id(id)?)?))),
+            (Method::Patch, ["tareas", id]) => {
+                let id = parsear_id(id)?;
+                let cuerpo = leer_cuerpo(peticion)?;
+                self.modificar(id, &cuerpo)?;
+                Ok((200, Some(self.tarea(id)?)))
+            }
+            (Method::Delete, ["tareas", id]) => {
+                let id = parsear_id(id)?;
+                self.tarea(id)?;
+                self.servicio.eliminar(id)?;
+                Ok((204, None))
+            }
+            (Method::Post | Method::Delete, ["tareas", id, "temporizador"]) => {
+                let id = parsear_id(id)?;
+                let tarea = self.servicio.tarea(id).ok_or(ErrorApi::NoEncontrado)?;
+                if tarea.temporizador_activo() != (metodo == Method::Post) {
+                    self.servicio.alternar_temporizador(id);
+                }
+                Ok((200, Some(self.tarea(id)?)))
+            }
+            (Method::Get, ["estadisticas"]) => Ok((200, Some(self.estadisticas()?))),
+            _ => Err(ErrorApi::NoEncontrado),
+        }
+    }
+
+    fn autorizada(&self, peticion: &Request) -> bool {
+        let esperada = format!("Bearer {}", self.token);
+        peticion.headers().iter().any(|h| {
+            h.field.equiv("Authorization") && iguales(h.value.as_bytes(), esperada.as_bytes())
+        })
+    }
+
+    fn tarea(&self, id: i32) -> Result<Value, ErrorApi> {
+        self.servicio
+            .tarea(id)
+            .map(a_json)
+            .ok_or(ErrorApi::NoEncontrado)
+    }
+
+    // Aplica los campos de un `PATCH`; los que no vienen no cambian
+    fn modificar(&mut self, id: i32, cuerpo: &Map<String, Value>) -> Result<(), ErrorApi> {
+        let tarea = self.servicio.tarea(id).ok_or(ErrorApi::NoEncontrado)?;
+        let mut edicion = Edicion::desde(tarea);
+        let mut editada = false;
+        for (campo, valor) in cuerpo {
+            let invalido = || ErrorApi::Peticion(format!("invalid \"{}\"", campo));
+            match campo.as_str() {
+                "descripcion" => edicion.descripcion = valor.as_str().ok_or_else(invalido)?.into(),
+                "notas" => edicion.notas = valor.as_str().ok_or_else(invalido)?.into(),
+                "prioridad" => {
+                    let prioridad = valor.as_i64().ok_or_else(invalido)?;
+                    edicion.prioridad = Prioridad::TODAS
+                        .into_iter()
+                        .find(|p| *p as i64 == prioridad)
+                        .ok_or_else(invalido)?;
+                }
+                "estimacion" if valor.is_null() => edicion.estimacion = None,
+                "estimacion" => {
+                    let segundos = valor.as_i64().filter(|s| *s >= 0).ok_or_else(invalido)?;
+                    edicion.estimacion = Some(i32::try_from(segundos).map_err(|_| invalido())?);
+                }
+                "completada" => continue,
+                _ => return Err(ErrorApi::Peticion(format!("unknown field \"{}\"", campo))),
+            }
+            editada = true;
+        }
+        let completada = match cuerpo.get("completada") {
+            Some(valor) => Some(
+                valor
+                    .as_bool()
+                    .ok_or_else(|| ErrorApi::Peticion("invalid \"completada\"".to_string()))?,
+            ),
+            None => None,
+        };
+
+        if !editada {
+            if let Some(completada) = completada {
+                self.servicio.marcar(id, completada)?;
+            }
+            return Ok(());
+        }
+        match self.servicio.editar_y_marcar(id, &edicion, completada)? {
+            ResultadoEdicion::Guardada => Ok(()),
+            ResultadoEdicion::Vacia => Err(ErrorApi::Peticion("empty \"descripcion\"".to_string())),
+            ResultadoEdicion::Conflicto => Err(ErrorApi::Conflicto),
+        }
+    }
+
+    // Totales, progreso de hoy y de la semana y sus metas, con las sesiones
+    // en curso
+    fn estadisticas(&self) -> Result<Value, ErrorApi> {
+        let db = self.servicio.db();
+        let tareas = self.servicio.tareas();
+        let totales = db.totales()?.con_sesiones(tareas);
+        let dias = db.resumen_por_dia()?;
+        let dia = db.dia_actual()?;
+        let guardado = dias.iter().find(|r| r.dia == dia);
+        let hoy = ResumenDia {
+            dia,
+            segundos: guardado.map_or(0, |r| r.segundos)
+                + tareas.iter().map(|t| t.tiempo_sesion()).sum::<i32>(),
+            completadas: guardado.map_or(0, |r| r.completadas),
+        };
+        let meta = db.cargar_meta(Periodo::Dia)?;
+        let meta_semanal = db.cargar_meta(Periodo::Semana)?;
+        let semana = Periodo::Semana.resumen(&dias, &hoy);
+        Ok(json!({
+            "tareas": totales.tareas,
+            "completadas": totales.completadas,
+            "segundos": totales.segundos,
+            "hoy": { "segundos": hoy.segundos, "completadas": hoy.completadas },
+            "meta_diaria": {
+                "segundos": meta.segundos,
+                "tareas": meta.tareas,
+                "cumplida": meta.cumplida(hoy.segundos, hoy.completadas),
+                "racha": meta.racha(Periodo::Dia, &dias, &hoy),
+            },
+            "semana": { "segundos": semana.segundos, "completadas": semana.completadas },
+            "meta_semanal": {
+                "segundos": meta_semanal.segundos,
+                "tareas": meta_semanal.tareas,
+                "cumplida": meta_semanal.cumplida(semana.segundos, semana.completadas),
+                "racha": meta_semanal.racha(Periodo::Semana, &dias, &hoy),
+            },
+        }))
+    }
+}
+
+impl Drop for ServidorApi {
+    fn drop(&mut self) {
+        self.pausar_temporizadores();
+    }
+}
+
+fn parsear_id(texto: &str) -> Result<i32, ErrorApi> {
+    texto.parse().map_err(|_| ErrorApi::NoEncontrado)
+}
+
+// El cuerpo de la petición, que debe ser un objeto JSON
+fn leer_cuerpo(peticion: &mut Request) -> Result<Map<String, Value>, ErrorApi> {
+    let mut texto = String::new();
+    peticion
+        .as_reader()
+        .take(MAX_CUERPO)
+        .read_to_string(&mut texto)
+        .map_err(|e| ErrorApi::Peticion(e.to_string()))?;
+    match serde_json::from_str(&texto) {
+        Ok(Value::Object(cuerpo)) => Ok(cuerpo),
+        Ok(_) => Err(ErrorApi::Peticion("expected a JSON object".to_string())),
+        Err(e) => Err(ErrorApi::Peticion(e.to_string())),
+    }
+}
+
+// Compara sin parar en la primera diferencia, para que el tiempo de
+// respuesta no vaya delatando el token
+fn iguales(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .fold(0, |diferencia, (x, y)| diferencia | (x ^ y))
+            == 0
+}
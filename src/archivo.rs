@@ -0,0 +1,35 @@
+use rusqlite::Result as SqlResult;
+
+use crate::{COLUMNAS_TAREA, Db, TodoItem, tarea_desde_fila};
+
+impl Db {
+    /// Archiva todas las tareas completadas y devuelve sus ids. Siguen en la
+    /// base de datos, con su tiempo, pero dejan de formar parte de la lista.
+    pub fn archivar_completadas(&self) -> SqlResult<Vec<i32>> {
+        let mut stmt = self.conn.prepare(
+            "UPDATE tareas SET archivada_en = datetime('now', 'localtime')
+             WHERE completada AND archivada_en IS NULL AND eliminada_en IS NULL
+             RETURNING id",
+        )?;
+        let ids = stmt.query_map([], |row| row.get(0))?;
+        ids.collect()
+    }
+
+    /// Tareas archivadas, de la más reciente a la más antigua
+    pub fn cargar_archivadas(&self) -> SqlResult<Vec<TodoItem>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tareas WHERE archivada_en IS NOT NULL AND eliminada_en IS NULL
+             ORDER BY archivada_en DESC, id DESC",
+            COLUMNAS_TAREA
+        ))?;
+        let tareas = stmt.query_map([], tarea_desde_fila)?;
+        tareas.collect()
+    }
+
+    /// Devuelve una tarea archivada a la lista
+    pub fn restaurar_archivada(&self, id: i32) -> SqlResult<()> {
+        self.conn
+            .execute("UPDATE tareas SET archivada_en = NULL WHERE id = ?1", [id])?;
+        Ok(())
+    }
+}
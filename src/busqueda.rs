@@ -0,0 +1,65 @@
+use rusqlite::Result as SqlResult;
+
+use crate::Db;
+
+impl Db {
+    /// Índice de texto completo sobre descripción y notas, mantenido por triggers
+    pub(crate) fn crear_indice_busqueda(&self) -> SqlResult<()> {
+        let existia: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE name = 'tareas_fts'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        self.conn.execute_batch(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS tareas_fts USING fts5(
+                descripcion, notas,
+                content = 'tareas', content_rowid = 'id',
+                tokenize = 'unicode61 remove_diacritics 2'
+            );
+
+            CREATE TRIGGER IF NOT EXISTS tareas_fts_insert AFTER INSERT ON tareas BEGIN
+                INSERT INTO tareas_fts (rowid, descripcion, notas)
+                VALUES (new.id, new.descripcion, new.notas);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS tareas_fts_delete AFTER DELETE ON tareas BEGIN
+                INSERT INTO tareas_fts (tareas_fts, rowid, descripcion, notas)
+                VALUES ('delete', old.id, old.descripcion, old.notas);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS tareas_fts_update
+            AFTER UPDATE OF descripcion, notas ON tareas BEGIN
+                INSERT INTO tareas_fts (tareas_fts, rowid, descripcion, notas)
+                VALUES ('delete', old.id, old.descripcion, old.notas);
+                INSERT INTO tareas_fts (rowid, descripcion, notas)
+                VALUES (new.id, new.descripcion, new.notas);
+            END;",
+        )?;
+
+        // Bases de datos anteriores al índice: indexar lo que ya hay
+        if existia == 0 {
+            self.conn
+                .execute("INSERT INTO tareas_fts (tareas_fts) VALUES ('rebuild')", [])?;
+        }
+        Ok(())
+    }
+
+    /// Ids de las tareas cuya descripción o notas contienen todas las palabras
+    /// de `consulta` (como prefijo), de más a menos relevante
+    pub fn buscar_tareas(&self, consulta: &str) -> SqlResult<Vec<i32>> {
+        let terminos: Vec<String> = consulta
+            .split_whitespace()
+            .map(|t| format!("\"{}\"*", t.replace('"', "\"\"")))
+            .collect();
+        if terminos.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT rowid FROM tareas_fts WHERE tareas_fts MATCH ?1 ORDER BY rank")?;
+        let ids = stmt.query_map([terminos.join(" ")], |row| row.get(0))?;
+        ids.collect()
+    }
+}
@@ -0,0 +1,120 @@
+//! Base de datos cifrada con SQLCipher (característica `cifrado`).
+//!
+//! Un fichero cifrado no empieza por la cabecera de SQLite, así que se
+//! reconoce antes de abrirlo y se pide la clave. Las copias de seguridad de
+//! una base de datos cifrada se cifran con la misma clave.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, ErrorCode, OpenFlags, Result as SqlResult, params};
+
+use crate::copias::{
+    ErrorCopia, abrir_en_exclusiva, directorio_copias, listar_copias, validar_conexion,
+};
+use crate::{Db, concurrencia};
+
+/// Primeros bytes de toda base de datos SQLite sin cifrar
+const CABECERA_SQLITE: &[u8; 16] = b"SQLite format 3\0";
+
+/// Si `ruta` es una base de datos cifrada. Un fichero que no existe o está
+/// vacío no lo es: se creará sin cifrar.
+pub fn esta_cifrada(ruta: &Path) -> bool {
+    let mut cabecera = [0; 16];
+    fs::File::open(ruta)
+        .and_then(|mut fichero| fichero.read_exact(&mut cabecera))
+        .is_ok_and(|()| &cabecera != CABECERA_SQLITE)
+}
+
+/// Si el error al abrir una base de datos cifrada se debe a la clave
+pub fn clave_incorrecta(e: &rusqlite::Error) -> bool {
+    e.sqlite_error_code() == Some(ErrorCode::NotADatabase)
+}
+
+impl Db {
+    /// Abre una base de datos cifrada con `clave`, o la crea cifrada si no
+    /// existe
+    pub fn abrir_cifrada(path: &str, clave: &str) -> SqlResult<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "key", clave)?;
+        // Con otra clave, la primera lectura ya falla con `NotADatabase`
+        concurrencia::configurar(&conn)?;
+        let db = Self {
+            conn,
+            clave: Some(clave.to_string()),
+            ganchos: Default::default(),
+        };
+        db.init()?;
+        Ok(db)
+    }
+}
+
+/// Cifra con `clave` la base de datos sin cifrar de `ruta` y sus copias de
+/// seguridad, sustituyendo cada fichero por su versión cifrada
+pub fn cifrar(ruta: &Path, clave: &str) -> Result<(), ErrorCopia> {
+    for fichero in con_copias(ruta)? {
+        if !esta_cifrada(&fichero) {
+            recifrar(&fichero, None, Some(clave))?;
+        }
+    }
+    Ok(())
+}
+
+/// Descifra la base de datos de `ruta` y sus copias de seguridad
+pub fn descifrar(ruta: &Path, clave: &str) -> Result<(), ErrorCopia> {
+    for fichero in con_copias(ruta)? {
+        if esta_cifrada(&fichero) {
+            recifrar(&fichero, Some(clave), None)?;
+        }
+    }
+    Ok(())
+}
+
+// La base de datos seguida de sus copias automáticas
+fn con_copias(ruta: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut ficheros = vec![ruta.to_path_buf()];
+    match listar_copias(&directorio_copias(ruta)) {
+        Ok(copias) => ficheros.extend(copias),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    Ok(ficheros)
+}
+
+// Pone en lugar de `ruta` una copia suya con la clave `nueva` (o sin clave).
+// Se niega si otra instancia la tiene abierta: seguiría usando el fichero
+// sustituido, y lo que tuviera aún en el WAL se perdería.
+fn recifrar(ruta: &Path, actual: Option<&str>, nueva: Option<&str>) -> Result<(), ErrorCopia> {
+    let mut nombre = ruta.as_os_str().to_owned();
+    nombre.push(".cifrando");
+    let temporal = PathBuf::from(nombre);
+    let _ = fs::remove_file(&temporal);
+
+    let conn = abrir_en_exclusiva(ruta, actual)?;
+    if let Err(e) = exportar(&conn, &temporal, nueva) {
+        let _ = fs::remove_file(&temporal);
+        return Err(e);
+    }
+    // Con el bloqueo aún puesto, nadie puede escribir en el original entre
+    // la copia y la sustitución
+    fs::rename(&temporal, ruta)?;
+    Ok(())
+}
+
+// Exporta la base de datos de `conn` a `destino` con otra clave y valida el
+// resultado
+fn exportar(conn: &Connection, destino: &Path, nueva: Option<&str>) -> Result<(), ErrorCopia> {
+    conn.execute(
+        "ATTACH DATABASE ?1 AS destino KEY ?2",
+        params![destino.to_string_lossy(), nueva.unwrap_or("")],
+    )?;
+    conn.query_row("SELECT sqlcipher_export('destino')", [], |_| Ok(()))?;
+    conn.execute("DETACH DATABASE destino", [])?;
+
+    let copia = Connection::open_with_flags(destino, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    if let Some(clave) = nueva {
+        copia.pragma_update(None, "key", clave)?;
+    }
+    validar_conexion(&copia)
+}
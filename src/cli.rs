@@ -0,0 +1,367 @@
+//! Órdenes de línea de comandos que trabajan sobre la base de datos sin
+//! abrir ninguna interfaz.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use pixi::api::{DIRECCION_POR_DEFECTO, ServidorApi};
+use pixi::copias::ErrorCopia;
+use pixi::ganchos::Evento;
+use pixi::i18n::rellenar;
+use pixi::sincronizacion::ErrorSincronizacion;
+use pixi::{Db, Idioma};
+
+/// Abre la base de datos para una orden de terminal, o termina con un
+/// mensaje si no se puede. Si está cifrada, pide antes la clave.
+pub fn open_database(ruta_db: &str) -> Db {
+    #[cfg(feature = "cifrado")]
+    if pixi::cifrado::esta_cifrada(Path::new(ruta_db)) {
+        return encryption::unlock(ruta_db);
+    }
+    Db::new(ruta_db).unwrap_or_else(|e| {
+        let t = Idioma::detectar().textos();
+        eprintln!("{}", rellenar(t.error_abrir_db, &[&ruta_db, &e]));
+        std::process::exit(1);
+    })
+}
+
+/// Ejecuta `pixi <comando> <args…>` y devuelve el código de salida
+pub fn run(mut db: Db, comando: &str, args: &[String]) -> i32 {
+    let t = db.idioma().textos();
+    if comando == "sync" {
+        return sync(&db, args);
+    }
+    let resultado = match (comando, args) {
+        // Sin destino, una copia más en la carpeta de copias automáticas
+        ("backup", []) => backup(&db),
+        ("backup", [destino]) => db
+            .hacer_copia(Path::new(destino))
+            .map(|()| rellenar(t.copia_guardada, &[destino]))
+            .map_err(ErrorCopia::from),
+        ("restore", [origen]) => db
+            .restaurar_copia(Path::new(origen))
+            .map(|()| rellenar(t.copia_restaurada, &[origen])),
+        _ => {
+            eprintln!("{}", t.uso_restaurar);
+            return 2;
+        }
+    };
+    match resultado {
+        Ok(mensaje) => {
+            println!("{}", mensaje);
+            0
+        }
+        Err(ErrorCopia::EnUso) => {
+            eprintln!("{}", t.base_en_uso);
+            1
+        }
+        Err(e) => {
+            eprintln!("{}", rellenar(t.error_copia, &[&e]));
+            1
+        }
+    }
+}
+
+fn backup(db: &Db) -> Result<String, ErrorCopia> {
+    let t = db.idioma().textos();
+    let ruta = db.ruta().unwrap_or_default();
+    let conservar = db.cargar_ajustes()?.copias_conservar.max(1);
+    let copia = db.copia_rotativa(&pixi::copias::directorio_copias(&ruta), conservar as usize)?;
+    Ok(rellenar(t.copia_guardada, &[&copia.display()]))
+}
+
+/// `pixi sync [carpeta]`: con carpeta, la guarda como la de sincronización
+fn sync(db: &Db, args: &[String]) -> i32 {
+    let t = db.idioma().textos();
+    let resultado = match args {
+        [] => db.sincronizar(),
+        [carpeta] => db
+            .guardar_carpeta_sincronizacion(Path::new(carpeta))
+            .map_err(ErrorSincronizacion::from)
+            .and_then(|()| db.sincronizar()),
+        _ => Err(ErrorSincronizacion::SinCarpeta),
+    };
+    match resultado {
+        Ok(informe) => {
+            println!(
+                "{}",
+                rellenar(t.sincronizado, &[&informe.recibidos, &informe.enviados])
+            );
+            for c in &informe.conflictos {
+                println!(
+                    "{}",
+                    rellenar(t.conflicto_sync, &[&c.id, &c.conservada, &c.descartada])
+                );
+            }
+            0
+        }
+        Err(ErrorSincronizacion::SinCarpeta) => {
+            eprintln!("{}", t.sin_carpeta_sync);
+            2
+        }
+        Err(e) => {
+            eprintln!("{}", rellenar(t.error_sync, &[&e]));
+            1
+        }
+    }
+}
+
+/// Variable de entorno con el token de la API, en lugar del guardado
+const VAR_TOKEN: &str = "PIXI_TOKEN";
+
+/// Cada cuánto mira `pixi serve` si ha llegado una señal para parar
+const SIGNAL_POLL: Duration = Duration::from_millis(200);
+
+/// `pixi serve [dirección]`: atiende la API hasta Ctrl+C o SIGTERM
+pub fn serve(db: Db, args: &[String]) -> i32 {
+    let t = db.idioma().textos();
+    let direccion = match args {
+        [] => DIRECCION_POR_DEFECTO,
+        [direccion] => direccion.as_str(),
+        _ => {
+            eprintln!("{}", t.uso_serve);
+            return 2;
+        }
+    };
+    // Solo se muestra el token recién creado, y por la salida de error
+    // para que no acabe en el registro de quien guarde la salida
+    let token = match std::env::var(VAR_TOKEN) {
+        Ok(token) => Ok(token),
+        Err(_) => match db.token_api() {
+            Ok(Some(token)) => Ok(token),
+            Ok(None) => db.crear_token_api().inspect(|token| {
+                eprintln!("{}", rellenar(t.api_token_nuevo, &[token]));
+            }),
+            Err(e) => Err(std::io::Error::other(e)),
+        },
+    };
+    let token = match token {
+        Ok(token) => token,
+        Err(e) => {
+            eprintln!("{}", rellenar(t.error_servidor, &[&direccion, &e]));
+            return 1;
+        }
+    };
+    let mut servidor = match ServidorApi::new(db, direccion, &token) {
+        Ok(servidor) => servidor,
+        Err(e) => {
+            eprintln!("{}", rellenar(t.error_servidor, &[&direccion, &e]));
+            return 1;
+        }
+    };
+
+    // Con Ctrl+C o SIGTERM se deja de atender y se guardan los temporizadores
+    let parar = Arc::new(AtomicBool::new(false));
+    let aviso = Arc::clone(&parar);
+    // Si no se puede, la señal cierra el proceso como siempre
+    let _ = ctrlc::set_handler(move || aviso.store(true, Ordering::SeqCst));
+
+    let escuchando = servidor
+        .direccion()
+        .map_or(direccion.to_string(), |d| d.to_string());
+    println!("{}", rellenar(t.api_escuchando, &[&escuchando]));
+    while !parar.load(Ordering::SeqCst) && servidor.atender_durante(SIGNAL_POLL) {
+        for e in servidor.db().errores_ganchos() {
+            eprintln!("{}", e.describir(t));
+        }
+    }
+    servidor.pausar_temporizadores();
+    servidor.db().esperar_ganchos();
+    for e in servidor.db().errores_ganchos() {
+        eprintln!("{}", e.describir(t));
+    }
+    0
+}
+
+/// `pixi hook`: sin argumentos lista los ganchos; con un evento muestra el
+/// suyo y con una orden lo guarda (una orden vacía lo quita)
+pub fn hook(db: &Db, args: &[String]) -> i32 {
+    let t = db.idioma().textos();
+    let evento = args.first().map(|codigo| Evento::desde_codigo(codigo));
+    let resultado = match (evento, args) {
+        (None, _) => Evento::TODOS.into_iter().try_for_each(|evento| {
+            let orden = db.gancho(evento)?;
+            println!(
+                "{}: {}",
+                evento.codigo(),
+                orden.as_deref().unwrap_or(t.sin_gancho)
+            );
+            Ok(())
+        }),
+        (Some(Some(evento)), [_]) => db.gancho(evento).map(|orden| {
+            println!("{}", orden.as_deref().unwrap_or(t.sin_gancho));
+        }),
+        (Some(Some(evento)), [_, orden]) => db.guardar_gancho(evento, orden).map(|()| {
+            let mensaje = if orden.trim().is_empty() {
+                t.gancho_quitado
+            } else {
+                t.gancho_guardado
+            };
+            println!("{}", rellenar(mensaje, &[&evento.codigo()]));
+        }),
+        _ => {
+            eprintln!("{}", t.uso_hook);
+            return 2;
+        }
+    };
+    match resultado {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+/// `pixi encrypt` y `pixi decrypt`, y la clave pedida en la terminal
+#[cfg(feature = "cifrado")]
+pub mod encryption {
+    use std::io::{self, Write};
+    use std::path::Path;
+
+    use pixi::cifrado::{cifrar, clave_incorrecta, descifrar, esta_cifrada};
+    use pixi::copias::ErrorCopia;
+    use pixi::i18n::rellenar;
+    use pixi::{Db, Idioma, Textos};
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use ratatui::crossterm::terminal;
+
+    /// Variable de entorno con la clave, para usar `pixi` desde scripts
+    const VAR_CLAVE: &str = "PIXI_CLAVE";
+
+    /// Intentos de escribir la clave antes de desistir
+    const INTENTOS: usize = 3;
+
+    /// Cifra o descifra la base de datos y sus copias; devuelve el código
+    /// de salida
+    pub fn run(ruta_db: &str, comando: &str) -> i32 {
+        let t = Idioma::detectar().textos();
+        let ruta = Path::new(ruta_db);
+        let resultado = match comando {
+            "encrypt" if esta_cifrada(ruta) => Err(rellenar(t.ya_cifrada, &[&ruta_db])),
+            // Abrirla antes la crea o la pone al día si hace falta
+            "encrypt" => Db::new(ruta_db)
+                .map_err(|e| rellenar(t.error_abrir_db, &[&ruta_db, &e]))
+                .and_then(|db| {
+                    drop(db);
+                    new_passphrase(t)
+                })
+                .and_then(|clave| cifrar(ruta, &clave).map_err(|e| error(t, e)))
+                .map(|()| rellenar(t.cifrada_ok, &[&ruta_db])),
+            _ if !esta_cifrada(ruta) => Err(rellenar(t.no_cifrada, &[&ruta_db])),
+            _ => read_passphrase(t.pedir_clave)
+                .map_err(|e| e.to_string())
+                .and_then(|clave| descifrar(ruta, &clave).map_err(|e| error(t, e)))
+                .map(|()| rellenar(t.descifrada_ok, &[&ruta_db])),
+        };
+        match resultado {
+            Ok(mensaje) => {
+                println!("{}", mensaje);
+                0
+            }
+            Err(mensaje) => {
+                eprintln!("{}", mensaje);
+                1
+            }
+        }
+    }
+
+    fn error(t: &Textos, e: ErrorCopia) -> String {
+        match e {
+            ErrorCopia::Sql(e) if clave_incorrecta(&e) => t.clave_incorrecta.to_string(),
+            ErrorCopia::EnUso => t.base_en_uso.to_string(),
+            e => rellenar(t.error_cifrado, &[&e]),
+        }
+    }
+
+    /// Abre la base de datos cifrada pidiendo la clave; termina el proceso
+    /// si no se consigue
+    pub fn unlock(ruta_db: &str) -> Db {
+        let t = Idioma::detectar().textos();
+        eprintln!("{}", rellenar(t.base_cifrada, &[&ruta_db]));
+        // Una clave incorrecta en el entorno no mejora repitiéndola
+        let intentos = if std::env::var_os(VAR_CLAVE).is_some() {
+            1
+        } else {
+            INTENTOS
+        };
+        for _ in 0..intentos {
+            let clave = match read_passphrase(t.pedir_clave) {
+                Ok(clave) => clave,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            match Db::abrir_cifrada(ruta_db, &clave) {
+                Ok(db) => return db,
+                Err(e) if clave_incorrecta(&e) => eprintln!("{}", t.clave_incorrecta),
+                Err(e) => {
+                    eprintln!("{}", rellenar(t.error_abrir_db, &[&ruta_db, &e]));
+                    break;
+                }
+            }
+        }
+        std::process::exit(1);
+    }
+
+    // Pide una clave nueva dos veces
+    fn new_passphrase(t: &Textos) -> Result<String, String> {
+        let clave = read_passphrase(t.pedir_clave).map_err(|e| e.to_string())?;
+        if clave.is_empty() {
+            return Err(t.clave_vacia.to_string());
+        }
+        if std::env::var_os(VAR_CLAVE).is_none()
+            && read_passphrase(t.repetir_clave).map_err(|e| e.to_string())? != clave
+        {
+            return Err(t.claves_distintas.to_string());
+        }
+        Ok(clave)
+    }
+
+    /// Lee la clave de `PIXI_CLAVE` o, si no está, de la terminal sin
+    /// mostrarla. Con la entrada redirigida se lee una línea normal.
+    pub fn read_passphrase(pregunta: &str) -> io::Result<String> {
+        if let Ok(clave) = std::env::var(VAR_CLAVE) {
+            return Ok(clave);
+        }
+        eprint!("{}", pregunta);
+        io::stderr().flush()?;
+        if terminal::enable_raw_mode().is_err() {
+            let mut linea = String::new();
+            io::stdin().read_line(&mut linea)?;
+            return Ok(linea.trim_end_matches(['\r', '\n']).to_string());
+        }
+        let clave = read_hidden();
+        terminal::disable_raw_mode()?;
+        eprintln!();
+        clave
+    }
+
+    // Lee teclas hasta Intro sin mostrarlas; Esc o Ctrl-C cancelan
+    fn read_hidden() -> io::Result<String> {
+        let mut clave = String::new();
+        loop {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Enter => return Ok(clave),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Err(io::ErrorKind::Interrupted.into());
+                }
+                KeyCode::Esc => return Err(io::ErrorKind::Interrupted.into()),
+                KeyCode::Backspace => {
+                    clave.pop();
+                }
+                KeyCode::Char(c) => clave.push(c),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Varias instancias (ventanas, `pixi tui`, la línea de órdenes) pueden
+//! abrir la misma base de datos. SQLite la abre en modo WAL para que las
+//! lecturas no bloqueen las escrituras, y cada instancia detecta con
+//! `data_version` los cambios guardados por las demás.
+//!
+//! Las ediciones usan concurrencia optimista: cada tarea lleva una versión
+//! que sube con cada edición, y solo se guarda si nadie la ha subido desde
+//! que se cargó. El tiempo de los temporizadores se suma en lugar de
+//! sobrescribirse, así que dos instancias no se pisan las sesiones.
+//!
+//! Solo [`crate::ServicioTareas::editar`] comprueba la versión: es la única
+//! escritura que guarda a la vez varios campos leídos antes. Las demás
+//! (completar, poner el tiempo a cero, borrar, archivar) cambian un solo
+//! dato por una acción explícita, en la que gana la última, y no tocan los
+//! campos que guarda `editar`. La excepción es la regla de repetición, que
+//! al completar pasa a la siguiente ocurrencia; por eso ahí sube la versión.
+
+use std::time::Duration;
+
+use rusqlite::{Connection, OpenFlags, Result as SqlResult};
+
+use crate::Db;
+
+/// Cuánto espera una escritura a que otra instancia libere la base de datos
+pub const ESPERA_BLOQUEO: Duration = Duration::from_secs(5);
+
+/// Prepara una conexión recién abierta para compartirla con otras instancias
+pub(crate) fn configurar(conn: &Connection) -> SqlResult<()> {
+    conn.busy_timeout(ESPERA_BLOQUEO)?;
+    // En memoria no hay WAL posible; SQLite lo ignora y sigue en "memory"
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))
+}
+
+/// Conexión aparte que solo mira si la base de datos ha cambiado, para
+/// hacerlo desde otro hilo sin bloquear la conexión principal. Ve también
+/// los cambios de la propia instancia.
+pub struct VigilanteCambios {
+    conn: Connection,
+    version: i64,
+}
+
+impl VigilanteCambios {
+    /// `true` si se ha guardado algo desde la última llamada
+    pub fn hay_cambios(&mut self) -> SqlResult<bool> {
+        let version = self
+            .conn
+            .query_row("PRAGMA data_version", [], |row| row.get(0))?;
+        Ok(std::mem::replace(&mut self.version, version) != version)
+    }
+}
+
+impl Db {
+    /// Abre un [`VigilanteCambios`] sobre el mismo fichero; `None` si la
+    /// base de datos está en memoria y nadie más puede cambiarla
+    pub fn vigilar_cambios(&self) -> SqlResult<Option<VigilanteCambios>> {
+        let Some(ruta) = self.ruta() else {
+            return Ok(None);
+        };
+        let conn = self.abrir_otra(&ruta, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        conn.busy_timeout(ESPERA_BLOQUEO)?;
+        let version = conn.query_row("PRAGMA data_version", [], |row| row.get(0))?;
+        Ok(Some(VigilanteCambios { conn, version }))
+    }
+
+    /// Número que cambia cada vez que otra conexión guarda algo en la base
+    /// de datos. Los cambios propios no lo alteran.
+    pub fn version_datos(&self) -> SqlResult<i64> {
+        self.conn
+            .query_row("PRAGMA data_version", [], |row| row.get(0))
+    }
+
+    /// Número que crece con cada fila que guarda esta conexión. Lo lee sin
+    /// consultar la base de datos.
+    pub fn cambios_guardados(&self) -> u64 {
+        self.conn.total_changes()
+    }
+
+    /// Sube la versión de una tarea si sigue siendo `version`; devuelve
+    /// `false` si otra instancia la editó antes
+    pub fn reservar_edicion(&self, id: i32, version: i64) -> SqlResult<bool> {
+        let cambiadas = self.conn.execute(
+            "UPDATE tareas SET version = version + 1 WHERE id = ?1 AND version = ?2",
+            rusqlite::params![id, version],
+        )?;
+        Ok(cambiadas == 1)
+    }
+
+    /// Añade `segundos` al tiempo registrado de una tarea y devuelve el
+    /// total, que incluye lo que hayan sumado otras instancias
+    pub fn sumar_tiempo(&self, id: i32, segundos: i32) -> SqlResult<i32> {
+        self.conn.query_row(
+            "UPDATE tareas SET tiempo_acumulado = tiempo_acumulado + ?1 WHERE id = ?2
+             RETURNING tiempo_acumulado",
+            [segundos, id],
+            |row| row.get(0),
+        )
+    }
+}
@@ -0,0 +1,317 @@
+//! Copias de seguridad de la base de datos con la API de backup de SQLite,
+//! que obtiene una copia coherente aunque la base de datos esté en uso.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, ErrorCode, OpenFlags, Result as SqlResult};
+
+use crate::Db;
+use crate::concurrencia::{self, ESPERA_BLOQUEO};
+
+/// Las copias se llaman `tareas-AAAAMMDD-HHMMSS-mmm.db`; así se reconocen
+/// al rotarlas y el orden alfabético es el cronológico
+const PREFIJO: &str = "tareas-";
+const EXTENSION: &str = ".db";
+
+/// Páginas que copia cada paso de [`CopiaAutomatica::hacer`], y pausa entre
+/// pasos para no bloquear a quien esté usando la base de datos
+const PAGINAS_POR_PASO: i32 = 100;
+const PAUSA_ENTRE_PASOS: Duration = Duration::from_millis(250);
+
+#[derive(Debug)]
+pub enum ErrorCopia {
+    Sql(rusqlite::Error),
+    Io(io::Error),
+    /// `PRAGMA integrity_check` encontró problemas
+    Corrupta(String),
+    /// Es una base de datos SQLite, pero no de tareas
+    SinTareas,
+    /// Otra instancia tiene abierta la base de datos
+    EnUso,
+}
+
+impl fmt::Display for ErrorCopia {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorCopia::Sql(e) => write!(f, "{}", e),
+            ErrorCopia::Io(e) => write!(f, "{}", e),
+            ErrorCopia::Corrupta(detalle) => write!(f, "integrity check failed: {}", detalle),
+            ErrorCopia::SinTareas => write!(f, "not a task database"),
+            ErrorCopia::EnUso => write!(f, "the database is open in another instance"),
+        }
+    }
+}
+
+impl std::error::Error for ErrorCopia {}
+
+impl From<rusqlite::Error> for ErrorCopia {
+    fn from(e: rusqlite::Error) -> Self {
+        ErrorCopia::Sql(e)
+    }
+}
+
+impl From<io::Error> for ErrorCopia {
+    fn from(e: io::Error) -> Self {
+        ErrorCopia::Io(e)
+    }
+}
+
+/// Carpeta de las copias automáticas: `copias` junto a la base de datos
+pub fn directorio_copias(ruta_db: &Path) -> PathBuf {
+    ruta_db.parent().unwrap_or(Path::new("")).join("copias")
+}
+
+/// Copias de `directorio`, de la más reciente a la más antigua
+pub fn listar_copias(directorio: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut copias: Vec<PathBuf> = fs::read_dir(directorio)?
+        .filter_map(|entrada| entrada.ok())
+        .map(|entrada| entrada.path())
+        .filter(|ruta| {
+            ruta.file_name()
+                .and_then(|nombre| nombre.to_str())
+                .is_some_and(|nombre| nombre.starts_with(PREFIJO) && nombre.ends_with(EXTENSION))
+        })
+        .collect();
+    copias.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(copias)
+}
+
+/// Comprueba, sin modificarla, que `ruta` es una base de datos SQLite
+/// íntegra y con la tabla de tareas
+pub fn validar_copia(ruta: &Path) -> Result<(), ErrorCopia> {
+    existe(ruta)?;
+    validar_conexion(&Connection::open_with_flags(
+        ruta,
+        OpenFlags::SQLITE_OPEN_READ_ONLY,
+    )?)
+}
+
+fn existe(ruta: &Path) -> io::Result<()> {
+    if !ruta.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            ruta.display().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Validación de [`validar_copia`] sobre una copia ya abierta
+pub(crate) fn validar_conexion(conn: &Connection) -> Result<(), ErrorCopia> {
+    let resultado: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if resultado != "ok" {
+        return Err(ErrorCopia::Corrupta(resultado));
+    }
+    let columnas: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('tareas') WHERE name IN ('id', 'descripcion')",
+        [],
+        |row| row.get(0),
+    )?;
+    if columnas != 2 {
+        return Err(ErrorCopia::SinTareas);
+    }
+    Ok(())
+}
+
+// Abre `ruta` con un bloqueo exclusivo que se mantiene hasta cerrarla, y con
+// todo el WAL ya volcado en el fichero. Si otra conexión la tiene abierta,
+// falla enseguida con `ErrorCopia::EnUso` en lugar de esperarla.
+pub(crate) fn abrir_en_exclusiva(
+    ruta: &Path,
+    clave: Option<&str>,
+) -> Result<Connection, ErrorCopia> {
+    let conn = Connection::open(ruta)?;
+    if let Some(clave) = clave {
+        conn.pragma_update(None, "key", clave)?;
+    }
+    conn.busy_timeout(Duration::ZERO)?;
+    conn.pragma_update(None, "locking_mode", "EXCLUSIVE")?;
+    let en_uso = |e: rusqlite::Error| match e.sqlite_error_code() {
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => ErrorCopia::EnUso,
+        _ => ErrorCopia::Sql(e),
+    };
+    conn.execute_batch("BEGIN EXCLUSIVE; COMMIT;")
+        .map_err(en_uso)?;
+    let bloqueado: i32 = conn
+        .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))
+        .map_err(en_uso)?;
+    if bloqueado != 0 {
+        return Err(ErrorCopia::EnUso);
+    }
+    Ok(conn)
+}
+
+// Ruta para una copia nueva en `directorio`, creándolo si hace falta
+fn nueva_copia(directorio: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(directorio)?;
+    let fecha = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f");
+    Ok(directorio.join(format!("{}{}{}", PREFIJO, fecha, EXTENSION)))
+}
+
+impl Db {
+    /// Fichero de la base de datos; `None` si está en memoria
+    pub fn ruta(&self) -> Option<PathBuf> {
+        self.conn
+            .path()
+            .filter(|ruta| !ruta.is_empty())
+            .map(PathBuf::from)
+    }
+
+    // Abre otro fichero con la misma clave que esta base de datos, si la tiene
+    pub(crate) fn abrir_otra(&self, ruta: &Path, flags: OpenFlags) -> SqlResult<Connection> {
+        let conn = Connection::open_with_flags(ruta, flags)?;
+        #[cfg(feature = "cifrado")]
+        if let Some(clave) = &self.clave {
+            conn.pragma_update(None, "key", clave)?;
+        }
+        Ok(conn)
+    }
+
+    /// Copia la base de datos entera a `destino`, cifrada con la misma
+    /// clave si la base de datos lo está. Se copia de una vez; para no
+    /// esperar, ver [`Db::preparar_copia_automatica`].
+    pub fn hacer_copia(&self, destino: &Path) -> SqlResult<()> {
+        let mut copia = self.abrir_otra(destino, OpenFlags::default())?;
+        copiar_de_una_vez(&self.conn, &mut copia)
+    }
+
+    /// Guarda una copia con fecha en `directorio` y borra las más antiguas
+    /// hasta dejar `conservar`. Devuelve la ruta de la copia nueva.
+    pub fn copia_rotativa(
+        &self,
+        directorio: &Path,
+        conservar: usize,
+    ) -> Result<PathBuf, ErrorCopia> {
+        let destino = nueva_copia(directorio)?;
+        self.hacer_copia(&destino)?;
+        rotar(directorio, conservar)?;
+        Ok(destino)
+    }
+
+    /// Copia rotativa según los ajustes, para hacerla al abrir, al salir y
+    /// cada cierto tiempo. `None` si están desactivadas o la base de datos
+    /// está en memoria.
+    pub fn copia_automatica(&self) -> Result<Option<PathBuf>, ErrorCopia> {
+        let conservar = self.cargar_ajustes()?.copias_conservar;
+        let Some(ruta) = self.ruta().filter(|_| conservar > 0) else {
+            return Ok(None);
+        };
+        self.copia_rotativa(&directorio_copias(&ruta), conservar as usize)
+            .map(Some)
+    }
+
+    /// Como [`Db::copia_automatica`], pero solo abre la copia; se hace con
+    /// [`CopiaAutomatica::hacer`], que puede llamarse en otro hilo para no
+    /// detener la interfaz
+    pub fn preparar_copia_automatica(&self) -> Result<Option<CopiaAutomatica>, ErrorCopia> {
+        let conservar = self.cargar_ajustes()?.copias_conservar;
+        let Some(ruta) = self.ruta().filter(|_| conservar > 0) else {
+            return Ok(None);
+        };
+        let origen = self.abrir_otra(&ruta, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        origen.busy_timeout(ESPERA_BLOQUEO)?;
+        let directorio = directorio_copias(&ruta);
+        let ruta = nueva_copia(&directorio)?;
+        let destino = self.abrir_otra(&ruta, OpenFlags::default())?;
+        Ok(Some(CopiaAutomatica {
+            origen,
+            destino,
+            ruta,
+            directorio,
+            conservar: conservar as usize,
+        }))
+    }
+
+    /// Sustituye el contenido de la base de datos por el de la copia
+    /// `origen`. Antes la valida y guarda una copia de lo que se sustituye,
+    /// que no cuenta para la rotación hasta la próxima copia automática.
+    ///
+    /// Se niega con [`ErrorCopia::EnUso`] si otra instancia tiene abierta la
+    /// base de datos: seguiría con las tareas de antes y podría guardarlas
+    /// encima de las restauradas.
+    pub fn restaurar_copia(&mut self, origen: &Path) -> Result<(), ErrorCopia> {
+        existe(origen)?;
+        let copia = self.abrir_otra(origen, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        validar_conexion(&copia)?;
+        match self.ruta() {
+            Some(ruta) => {
+                // Con la conexión propia cerrada, solo quedan las de otras
+                let propia = std::mem::replace(&mut self.conn, Connection::open_in_memory()?);
+                propia.close().map_err(|(_, e)| e)?;
+                let restaurada = self.restaurar_en_exclusiva(&ruta, &copia);
+                let conn = self.abrir_otra(&ruta, OpenFlags::default())?;
+                concurrencia::configurar(&conn)?;
+                self.conn = conn;
+                restaurada?;
+            }
+            None => copiar_de_una_vez(&copia, &mut self.conn)?,
+        }
+        // Una copia de una versión anterior puede no tener todas las columnas
+        self.init()?;
+        Ok(())
+    }
+
+    // Copia lo que hay en `ruta` a la carpeta de copias y lo sustituye por
+    // `copia`, todo con el bloqueo exclusivo puesto
+    fn restaurar_en_exclusiva(&self, ruta: &Path, copia: &Connection) -> Result<(), ErrorCopia> {
+        #[cfg(feature = "cifrado")]
+        let clave = self.clave.as_deref();
+        #[cfg(not(feature = "cifrado"))]
+        let clave = None;
+        let mut conn = abrir_en_exclusiva(ruta, clave)?;
+        let mut previa = self.abrir_otra(
+            &nueva_copia(&directorio_copias(ruta))?,
+            OpenFlags::default(),
+        )?;
+        copiar_de_una_vez(&conn, &mut previa)?;
+        copiar_de_una_vez(copia, &mut conn)?;
+        Ok(())
+    }
+}
+
+/// Copia automática con sus propias conexiones; ver
+/// [`Db::preparar_copia_automatica`]
+pub struct CopiaAutomatica {
+    origen: Connection,
+    destino: Connection,
+    ruta: PathBuf,
+    directorio: PathBuf,
+    conservar: usize,
+}
+
+impl CopiaAutomatica {
+    /// Copia por pasos, con pausas para que la base de datos siga
+    /// disponible, y borra las copias más antiguas. Devuelve la ruta de la
+    /// copia nueva.
+    pub fn hacer(mut self) -> Result<PathBuf, ErrorCopia> {
+        Backup::new(&self.origen, &mut self.destino)?.run_to_completion(
+            PAGINAS_POR_PASO,
+            PAUSA_ENTRE_PASOS,
+            None,
+        )?;
+        rotar(&self.directorio, self.conservar)?;
+        Ok(self.ruta)
+    }
+}
+
+// Copia todas las páginas en un paso; solo se hace pausa para reintentarlo
+// si la base de datos está ocupada
+fn copiar_de_una_vez(origen: &Connection, destino: &mut Connection) -> SqlResult<()> {
+    Backup::new(origen, destino)?.run_to_completion(i32::MAX, PAUSA_ENTRE_PASOS, None)
+}
+
+// Borra las copias de `directorio` más antiguas hasta dejar `conservar`
+fn rotar(directorio: &Path, conservar: usize) -> io::Result<()> {
+    for antigua in listar_copias(directorio)?
+        .into_iter()
+        .skip(conservar.max(1))
+    {
+        fs::remove_file(antigua)?;
+    }
+    Ok(())
+}
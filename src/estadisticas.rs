@@ -0,0 +1,58 @@
+use rusqlite::Result as SqlResult;
+
+use crate::{Db, TodoItem};
+
+/// Totales sobre todas las tareas guardadas, estén cargadas en memoria o no,
+/// salvo las que están en la papelera. Las archivadas ya no cuentan como
+/// tareas, pero su tiempo sí.
+#[derive(Clone, Copy, Default)]
+pub struct Totales {
+    /// Tareas de la lista, sin las archivadas
+    pub tareas: i32,
+    /// De esas, las completadas
+    pub completadas: i32,
+    /// Tiempo registrado en todas, también en las archivadas
+    pub segundos: i32,
+    /// Tiempo registrado en las tareas completadas que tenían estimación
+    pub real: i32,
+    /// Suma de las estimaciones de esas mismas tareas
+    pub estimado: i32,
+}
+
+impl Totales {
+    /// Suma a los totales guardados las sesiones aún en curso de `tareas`
+    pub fn con_sesiones(mut self, tareas: &[TodoItem]) -> Self {
+        for tarea in tareas {
+            let sesion = tarea.tiempo_sesion();
+            self.segundos += sesion;
+            if tarea.checked && tarea.estimacion.is_some() {
+                self.real += sesion;
+            }
+        }
+        self
+    }
+}
+
+impl Db {
+    pub fn totales(&self) -> SqlResult<Totales> {
+        self.conn.query_row(
+            "SELECT COALESCE(SUM(archivada_en IS NULL), 0),
+                COALESCE(SUM(completada != 0 AND archivada_en IS NULL), 0),
+                COALESCE(SUM(tiempo_acumulado), 0),
+                COALESCE(SUM(CASE WHEN completada AND tiempo_estimado IS NOT NULL
+                    THEN tiempo_acumulado END), 0),
+                COALESCE(SUM(CASE WHEN completada THEN tiempo_estimado END), 0)
+             FROM tareas WHERE eliminada_en IS NULL",
+            [],
+            |row| {
+                Ok(Totales {
+                    tareas: row.get(0)?,
+                    completadas: row.get(1)?,
+                    segundos: row.get(2)?,
+                    real: row.get(3)?,
+                    estimado: row.get(4)?,
+                })
+            },
+        )
+    }
+}
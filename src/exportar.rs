@@ -0,0 +1,34 @@
+use serde_json::{Value, json};
+
+use crate::{TodoItem, formatear_duracion};
+
+/// Lista de tareas como checklist de Markdown, con el tiempo registrado
+pub fn a_markdown(tareas: &[TodoItem]) -> String {
+    let mut salida = String::from("# Tareas\n\n");
+    for tarea in tareas {
+        salida.push_str(&format!(
+            "- [{}] {} ({})\n",
+            if tarea.checked { "x" } else { " " },
+            tarea.text,
+            formatear_duracion(tarea.tiempo_total())
+        ));
+    }
+    salida
+}
+
+/// Una tarea como objeto JSON. `tiempo` va en segundos e incluye la sesión
+/// en curso; la prioridad, como el entero guardado en la base de datos.
+pub fn a_json(tarea: &TodoItem) -> Value {
+    json!({
+        "id": tarea.id,
+        "descripcion": tarea.text,
+        "notas": tarea.notas,
+        "completada": tarea.checked,
+        "prioridad": tarea.prioridad as i32,
+        "estimacion": tarea.estimacion,
+        "recurrencia": tarea.recurrencia.map(|r| r.codificar()),
+        "fecha_proxima": tarea.fecha_proxima.map(|f| f.to_string()),
+        "tiempo": tarea.tiempo_total(),
+        "temporizador_activo": tarea.temporizador_activo(),
+    })
+}
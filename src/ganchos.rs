@@ -0,0 +1,331 @@
+//! Ganchos: órdenes del usuario que se ejecutan al crear, completar o
+//! borrar una tarea y al iniciar o parar su temporizador, por ejemplo para
+//! llevar un diario o avisar en un chat.
+//!
+//! Cada gancho es una línea de shell guardada en los ajustes. Recibe la
+//! tarea como JSON (ver [`crate::exportar::a_json`]) por la entrada
+//! estándar, su id en `PIXI_TAREA_ID` y el evento en `PIXI_EVENTO`. Se
+//! ejecutan de uno en uno y en orden en un hilo aparte, para no frenar la
+//! interfaz, y los que pasan de [`crate::Ajustes::limite_ganchos`] se
+//! matan. Los fallos se recogen con [`Db::errores_ganchos`].
+//!
+//! Dentro de una [`Db::transaccion`] los ganchos esperan a que se confirme,
+//! y si se deshace no se ejecutan.
+
+use std::cell::{Cell, RefCell};
+use std::io::{self, Read, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rusqlite::{OptionalExtension, Result as SqlResult};
+
+use crate::exportar::a_json;
+use crate::i18n::rellenar;
+use crate::{Db, Textos, TodoItem};
+
+const PREFIJO_CLAVE: &str = "gancho:";
+
+/// Cada cuánto se mira si un gancho ha terminado
+const SONDEO: Duration = Duration::from_millis(10);
+
+/// Bytes de la salida de error de un gancho que se guardan al fallar
+const MAX_SALIDA_ERROR: usize = 2000;
+
+/// Cuánto se espera, después de que un gancho falle, a que se cierre su
+/// salida de error; algún proceso que haya lanzado podría tenerla abierta
+const ESPERA_SALIDA_ERROR: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Evento {
+    Agregada,
+    Completada,
+    Eliminada,
+    TemporizadorIniciado,
+    TemporizadorParado,
+}
+
+impl Evento {
+    pub const TODOS: [Evento; 5] = [
+        Evento::Agregada,
+        Evento::Completada,
+        Evento::Eliminada,
+        Evento::TemporizadorIniciado,
+        Evento::TemporizadorParado,
+    ];
+
+    /// Nombre en `PIXI_EVENTO` y en `pixi hook`
+    pub fn codigo(self) -> &'static str {
+        match self {
+            Evento::Agregada => "add",
+            Evento::Completada => "complete",
+            Evento::Eliminada => "delete",
+            Evento::TemporizadorIniciado => "timer_start",
+            Evento::TemporizadorParado => "timer_stop",
+        }
+    }
+
+    pub fn desde_codigo(codigo: &str) -> Option<Self> {
+        Self::TODOS.into_iter().find(|e| e.codigo() == codigo)
+    }
+}
+
+#[derive(Debug)]
+pub enum FalloGancho {
+    /// No se pudo lanzar la orden
+    Lanzar(io::Error),
+    /// Terminó con error; se guarda el principio de su salida de error
+    Salida(ExitStatus, String),
+    /// Se mató al pasar del límite de tiempo
+    Limite(Duration),
+}
+
+impl FalloGancho {
+    pub fn describir(&self, t: &Textos) -> String {
+        match self {
+            FalloGancho::Lanzar(e) => e.to_string(),
+            FalloGancho::Salida(estado, salida) if salida.is_empty() => estado.to_string(),
+            FalloGancho::Salida(estado, salida) => format!("{}: {}", estado, salida),
+            FalloGancho::Limite(limite) => rellenar(t.gancho_limite, &[&limite.as_secs()]),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ErrorGancho {
+    pub evento: Evento,
+    pub tarea: i32,
+    pub fallo: FalloGancho,
+}
+
+impl ErrorGancho {
+    pub fn describir(&self, t: &Textos) -> String {
+        rellenar(
+            t.error_gancho,
+            &[&self.evento.codigo(), &self.tarea, &self.fallo.describir(t)],
+        )
+    }
+}
+
+// Un gancho por ejecutar, con todo lo que necesita
+struct Disparo {
+    evento: Evento,
+    tarea: i32,
+    json: String,
+    orden: String,
+    limite: Duration,
+}
+
+// Hilo que ejecuta los ganchos en orden
+struct Ejecutor {
+    envio: Sender<Disparo>,
+    hilo: JoinHandle<()>,
+}
+
+/// Estado de los ganchos de una [`Db`]
+#[derive(Default)]
+pub(crate) struct Ganchos {
+    /// Transacciones abiertas unas dentro de otras
+    profundidad: Cell<usize>,
+    /// Disparados dentro de una transacción aún sin confirmar
+    pendientes: RefCell<Vec<Disparo>>,
+    ejecutor: RefCell<Option<Ejecutor>>,
+    errores: Arc<Mutex<Vec<ErrorGancho>>>,
+}
+
+impl Ganchos {
+    /// Al abrir una transacción; devuelve la marca para [`Ganchos::salir`]
+    pub(crate) fn entrar(&self) -> usize {
+        self.profundidad.set(self.profundidad.get() + 1);
+        self.pendientes.borrow().len()
+    }
+
+    /// Al cerrar la transacción abierta con la marca `marca`. Si se ha
+    /// deshecho se olvidan sus ganchos; si era la de fuera, se ejecutan.
+    pub(crate) fn salir(&self, marca: usize, confirmada: bool) {
+        self.profundidad.set(self.profundidad.get() - 1);
+        if !confirmada {
+            self.pendientes.borrow_mut().truncate(marca);
+        } else if self.profundidad.get() == 0 {
+            let pendientes = std::mem::take(&mut *self.pendientes.borrow_mut());
+            pendientes.into_iter().for_each(|d| self.enviar(d));
+        }
+    }
+
+    fn disparar(&self, disparo: Disparo) {
+        if self.profundidad.get() > 0 {
+            self.pendientes.borrow_mut().push(disparo);
+        } else {
+            self.enviar(disparo);
+        }
+    }
+
+    fn enviar(&self, disparo: Disparo) {
+        let mut ejecutor = self.ejecutor.borrow_mut();
+        let ejecutor = ejecutor.get_or_insert_with(|| {
+            let (envio, recepcion) = mpsc::channel::<Disparo>();
+            let errores = Arc::clone(&self.errores);
+            let hilo = thread::spawn(move || {
+                for disparo in recepcion {
+                    if let Err(fallo) = ejecutar(&disparo) {
+                        errores.lock().unwrap().push(ErrorGancho {
+                            evento: disparo.evento,
+                            tarea: disparo.tarea,
+                            fallo,
+                        });
+                    }
+                }
+            });
+            Ejecutor { envio, hilo }
+        });
+        // El hilo solo termina cuando se cierra el canal
+        let _ = ejecutor.envio.send(disparo);
+    }
+
+    fn esperar(&self) {
+        if let Some(Ejecutor { envio, hilo }) = self.ejecutor.borrow_mut().take() {
+            drop(envio);
+            let _ = hilo.join();
+        }
+    }
+}
+
+impl Drop for Ganchos {
+    // Que no se pierdan los ganchos de lo último que se hizo al salir
+    fn drop(&mut self) {
+        self.esperar();
+    }
+}
+
+impl Db {
+    /// Orden del gancho de `evento`, si hay
+    pub fn gancho(&self, evento: Evento) -> SqlResult<Option<String>> {
+        Ok(self
+            .obtener_ajuste(&format!("{}{}", PREFIJO_CLAVE, evento.codigo()))?
+            .filter(|orden| !orden.trim().is_empty()))
+    }
+
+    /// Guarda el gancho de `evento`; una orden vacía lo quita
+    pub fn guardar_gancho(&self, evento: Evento, orden: &str) -> SqlResult<()> {
+        self.guardar_ajuste(
+            &format!("{}{}", PREFIJO_CLAVE, evento.codigo()),
+            orden.trim(),
+        )
+    }
+
+    /// Fallos de los ganchos desde la última llamada
+    pub fn errores_ganchos(&self) -> Vec<ErrorGancho> {
+        std::mem::take(&mut *self.ganchos.errores.lock().unwrap())
+    }
+
+    /// Espera a que terminen los ganchos ya lanzados
+    pub fn esperar_ganchos(&self) {
+        self.ganchos.esperar();
+    }
+
+    /// Lanza el gancho de `evento` con la tarea `id` tal como está guardada
+    pub(crate) fn disparar_gancho(&self, evento: Evento, id: i32) -> SqlResult<()> {
+        if self.gancho(evento)?.is_none() {
+            return Ok(());
+        }
+        match self.cargar_tarea(id).optional()? {
+            Some(tarea) => self.disparar_gancho_con(evento, &tarea),
+            None => Ok(()),
+        }
+    }
+
+    /// Lanza el gancho de `evento` con `tarea` tal como está en memoria
+    pub(crate) fn disparar_gancho_con(&self, evento: Evento, tarea: &TodoItem) -> SqlResult<()> {
+        let Some(orden) = self.gancho(evento)? else {
+            return Ok(());
+        };
+        let limite = self.cargar_ajustes()?.limite_ganchos;
+        self.ganchos.disparar(Disparo {
+            evento,
+            tarea: tarea.id,
+            json: a_json(tarea).to_string(),
+            orden,
+            limite: Duration::from_secs(u64::from(limite)),
+        });
+        Ok(())
+    }
+}
+
+fn ejecutar(disparo: &Disparo) -> Result<(), FalloGancho> {
+    let mut hijo = shell(&disparo.orden)
+        .env("PIXI_EVENTO", disparo.evento.codigo())
+        .env("PIXI_TAREA_ID", disparo.tarea.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(FalloGancho::Lanzar)?;
+
+    // Las dos tuberías, cada una en su hilo: si el gancho no lee la entrada
+    // o llena la salida de error, aquí se sigue contando el tiempo hasta
+    // matarlo
+    let (enviar_salida, salida_error) = mpsc::channel();
+    if let Some(mut stderr) = hijo.stderr.take() {
+        thread::spawn(move || {
+            let mut salida = Vec::new();
+            let _ = stderr.read_to_end(&mut salida);
+            let _ = enviar_salida.send(salida);
+        });
+    }
+    if let Some(mut entrada) = hijo.stdin.take() {
+        let json = disparo.json.clone();
+        // Si la orden no lee la entrada estándar no es un error
+        thread::spawn(move || {
+            let _ = entrada.write_all(json.as_bytes());
+        });
+    }
+
+    let inicio = Instant::now();
+    let estado = loop {
+        if let Some(estado) = hijo.try_wait().map_err(FalloGancho::Lanzar)? {
+            break estado;
+        }
+        if inicio.elapsed() >= disparo.limite {
+            let _ = hijo.kill();
+            let _ = hijo.wait();
+            // Sin esperar a la salida de error: algún proceso que haya
+            // lanzado el gancho podría tenerla aún abierta
+            return Err(FalloGancho::Limite(disparo.limite));
+        }
+        thread::sleep(SONDEO);
+    };
+    if estado.success() {
+        return Ok(());
+    }
+    // Si no se cierra a tiempo, el hilo que la lee se queda atrás y se
+    // informa sin ella
+    let salida = salida_error
+        .recv_timeout(ESPERA_SALIDA_ERROR)
+        .unwrap_or_default();
+    let mut salida = String::from_utf8_lossy(&salida).trim().to_string();
+    if salida.len() > MAX_SALIDA_ERROR {
+        let corte = (0..=MAX_SALIDA_ERROR)
+            .rev()
+            .find(|&i| salida.is_char_boundary(i))
+            .unwrap_or(0);
+        salida.truncate(corte);
+        salida.push('…');
+    }
+    Err(FalloGancho::Salida(estado, salida))
+}
+
+#[cfg(unix)]
+fn shell(orden: &str) -> Command {
+    let mut comando = Command::new("sh");
+    comando.arg("-c").arg(orden);
+    comando
+}
+
+#[cfg(windows)]
+fn shell(orden: &str) -> Command {
+    let mut comando = Command::new("cmd");
+    comando.arg("/C").arg(orden);
+    comando
+}
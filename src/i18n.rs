@@ -0,0 +1,551 @@
+//! Textos de la interfaz en español e inglés.
+//!
+//! Cada idioma es una instancia de [`Textos`], así que olvidar una
+//! traducción es un error de compilación. Los textos con `{}` se
+//! completan con [`rellenar`].
+
+use std::fmt::Display;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Idioma {
+    Es,
+    En,
+}
+
+impl Idioma {
+    pub const TODOS: [Idioma; 2] = [Idioma::Es, Idioma::En];
+
+    pub fn codigo(self) -> &'static str {
+        match self {
+            Idioma::Es => "es",
+            Idioma::En => "en",
+        }
+    }
+
+    pub fn desde_codigo(codigo: &str) -> Option<Self> {
+        Self::TODOS.into_iter().find(|i| i.codigo() == codigo)
+    }
+
+    /// Nombre del idioma en ese mismo idioma, para el selector
+    pub fn nombre(self) -> &'static str {
+        match self {
+            Idioma::Es => "Español",
+            Idioma::En => "English",
+        }
+    }
+
+    /// Idioma de las variables de entorno (`LC_ALL`, `LC_MESSAGES`, `LANG`);
+    /// inglés si no se reconoce
+    pub fn detectar() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|valor| !valor.is_empty())
+            .and_then(|valor| {
+                let codigo = valor.split(['_', '.', '@']).next().unwrap_or_default();
+                Self::desde_codigo(&codigo.to_lowercase())
+            })
+            .unwrap_or(Idioma::En)
+    }
+
+    pub fn textos(self) -> &'static Textos {
+        match self {
+            Idioma::Es => &ES,
+            Idioma::En => &EN,
+        }
+    }
+}
+
+/// Sustituye cada `{}` de la plantilla por el siguiente argumento
+pub fn rellenar(plantilla: &str, args: &[&dyn Display]) -> String {
+    let mut salida = String::with_capacity(plantilla.len());
+    let mut args = args.iter();
+    let mut partes = plantilla.split("{}").peekable();
+    while let Some(parte) = partes.next() {
+        salida.push_str(parte);
+        if partes.peek().is_some()
+            && let Some(arg) = args.next()
+        {
+            salida.push_str(&arg.to_string());
+        }
+    }
+    salida
+}
+
+pub struct Textos {
+    pub titulo: &'static str,
+    pub idioma: &'static str,
+    pub atajos_teclado: &'static str,
+    pub nueva_tarea: &'static str,
+    pub agregar: &'static str,
+    pub tiempo_estimado_ayuda: &'static str,
+    pub minutos_sufijo: &'static str,
+    pub notas_ayuda: &'static str,
+    pub sin_notas: &'static str,
+    pub exportado: &'static str,
+    pub error_exportar: &'static str,
+    pub dia_prefijo: &'static str,
+    pub cada_prefijo: &'static str,
+    pub dias_sufijo: &'static str,
+    pub orden: &'static str,
+    pub orden_manual: &'static str,
+    pub orden_prioridad: &'static str,
+    pub prioridad_minima: &'static str,
+    pub buscar_ayuda: &'static str,
+    pub total: &'static str,
+    pub completadas: &'static str,
+    pub pendientes: &'static str,
+    pub tiempo_total: &'static str,
+    pub real_vs_estimado: &'static str,
+    pub real_vs_estimado_sin_datos: &'static str,
+    pub recargar: &'static str,
+    pub limpiar_completadas: &'static str,
+    pub archivadas: &'static str,
+    pub archivo_titulo: &'static str,
+    pub archivo_vacio: &'static str,
+    pub restaurar: &'static str,
+    pub seleccionadas: &'static str,
+    pub lote_completar: &'static str,
+    pub lote_desmarcar: &'static str,
+    pub lote_archivar: &'static str,
+    pub lote_reiniciar: &'static str,
+    pub quitar_seleccion: &'static str,
+    pub confirmar_eliminar_varias: &'static str,
+    pub papelera_titulo: &'static str,
+    pub papelera_vacia: &'static str,
+    pub eliminar_definitivamente: &'static str,
+    pub vaciar_papelera: &'static str,
+    pub dias_papelera: &'static str,
+    pub dias_papelera_ayuda: &'static str,
+    pub copias_conservar: &'static str,
+    pub copias_conservar_ayuda: &'static str,
+    pub intervalo_copias: &'static str,
+    pub intervalo_copias_ayuda: &'static str,
+    pub copia_guardada: &'static str,
+    pub copia_restaurada: &'static str,
+    pub error_copia: &'static str,
+    pub uso_restaurar: &'static str,
+    pub conflicto_edicion: &'static str,
+    pub pedir_clave: &'static str,
+    pub repetir_clave: &'static str,
+    pub claves_distintas: &'static str,
+    pub clave_vacia: &'static str,
+    pub clave_incorrecta: &'static str,
+    pub desbloquear: &'static str,
+    pub base_cifrada: &'static str,
+    pub ya_cifrada: &'static str,
+    pub no_cifrada: &'static str,
+    pub cifrada_ok: &'static str,
+    pub descifrada_ok: &'static str,
+    pub error_cifrado: &'static str,
+    pub base_en_uso: &'static str,
+    pub carpeta_sync: &'static str,
+    pub carpeta_sync_ayuda: &'static str,
+    pub sincronizar: &'static str,
+    pub sincronizado: &'static str,
+    pub conflicto_sync: &'static str,
+    pub error_sync: &'static str,
+    pub sin_carpeta_sync: &'static str,
+    pub api_escuchando: &'static str,
+    pub api_token_nuevo: &'static str,
+    pub error_servidor: &'static str,
+    pub uso_serve: &'static str,
+    pub limite_ganchos: &'static str,
+    pub limite_ganchos_ayuda: &'static str,
+    pub error_gancho: &'static str,
+    pub gancho_limite: &'static str,
+    pub gancho_guardado: &'static str,
+    pub gancho_quitado: &'static str,
+    pub sin_gancho: &'static str,
+    pub uso_hook: &'static str,
+    pub meta_diaria: &'static str,
+    pub tareas_sufijo: &'static str,
+    pub racha: &'static str,
+    pub meta_semanal: &'static str,
+    pub racha_semanas: &'static str,
+    pub paleta_exportar: &'static str,
+    pub paleta_tema: &'static str,
+    pub paleta_orden_manual: &'static str,
+    pub paleta_orden_prioridad: &'static str,
+    pub paleta_limpiar_busqueda: &'static str,
+    pub paleta_agregar: &'static str,
+    pub paleta_ayuda: &'static str,
+    pub ayuda_titulo: &'static str,
+    pub pulsa_tecla: &'static str,
+    pub cambiar: &'static str,
+    pub cerrar_ayuda: &'static str,
+    pub restablecer_atajos: &'static str,
+    pub ajustes_titulo: &'static str,
+    pub tema: &'static str,
+    pub tamano_letra: &'static str,
+    pub color_temporizador: &'static str,
+    pub color_arrastre: &'static str,
+    pub confirmar_borrado: &'static str,
+    pub base_datos: &'static str,
+    pub abrir: &'static str,
+    pub error_abrir_db: &'static str,
+    pub error_guardar_config: &'static str,
+    pub restablecer_ajustes: &'static str,
+    pub confirmar_eliminar: &'static str,
+    pub eliminar: &'static str,
+    pub cancelar: &'static str,
+    pub editar_tarea: &'static str,
+    /// Teclas de la interfaz de terminal, en la barra inferior
+    pub tui_ayuda: &'static str,
+    pub tui_confirmar: &'static str,
+    pub accion_siguiente: &'static str,
+    pub accion_anterior: &'static str,
+    pub accion_marcar: &'static str,
+    pub accion_editar: &'static str,
+    pub accion_eliminar: &'static str,
+    pub accion_temporizador: &'static str,
+    pub accion_reiniciar: &'static str,
+    pub accion_mover_arriba: &'static str,
+    pub accion_mover_abajo: &'static str,
+    pub accion_marcar_seleccion: &'static str,
+    pub accion_seleccionar_todo: &'static str,
+    pub accion_nueva_tarea: &'static str,
+    pub accion_ayuda: &'static str,
+    pub accion_paleta: &'static str,
+    pub sin_repeticion: &'static str,
+    pub diaria: &'static str,
+    pub semanal: &'static str,
+    pub mensual: &'static str,
+    pub cada_n_dias: &'static str,
+    pub todos_los_dias: &'static str,
+    pub cada_semana: &'static str,
+    pub dia_del_mes: &'static str,
+    pub dias_tras_completar: &'static str,
+    /// Formato `chrono` de las fechas cortas (día y mes)
+    pub formato_fecha: &'static str,
+    /// Iniciales de los días, empezando por el lunes
+    pub dias_semana: [&'static str; 7],
+    /// En el orden de [`crate::Tema::TODOS`]
+    pub temas: [&'static str; 3],
+    /// En el orden de [`crate::Prioridad::TODAS`]
+    pub prioridades: [&'static str; 5],
+    /// Tareas de ejemplo para una base de datos nueva
+    pub ejemplos: [&'static str; 10],
+}
+
+pub const ES: Textos = Textos {
+    titulo: "📋 Lista de Tareas",
+    idioma: "Idioma",
+    atajos_teclado: "Atajos de teclado",
+    nueva_tarea: "Nueva tarea:",
+    agregar: "➕ Agregar",
+    tiempo_estimado_ayuda: "Tiempo estimado (0 = sin estimación)",
+    minutos_sufijo: " min",
+    notas_ayuda: "Notas (Markdown)",
+    sin_notas: "Sin notas. Usa ✏️ para añadirlas.",
+    exportado: "Tareas exportadas a {}",
+    error_exportar: "No se pudo exportar: {}",
+    dia_prefijo: "día ",
+    cada_prefijo: "cada ",
+    dias_sufijo: " días",
+    orden: "Orden:",
+    orden_manual: "Manual",
+    orden_prioridad: "Prioridad",
+    prioridad_minima: "Prioridad mínima:",
+    buscar_ayuda: "Buscar en tareas y notas",
+    total: "📊 Total: {}",
+    completadas: "✅ Completadas: {}",
+    pendientes: "⏳ Pendientes: {}",
+    tiempo_total: "⏱️ Tiempo total: {}",
+    real_vs_estimado: "🎯 Real vs. estimado: {} / {} ({}%)",
+    real_vs_estimado_sin_datos: "🎯 Real vs. estimado: sin datos",
+    recargar: "🔄 Recargar tareas",
+    limpiar_completadas: "🧹 Limpiar completadas",
+    archivadas: "{} tareas archivadas",
+    archivo_titulo: "🗄 Archivo",
+    archivo_vacio: "No hay tareas archivadas.",
+    restaurar: "↩ Restaurar",
+    seleccionadas: "{} seleccionadas:",
+    lote_completar: "✔ Completar",
+    lote_desmarcar: "☐ Desmarcar",
+    lote_archivar: "🗄 Archivar",
+    lote_reiniciar: "🔄 Reiniciar tiempo",
+    quitar_seleccion: "Quitar la selección",
+    confirmar_eliminar_varias: "¿Eliminar {} tareas?",
+    papelera_titulo: "♻ Papelera",
+    papelera_vacia: "La papelera está vacía.",
+    eliminar_definitivamente: "Eliminar definitivamente",
+    vaciar_papelera: "Vaciar papelera",
+    dias_papelera: "Vaciar papelera tras",
+    dias_papelera_ayuda: "Días que se guardan las tareas eliminadas (0 = siempre)",
+    copias_conservar: "Copias de seguridad",
+    copias_conservar_ayuda: "Copias automáticas que se conservan (0 = ninguna)",
+    intervalo_copias: "Copia cada",
+    intervalo_copias_ayuda: "Además de al abrir y al salir (0 = solo entonces)",
+    copia_guardada: "Copia guardada en {}",
+    copia_restaurada: "Base de datos restaurada desde {}",
+    error_copia: "Error en la copia de seguridad: {}",
+    uso_restaurar: "Uso: pixi restore <copia.db>",
+    conflicto_edicion: "La tarea se había editado en otra ventana: se muestran esos cambios y los tuyos no se han guardado",
+    pedir_clave: "Contraseña de la base de datos: ",
+    repetir_clave: "Repite la contraseña: ",
+    claves_distintas: "Las contraseñas no coinciden",
+    clave_vacia: "La contraseña no puede estar vacía",
+    clave_incorrecta: "Contraseña incorrecta",
+    desbloquear: "Desbloquear",
+    base_cifrada: "{} está cifrada",
+    ya_cifrada: "{} ya está cifrada",
+    no_cifrada: "{} no está cifrada",
+    cifrada_ok: "Base de datos cifrada, junto con sus copias: {}",
+    descifrada_ok: "Base de datos descifrada, junto con sus copias: {}",
+    error_cifrado: "Error al cambiar el cifrado: {}",
+    base_en_uso: "La base de datos está abierta en otra instancia de pixi; ciérrala y vuelve a intentarlo",
+    carpeta_sync: "Carpeta de sincronización",
+    carpeta_sync_ayuda: "Carpeta compartida entre tus equipos, como una de Dropbox o Syncthing (vacía = sin sincronizar)",
+    sincronizar: "🔄 Sincronizar ahora",
+    sincronizado: "Sincronizado: {} cambios recibidos, {} enviados",
+    conflicto_sync: "Tarea {} cambiada a la vez en otro equipo: se conserva «{}» y se descarta «{}»",
+    error_sync: "Error al sincronizar: {}",
+    sin_carpeta_sync: "No hay carpeta de sincronización. Uso: pixi sync <carpeta>",
+    api_escuchando: "API en http://{}",
+    api_token_nuevo: "Token nuevo de la API; no se volverá a mostrar (con PIXI_TOKEN se usa otro).\nCabecera de autenticación: Authorization: Bearer {}",
+    error_servidor: "No se pudo abrir el servidor en {}: {}",
+    uso_serve: "Uso: pixi serve [dirección:puerto]",
+    limite_ganchos: "Límite de los ganchos",
+    limite_ganchos_ayuda: "Segundos que puede tardar un gancho (`pixi hook`) antes de detenerlo",
+    error_gancho: "Falló el gancho «{}» de la tarea {}: {}",
+    gancho_limite: "se detuvo tras {} s sin terminar",
+    gancho_guardado: "Gancho «{}» guardado",
+    gancho_quitado: "Gancho «{}» quitado",
+    sin_gancho: "(ninguno)",
+    uso_hook: "Uso: pixi hook [add|complete|delete|timer_start|timer_stop [orden]]",
+    meta_diaria: "🎯 Meta diaria",
+    tareas_sufijo: " tareas",
+    racha: "🔥 Racha: {} días",
+    meta_semanal: "🗓 Meta semanal",
+    racha_semanas: "🔥 Racha: {} semanas",
+    paleta_exportar: "📤 Exportar a {}",
+    paleta_tema: "🌓 Cambiar tema claro/oscuro",
+    paleta_orden_manual: "↕ Orden manual",
+    paleta_orden_prioridad: "↕ Ordenar por prioridad",
+    paleta_limpiar_busqueda: "✖ Limpiar búsqueda",
+    paleta_agregar: "➕ Agregar «{}»",
+    paleta_ayuda: "Escribe un comando o una tarea…",
+    ayuda_titulo: "⌨ Atajos de teclado",
+    pulsa_tecla: "Pulsa una tecla…",
+    cambiar: "Cambiar",
+    cerrar_ayuda: "Cerrar ayuda / quitar selección",
+    restablecer_atajos: "Restablecer atajos",
+    ajustes_titulo: "⚙ Ajustes",
+    tema: "Tema",
+    tamano_letra: "Tamaño de letra",
+    color_temporizador: "Color del temporizador",
+    color_arrastre: "Color al arrastrar",
+    confirmar_borrado: "Confirmar antes de eliminar",
+    base_datos: "Base de datos",
+    abrir: "Abrir",
+    error_abrir_db: "No se pudo abrir {}: {}",
+    error_guardar_config: "No se pudo guardar {}: {}",
+    restablecer_ajustes: "Restablecer valores por defecto",
+    confirmar_eliminar: "¿Eliminar «{}»?",
+    eliminar: "🗑 Eliminar",
+    cancelar: "Cancelar",
+    editar_tarea: "Editar tarea:",
+    tui_ayuda: "↑↓ mover · espacio marcar · a añadir · e editar · d eliminar · t temporizador · r reiniciar · q salir",
+    tui_confirmar: "Enter eliminar · Esc cancelar",
+    accion_siguiente: "Seleccionar siguiente",
+    accion_anterior: "Seleccionar anterior",
+    accion_marcar: "Marcar / desmarcar",
+    accion_editar: "Editar",
+    accion_eliminar: "Eliminar",
+    accion_temporizador: "Iniciar / pausar temporizador",
+    accion_reiniciar: "Reiniciar temporizador",
+    accion_mover_arriba: "Mover arriba",
+    accion_mover_abajo: "Mover abajo",
+    accion_marcar_seleccion: "Añadir / quitar de la selección",
+    accion_seleccionar_todo: "Seleccionar todas",
+    accion_nueva_tarea: "Escribir nueva tarea",
+    accion_ayuda: "Mostrar esta ayuda",
+    accion_paleta: "Paleta de comandos",
+    sin_repeticion: "Sin repetición",
+    diaria: "Diaria",
+    semanal: "Semanal",
+    mensual: "Mensual",
+    cada_n_dias: "Cada N días",
+    todos_los_dias: "Todos los días",
+    cada_semana: "Cada semana: {}",
+    dia_del_mes: "El día {} de cada mes",
+    dias_tras_completar: "{} días después de completarla",
+    formato_fecha: "%d/%m",
+    dias_semana: ["L", "M", "X", "J", "V", "S", "D"],
+    temas: ["Oscuro", "Claro", "Sistema"],
+    prioridades: ["Ninguna", "Baja", "Media", "Alta", "Urgente"],
+    ejemplos: [
+        "Comprar leche y pan en el supermercado",
+        "Llamar al dentista para cita",
+        "Revisar correo electrónico importante",
+        "Hacer ejercicio 30 minutos",
+        "Leer capítulo del libro",
+        "Preparar presentación para reunión",
+        "Pagar facturas del mes",
+        "Organizar escritorio de trabajo",
+        "Estudiar Rust y egui",
+        "Backup de archivos importantes",
+    ],
+};
+
+pub const EN: Textos = Textos {
+    titulo: "📋 Task List",
+    idioma: "Language",
+    atajos_teclado: "Keyboard shortcuts",
+    nueva_tarea: "New task:",
+    agregar: "➕ Add",
+    tiempo_estimado_ayuda: "Estimated time (0 = no estimate)",
+    minutos_sufijo: " min",
+    notas_ayuda: "Notes (Markdown)",
+    sin_notas: "No notes. Use ✏️ to add some.",
+    exportado: "Tasks exported to {}",
+    error_exportar: "Export failed: {}",
+    dia_prefijo: "day ",
+    cada_prefijo: "every ",
+    dias_sufijo: " days",
+    orden: "Order:",
+    orden_manual: "Manual",
+    orden_prioridad: "Priority",
+    prioridad_minima: "Minimum priority:",
+    buscar_ayuda: "Search tasks and notes",
+    total: "📊 Total: {}",
+    completadas: "✅ Completed: {}",
+    pendientes: "⏳ Pending: {}",
+    tiempo_total: "⏱️ Total time: {}",
+    real_vs_estimado: "🎯 Actual vs. estimate: {} / {} ({}%)",
+    real_vs_estimado_sin_datos: "🎯 Actual vs. estimate: no data",
+    recargar: "🔄 Reload tasks",
+    limpiar_completadas: "🧹 Clear completed",
+    archivadas: "{} tasks archived",
+    archivo_titulo: "🗄 Archive",
+    archivo_vacio: "No archived tasks.",
+    restaurar: "↩ Restore",
+    seleccionadas: "{} selected:",
+    lote_completar: "✔ Complete",
+    lote_desmarcar: "☐ Uncheck",
+    lote_archivar: "🗄 Archive",
+    lote_reiniciar: "🔄 Reset time",
+    quitar_seleccion: "Clear selection",
+    confirmar_eliminar_varias: "Delete {} tasks?",
+    papelera_titulo: "♻ Trash",
+    papelera_vacia: "The trash is empty.",
+    eliminar_definitivamente: "Delete permanently",
+    vaciar_papelera: "Empty trash",
+    dias_papelera: "Empty trash after",
+    dias_papelera_ayuda: "Days deleted tasks are kept (0 = forever)",
+    copias_conservar: "Backups",
+    copias_conservar_ayuda: "Automatic backups to keep (0 = none)",
+    intervalo_copias: "Back up every",
+    intervalo_copias_ayuda: "Besides on startup and exit (0 = only then)",
+    copia_guardada: "Backup saved to {}",
+    copia_restaurada: "Database restored from {}",
+    error_copia: "Backup error: {}",
+    uso_restaurar: "Usage: pixi restore <backup.db>",
+    conflicto_edicion: "The task had been edited in another window: showing those changes, yours were not saved",
+    pedir_clave: "Database password: ",
+    repetir_clave: "Repeat the password: ",
+    claves_distintas: "The passwords do not match",
+    clave_vacia: "The password cannot be empty",
+    clave_incorrecta: "Wrong password",
+    desbloquear: "Unlock",
+    base_cifrada: "{} is encrypted",
+    ya_cifrada: "{} is already encrypted",
+    no_cifrada: "{} is not encrypted",
+    cifrada_ok: "Database and its backups encrypted: {}",
+    descifrada_ok: "Database and its backups decrypted: {}",
+    error_cifrado: "Encryption error: {}",
+    base_en_uso: "The database is open in another pixi instance; close it and try again",
+    carpeta_sync: "Sync folder",
+    carpeta_sync_ayuda: "Folder shared between your machines, such as a Dropbox or Syncthing one (empty = no sync)",
+    sincronizar: "🔄 Sync now",
+    sincronizado: "Synced: {} changes received, {} sent",
+    conflicto_sync: "Task {} was changed on another machine at the same time: keeping “{}” and discarding “{}”",
+    error_sync: "Sync error: {}",
+    sin_carpeta_sync: "No sync folder set. Usage: pixi sync <folder>",
+    api_escuchando: "API at http://{}",
+    api_token_nuevo: "New API token; it will not be shown again (set PIXI_TOKEN to use another).\nAuthentication header: Authorization: Bearer {}",
+    error_servidor: "Could not start the server on {}: {}",
+    uso_serve: "Usage: pixi serve [address:port]",
+    limite_ganchos: "Hook time limit",
+    limite_ganchos_ayuda: "Seconds a hook (`pixi hook`) may run before it is stopped",
+    error_gancho: "The “{}” hook failed for task {}: {}",
+    gancho_limite: "killed after {} s",
+    gancho_guardado: "“{}” hook saved",
+    gancho_quitado: "“{}” hook removed",
+    sin_gancho: "(none)",
+    uso_hook: "Usage: pixi hook [add|complete|delete|timer_start|timer_stop [command]]",
+    meta_diaria: "🎯 Daily goal",
+    tareas_sufijo: " tasks",
+    racha: "🔥 Streak: {} days",
+    meta_semanal: "🗓 Weekly goal",
+    racha_semanas: "🔥 Streak: {} weeks",
+    paleta_exportar: "📤 Export to {}",
+    paleta_tema: "🌓 Toggle light/dark theme",
+    paleta_orden_manual: "↕ Manual order",
+    paleta_orden_prioridad: "↕ Sort by priority",
+    paleta_limpiar_busqueda: "✖ Clear search",
+    paleta_agregar: "➕ Add “{}”",
+    paleta_ayuda: "Type a command or a task…",
+    ayuda_titulo: "⌨ Keyboard shortcuts",
+    pulsa_tecla: "Press a key…",
+    cambiar: "Change",
+    cerrar_ayuda: "Close help / clear selection",
+    restablecer_atajos: "Reset shortcuts",
+    ajustes_titulo: "⚙ Settings",
+    tema: "Theme",
+    tamano_letra: "Font size",
+    color_temporizador: "Timer colour",
+    color_arrastre: "Drag colour",
+    confirmar_borrado: "Confirm before deleting",
+    base_datos: "Database",
+    abrir: "Open",
+    error_abrir_db: "Could not open {}: {}",
+    error_guardar_config: "Could not save {}: {}",
+    restablecer_ajustes: "Restore defaults",
+    confirmar_eliminar: "Delete “{}”?",
+    eliminar: "🗑 Delete",
+    cancelar: "Cancel",
+    editar_tarea: "Edit task:",
+    tui_ayuda: "↑↓ move · space check · a add · e edit · d delete · t timer · r reset · q quit",
+    tui_confirmar: "Enter delete · Esc cancel",
+    accion_siguiente: "Select next",
+    accion_anterior: "Select previous",
+    accion_marcar: "Check / uncheck",
+    accion_editar: "Edit",
+    accion_eliminar: "Delete",
+    accion_temporizador: "Start / pause timer",
+    accion_reiniciar: "Reset timer",
+    accion_mover_arriba: "Move up",
+    accion_mover_abajo: "Move down",
+    accion_marcar_seleccion: "Add to / remove from selection",
+    accion_seleccionar_todo: "Select all",
+    accion_nueva_tarea: "Type a new task",
+    accion_ayuda: "Show this help",
+    accion_paleta: "Command palette",
+    sin_repeticion: "No repeat",
+    diaria: "Daily",
+    semanal: "Weekly",
+    mensual: "Monthly",
+    cada_n_dias: "Every N days",
+    todos_los_dias: "Every day",
+    cada_semana: "Every week: {}",
+    dia_del_mes: "Day {} of every month",
+    dias_tras_completar: "{} days after completion",
+    formato_fecha: "%m/%d",
+    dias_semana: ["M", "T", "W", "T", "F", "S", "S"],
+    temas: ["Dark", "Light", "System"],
+    prioridades: ["None", "Low", "Medium", "High", "Urgent"],
+    ejemplos: [
+        "Buy milk and bread at the supermarket",
+        "Call the dentist for an appointment",
+        "Check important email",
+        "Exercise for 30 minutes",
+        "Read a book chapter",
+        "Prepare presentation for the meeting",
+        "Pay this month's bills",
+        "Tidy up the desk",
+        "Study Rust and egui",
+        "Back up important files",
+    ],
+};
@@ -1,6 +1,39 @@
 use std::time::Instant;
 
-use rusqlite::{Connection, Result as SqlResult};
+use chrono::NaiveDate;
+use rusqlite::{Connection, OptionalExtension, Result as SqlResult, Row, params};
+
+pub mod ajustes;
+pub mod api;
+pub mod archivo;
+pub mod busqueda;
+#[cfg(feature = "cifrado")]
+pub mod cifrado;
+pub mod concurrencia;
+pub mod copias;
+pub mod estadisticas;
+pub mod exportar;
+pub mod ganchos;
+pub mod i18n;
+pub mod lista;
+pub mod lotes;
+pub mod metas;
+pub mod papelera;
+pub mod prioridad;
+pub mod recurrencia;
+pub mod servicio;
+pub mod sincronizacion;
+pub mod transacciones;
+
+pub use ajustes::{Ajustes, Tema};
+pub use estadisticas::Totales;
+pub use ganchos::Evento;
+pub use i18n::{Idioma, Textos};
+pub use lista::{EstadoLista, ListaTareas};
+pub use metas::{Meta, Periodo, ResumenDia};
+pub use prioridad::Prioridad;
+pub use recurrencia::Recurrencia;
+pub use servicio::{Edicion, Filtro, Orden, ResultadoEdicion, ServicioTareas};
 
 pub struct Timer {
     inicio: Instant,
@@ -10,38 +43,72 @@ pub struct Timer {
 pub struct TodoItem {
     pub id: i32,
     pub text: String,
+    /// Notas en Markdown, sin límite de líneas
+    pub notas: String,
     pub checked: bool,
+    /// Duración estimada en segundos, si se ha indicado
+    pub estimacion: Option<i32>,
+    pub prioridad: Prioridad,
+    pub recurrencia: Option<Recurrencia>,
+    /// Fecha en la que toca esta ocurrencia de una tarea recurrente
+    pub fecha_proxima: Option<NaiveDate>,
     tiempo_acumulado: i32,
+    /// Número de ediciones guardadas; ver [`ServicioTareas::editar`]
+    version: i64,
     temporizador: Option<Timer>,
 }
 
 impl TodoItem {
     pub fn tiempo_total(&self) -> i32 {
-        if let Some(ref timer) = self.temporizador {
-            if timer.activo {
-                return self.tiempo_acumulado + timer.inicio.elapsed().as_secs() as i32;
-            }
+        if let Some(ref timer) = self.temporizador
+            && timer.activo
+        {
+            return self.tiempo_acumulado + timer.inicio.elapsed().as_secs() as i32;
         }
         self.tiempo_acumulado
     }
 
+    /// Fracción del tiempo estimado ya consumida (puede superar 1.0)
+    pub fn progreso_estimacion(&self) -> Option<f32> {
+        self.estimacion
+            .filter(|&e| e > 0)
+            .map(|e| self.tiempo_total() as f32 / e as f32)
+    }
+
+    pub fn excede_estimacion(&self) -> bool {
+        self.progreso_estimacion().is_some_and(|p| p > 1.0)
+    }
+
     pub fn temporizador_activo(&self) -> bool {
-        self.temporizador.as_ref().map_or(false, |t| t.activo)
+        self.temporizador.as_ref().is_some_and(|t| t.activo)
+    }
+
+    /// Segundos de la sesión en curso, todavía no guardados
+    pub fn tiempo_sesion(&self) -> i32 {
+        self.temporizador
+            .as_ref()
+            .filter(|t| t.activo)
+            .map_or(0, |t| t.inicio.elapsed().as_secs() as i32)
     }
 
     pub fn pausar_temporizador(&mut self, db: &Db) {
-        if let Some(ref timer) = self.temporizador {
-            self.tiempo_acumulado += timer.inicio.elapsed().as_secs() as i32;
-            let _ = db.actualizar_tiempo(self.id, self.tiempo_acumulado);
-        }
-        self.temporizador = None;
+        let Some(timer) = self.temporizador.take() else {
+            return;
+        };
+        let sesion = timer.inicio.elapsed().as_secs() as i32;
+        // El total guardado incluye lo que hayan sumado otras instancias
+        self.tiempo_acumulado = db
+            .guardar_sesion(self.id, sesion)
+            .unwrap_or(self.tiempo_acumulado + sesion);
+        let _ = db.disparar_gancho_con(Evento::TemporizadorParado, self);
     }
 
-    pub fn iniciar_temporizador(&mut self) {
+    pub fn iniciar_temporizador(&mut self, db: &Db) {
         self.temporizador = Some(Timer {
             inicio: Instant::now(),
             activo: true,
         });
+        let _ = db.disparar_gancho_con(Evento::TemporizadorIniciado, self);
     }
 
     pub fn resetear_temporizador(&mut self, db: &Db) {
@@ -51,15 +118,60 @@ impl TodoItem {
     }
 }
 
+/// Segundos como `HH:MM:SS`
+pub fn formatear_duracion(segundos: i32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        segundos / 3600,
+        (segundos % 3600) / 60,
+        segundos % 60
+    )
+}
+
+const COLUMNAS_TAREA: &str = "id, descripcion, completada, tiempo_acumulado, \
+    tiempo_estimado, recurrencia, fecha_proxima, prioridad, notas, version";
+
+/// Condición de las tareas que forman la lista (ni archivadas ni en la papelera)
+const SOLO_ACTIVAS: &str = "archivada_en IS NULL AND eliminada_en IS NULL";
+
+fn tarea_desde_fila(row: &Row) -> SqlResult<TodoItem> {
+    Ok(TodoItem {
+        id: row.get(0)?,
+        text: row.get(1)?,
+        checked: row.get::<_, i32>(2)? != 0,
+        tiempo_acumulado: row.get::<_, i32>(3)?,
+        estimacion: row.get::<_, Option<i32>>(4)?,
+        recurrencia: row
+            .get::<_, Option<String>>(5)?
+            .and_then(|r| Recurrencia::decodificar(&r)),
+        fecha_proxima: row.get::<_, Option<NaiveDate>>(6)?,
+        prioridad: Prioridad::desde_entero(row.get(7)?),
+        notas: row.get(8)?,
+        version: row.get(9)?,
+        temporizador: None,
+    })
+}
+
 pub struct Db {
     conn: Connection,
+    /// Clave con la que se abrió, si está cifrada; sus copias de seguridad
+    /// se cifran con la misma
+    #[cfg(feature = "cifrado")]
+    clave: Option<String>,
+    ganchos: ganchos::Ganchos,
 }
 
 impl Db {
     /// Open or create the database and initialize tables
     pub fn new(path: &str) -> SqlResult<Self> {
         let conn = Connection::open(path)?;
-        let db = Self { conn };
+        concurrencia::configurar(&conn)?;
+        let db = Self {
+            conn,
+            #[cfg(feature = "cifrado")]
+            clave: None,
+            ganchos: Default::default(),
+        };
         db.init()?;
         Ok(db)
     }
@@ -71,7 +183,48 @@ impl Db {
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 descripcion TEXT NOT NULL,
                 completada INTEGER DEFAULT 0,
-                tiempo_acumulado INTEGER DEFAULT 0
+                tiempo_acumulado INTEGER DEFAULT 0,
+                tiempo_estimado INTEGER,
+                completada_en TEXT,
+                recurrencia TEXT,
+                fecha_proxima TEXT,
+                prioridad INTEGER DEFAULT 0,
+                notas TEXT NOT NULL DEFAULT '',
+                archivada_en TEXT,
+                eliminada_en TEXT,
+                version INTEGER NOT NULL DEFAULT 0,
+                uid TEXT,
+                ocurrencia_siguiente INTEGER
+            )",
+            [],
+        )?;
+        self.agregar_columna_si_falta("tiempo_estimado", "INTEGER")?;
+        self.agregar_columna_si_falta("completada_en", "TEXT")?;
+        self.agregar_columna_si_falta("recurrencia", "TEXT")?;
+        self.agregar_columna_si_falta("fecha_proxima", "TEXT")?;
+        self.agregar_columna_si_falta("prioridad", "INTEGER DEFAULT 0")?;
+        self.agregar_columna_si_falta("notas", "TEXT NOT NULL DEFAULT ''")?;
+        self.agregar_columna_si_falta("archivada_en", "TEXT")?;
+        self.agregar_columna_si_falta("eliminada_en", "TEXT")?;
+        self.agregar_columna_si_falta("version", "INTEGER NOT NULL DEFAULT 0")?;
+        self.agregar_columna_si_falta("uid", "TEXT")?;
+        self.agregar_columna_si_falta("ocurrencia_siguiente", "INTEGER")?;
+        self.crear_indice_busqueda()?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS sesiones (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                tarea_id INTEGER NOT NULL,
+                fecha TEXT NOT NULL,
+                segundos INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS ajustes (
+                clave TEXT PRIMARY KEY,
+                valor TEXT NOT NULL
             )",
             [],
         )?;
@@ -81,67 +234,143 @@ impl Db {
             .query_row("SELECT COUNT(*) FROM tareas", [], |row| row.get(0))?;
 
         if count == 0 {
-            let ejemplos = [
-                "Comprar leche y pan en el supermercado",
-                "Llamar al dentista para cita",
-                "Revisar correo electrónico importante",
-                "Hacer ejercicio 30 minutos",
-                "Leer capítulo del libro",
-                "Preparar presentación para reunión",
-                "Pagar facturas del mes",
-                "Organizar escritorio de trabajo",
-                "Estudiar Rust y egui",
-                "Backup de archivos importantes",
+            // Repetición de cada tarea de ejemplo de `Textos::ejemplos`
+            let recurrencias = [
+                None,
+                None,
+                None,
+                Some(Recurrencia::Diaria),
+                None,
+                None,
+                Some(Recurrencia::Mensual(1)),
+                None,
+                None,
+                None,
             ];
+            let ejemplos = self.idioma().textos().ejemplos;
 
-            for tarea in ejemplos {
-                self.agregar_tarea(tarea)?;
-            }
+            self.transaccion(|db| {
+                for (n, (tarea, recurrencia)) in ejemplos.into_iter().zip(recurrencias).enumerate()
+                {
+                    let id = db.agregar_tarea(tarea)?;
+                    // Igual en todos los equipos, para que al sincronizar
+                    // no se copien los ejemplos de uno a otro
+                    db.conn.execute(
+                        "UPDATE tareas SET uid = ?1 WHERE id = ?2",
+                        params![sincronizacion::uid_ejemplo(n), id],
+                    )?;
+                    if recurrencia.is_some() {
+                        db.actualizar_recurrencia(id, recurrencia)?;
+                    }
+                }
+                Ok(())
+            })?;
         }
 
         Ok(())
     }
 
+    /// Add a column to `tareas` when opening a database created by an older version
+    fn agregar_columna_si_falta(&self, columna: &str, definicion: &str) -> SqlResult<()> {
+        let existe: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('tareas') WHERE name = ?1",
+            [columna],
+            |row| row.get(0),
+        )?;
+        if existe == 0 {
+            self.conn.execute(
+                &format!("ALTER TABLE tareas ADD COLUMN {} {}", columna, definicion),
+                [],
+            )?;
+        }
+        Ok(())
+    }
+
     pub fn cargar_tareas(&self) -> SqlResult<Vec<TodoItem>> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT id, descripcion, completada, tiempo_acumulado FROM tareas")?;
-
-        let tareas_iter = stmt.query_map([], |row| {
-            Ok(TodoItem {
-                id: row.get(0)?,
-                text: row.get(1)?,
-                checked: row.get::<_, i32>(2)? != 0,
-                tiempo_acumulado: row.get::<_, i32>(3)?,
-                temporizador: None,
-            })
-        })?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tareas WHERE {} ORDER BY id",
+            COLUMNAS_TAREA, SOLO_ACTIVAS
+        ))?;
+
+        let tareas_iter = stmt.query_map([], tarea_desde_fila)?;
 
         Ok(tareas_iter.filter_map(|t| t.ok()).collect())
     }
 
-    pub fn agregar_tarea(&self, descripcion: &str) -> SqlResult<()> {
+    /// Hasta `limite` tareas con id mayor que `despues_de`, en orden de id
+    pub fn cargar_pagina_tareas(&self, despues_de: i32, limite: usize) -> SqlResult<Vec<TodoItem>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tareas WHERE id > ?1 AND {} ORDER BY id LIMIT ?2",
+            COLUMNAS_TAREA, SOLO_ACTIVAS
+        ))?;
+
+        let tareas_iter = stmt.query_map(
+            rusqlite::params![despues_de, limite as i64],
+            tarea_desde_fila,
+        )?;
+
+        Ok(tareas_iter.filter_map(|t| t.ok()).collect())
+    }
+
+    pub fn cargar_tarea(&self, id: i32) -> SqlResult<TodoItem> {
+        self.conn.query_row(
+            &format!(
+                "SELECT {} FROM tareas WHERE id = ?1 AND eliminada_en IS NULL",
+                COLUMNAS_TAREA
+            ),
+            [id],
+            tarea_desde_fila,
+        )
+    }
+
+    /// Crea una tarea pendiente y devuelve su id
+    pub fn agregar_tarea(&self, descripcion: &str) -> SqlResult<i32> {
         self.conn.execute(
             "INSERT INTO tareas (descripcion) VALUES (?1)",
             [descripcion],
         )?;
-        Ok(())
+        let id = self.conn.last_insert_rowid() as i32;
+        self.disparar_gancho(Evento::Agregada, id)?;
+        Ok(id)
     }
 
+    /// Mueve una tarea a la papelera; ver [`Db::eliminar_definitivamente`]
     pub fn eliminar_tarea(&self, id: i32) -> SqlResult<()> {
-        self.conn
-            .execute("DELETE FROM tareas WHERE id = ?1", [id])?;
-        Ok(())
-    }
-
-    pub fn actualizar_tarea(&self, id: i32, completada: bool) -> SqlResult<()> {
+        // El gancho la recibe tal como estaba antes de ir a la papelera
+        let tarea = match self.gancho(Evento::Eliminada)? {
+            Some(_) => self.cargar_tarea(id).optional()?,
+            None => None,
+        };
         self.conn.execute(
-            "UPDATE tareas SET completada = ?1 WHERE id = ?2",
-            [completada as i32, id],
+            "UPDATE tareas SET eliminada_en = datetime('now', 'localtime') WHERE id = ?1",
+            [id],
         )?;
+        if let Some(tarea) = tarea {
+            self.disparar_gancho_con(Evento::Eliminada, &tarea)?;
+        }
         Ok(())
     }
 
+    /// Marca una tarea como completada o pendiente. Al completar una tarea
+    /// recurrente se crea su siguiente ocurrencia, cuyo id se devuelve; al
+    /// desmarcarla se deshace si no se ha tocado.
+    pub fn actualizar_tarea(&self, id: i32, completada: bool) -> SqlResult<Option<i32>> {
+        self.transaccion(|db| {
+            db.conn.execute(
+                "UPDATE tareas SET completada = ?1,
+                    completada_en = CASE WHEN ?1 THEN datetime('now', 'localtime') END
+                 WHERE id = ?2",
+                [completada as i32, id],
+            )?;
+            if completada {
+                db.disparar_gancho(Evento::Completada, id)?;
+                return db.generar_siguiente_ocurrencia(id);
+            }
+            db.deshacer_siguiente_ocurrencia(id)?;
+            Ok(None)
+        })
+    }
+
     pub fn actualizar_tiempo(&self, id: i32, tiempo: i32) -> SqlResult<()> {
         self.conn.execute(
             "UPDATE tareas SET tiempo_acumulado = ?1 WHERE id = ?2",
@@ -157,4 +386,52 @@ impl Db {
         )?;
         Ok(())
     }
+
+    pub fn actualizar_estimacion(&self, id: i32, estimacion: Option<i32>) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE tareas SET tiempo_estimado = ?1 WHERE id = ?2",
+            rusqlite::params![estimacion, id],
+        )?;
+        Ok(())
+    }
+
+    /// Idioma elegido por el usuario, o el del sistema si no hay ninguno
+    pub fn idioma(&self) -> Idioma {
+        self.obtener_ajuste("idioma")
+            .ok()
+            .flatten()
+            .and_then(|codigo| Idioma::desde_codigo(&codigo))
+            .unwrap_or_else(Idioma::detectar)
+    }
+
+    pub fn guardar_idioma(&self, idioma: Idioma) -> SqlResult<()> {
+        self.guardar_ajuste("idioma", idioma.codigo())
+    }
+
+    pub fn obtener_ajuste(&self, clave: &str) -> SqlResult<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT valor FROM ajustes WHERE clave = ?1",
+                [clave],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    pub fn guardar_ajuste(&self, clave: &str, valor: &str) -> SqlResult<()> {
+        self.conn.execute(
+            "INSERT INTO ajustes (clave, valor) VALUES (?1, ?2)
+             ON CONFLICT(clave) DO UPDATE SET valor = excluded.valor",
+            [clave, valor],
+        )?;
+        Ok(())
+    }
+
+    pub fn actualizar_notas(&self, id: i32, notas: &str) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE tareas SET notas = ?1 WHERE id = ?2",
+            rusqlite::params![notas, id],
+        )?;
+        Ok(())
+    }
 }
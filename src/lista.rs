@@ -0,0 +1,154 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
+
+use crate::TodoItem;
+
+/// Tareas en el orden en que se muestran, accesibles por id.
+///
+/// El estado de la interfaz (selección, edición, arrastre…) guarda ids y no
+/// posiciones, de modo que sigue apuntando a la misma tarea aunque la lista
+/// se reordene o se borren otras tareas.
+#[derive(Default)]
+pub struct ListaTareas {
+    tareas: Vec<TodoItem>,
+}
+
+impl ListaTareas {
+    pub fn new(tareas: Vec<TodoItem>) -> Self {
+        Self { tareas }
+    }
+
+    pub fn posicion(&self, id: i32) -> Option<usize> {
+        self.tareas.iter().position(|t| t.id == id)
+    }
+
+    pub fn get(&self, id: i32) -> Option<&TodoItem> {
+        self.tareas.iter().find(|t| t.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: i32) -> Option<&mut TodoItem> {
+        self.tareas.iter_mut().find(|t| t.id == id)
+    }
+
+    pub fn push(&mut self, tarea: TodoItem) {
+        self.tareas.push(tarea);
+    }
+
+    pub fn extend(&mut self, tareas: impl IntoIterator<Item = TodoItem>) {
+        self.tareas.extend(tareas);
+    }
+
+    pub fn clear(&mut self) {
+        self.tareas.clear();
+    }
+
+    /// Mueve la tarea `id` al lugar que ocupa `destino`, desplazando las demás
+    pub fn mover(&mut self, id: i32, destino: i32) -> bool {
+        let (Some(desde), Some(hasta)) = (self.posicion(id), self.posicion(destino)) else {
+            return false;
+        };
+        let tarea = self.tareas.remove(desde);
+        self.tareas.insert(hasta, tarea);
+        true
+    }
+
+    /// Reordena las tareas como estaban `ids`; las que no están en `ids`
+    /// van al final, en el orden que ya tenían
+    pub fn ordenar_como(&mut self, ids: &[i32]) {
+        let posiciones: HashMap<i32, usize> =
+            ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        self.tareas
+            .sort_by_key(|t| posiciones.get(&t.id).copied().unwrap_or(usize::MAX));
+    }
+
+    /// Id de la tarea que ocupará el lugar de `id` si se quita: la siguiente,
+    /// o la anterior si era la última
+    pub fn vecina(&self, id: i32) -> Option<i32> {
+        let pos = self.posicion(id)?;
+        self.tareas
+            .get(pos + 1)
+            .or_else(|| pos.checked_sub(1).and_then(|p| self.tareas.get(p)))
+            .map(|t| t.id)
+    }
+
+    pub fn quitar(&mut self, id: i32) -> Option<TodoItem> {
+        let pos = self.posicion(id)?;
+        Some(self.tareas.remove(pos))
+    }
+}
+
+impl Deref for ListaTareas {
+    type Target = [TodoItem];
+
+    fn deref(&self) -> &[TodoItem] {
+        &self.tareas
+    }
+}
+
+impl DerefMut for ListaTareas {
+    fn deref_mut(&mut self) -> &mut [TodoItem] {
+        &mut self.tareas
+    }
+}
+
+/// Lo que la interfaz recuerda de la lista, siempre por id de tarea: así
+/// sigue en la misma tarea aunque la lista se reordene o se quiten otras.
+#[derive(Default)]
+pub struct EstadoLista {
+    /// Tarea seleccionada con el teclado
+    pub seleccionada: Option<i32>,
+    /// Tareas marcadas para las acciones en lote
+    pub marcadas: HashSet<i32>,
+    /// Tarea con la caja de edición abierta
+    pub editando: Option<i32>,
+    /// Tarea con el panel de notas desplegado
+    pub desplegada: Option<i32>,
+    /// Tarea que se está arrastrando
+    pub arrastrando: Option<i32>,
+}
+
+impl EstadoLista {
+    /// Tarea que heredará la selección si se quitan `ids` de `tareas`: la
+    /// primera que quede a partir de la seleccionada, o si no la anterior
+    pub fn vecina_superviviente(&self, tareas: &ListaTareas, ids: &[i32]) -> Option<i32> {
+        let pos = tareas.posicion(self.seleccionada?)?;
+        tareas[pos..]
+            .iter()
+            .chain(tareas[..pos].iter().rev())
+            .map(|tarea| tarea.id)
+            .find(|id| !ids.contains(id))
+    }
+
+    /// Olvida las tareas `ids`, que ya no están en la lista; si estaba
+    /// seleccionada alguna, la selección pasa a `vecina`
+    pub fn olvidar(&mut self, ids: &[i32], vecina: Option<i32>) {
+        if self.seleccionada.is_some_and(|id| ids.contains(&id)) {
+            self.seleccionada = vecina;
+        }
+        for estado in [
+            &mut self.editando,
+            &mut self.desplegada,
+            &mut self.arrastrando,
+        ] {
+            if estado.is_some_and(|id| ids.contains(&id)) {
+                *estado = None;
+            }
+        }
+        self.marcadas.retain(|id| !ids.contains(id));
+    }
+
+    /// Olvida las tareas que ya no están en `tareas`, p. ej. tras recargarlas
+    pub fn olvidar_ausentes(&mut self, tareas: &ListaTareas) {
+        for estado in [
+            &mut self.seleccionada,
+            &mut self.editando,
+            &mut self.desplegada,
+            &mut self.arrastrando,
+        ] {
+            if estado.is_some_and(|id| tareas.get(id).is_none()) {
+                *estado = None;
+            }
+        }
+        self.marcadas.retain(|&id| tareas.get(id).is_some());
+    }
+}
@@ -0,0 +1,44 @@
+//! Operaciones sobre varias tareas a la vez. Cada una se hace en una sola
+//! transacción: o se aplica a todas las tareas o a ninguna.
+
+use rusqlite::Result as SqlResult;
+
+use crate::Db;
+
+impl Db {
+    /// Marca varias tareas como completadas o pendientes; devuelve los ids
+    /// de las nuevas ocurrencias de las recurrentes
+    pub fn marcar_tareas(&self, ids: &[i32], completada: bool) -> SqlResult<Vec<i32>> {
+        self.transaccion(|db| {
+            let mut nuevas = Vec::new();
+            for &id in ids {
+                nuevas.extend(db.actualizar_tarea(id, completada)?);
+            }
+            Ok(nuevas)
+        })
+    }
+
+    /// Mueve varias tareas a la papelera
+    pub fn eliminar_tareas(&self, ids: &[i32]) -> SqlResult<()> {
+        self.transaccion(|db| ids.iter().try_for_each(|&id| db.eliminar_tarea(id)))
+    }
+
+    /// Archiva varias tareas, estén completadas o no
+    pub fn archivar_tareas(&self, ids: &[i32]) -> SqlResult<()> {
+        self.transaccion(|db| {
+            for &id in ids {
+                db.conn.execute(
+                    "UPDATE tareas SET archivada_en = datetime('now', 'localtime')
+                     WHERE id = ?1 AND archivada_en IS NULL",
+                    [id],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Pone a cero el tiempo registrado de varias tareas
+    pub fn resetear_tiempos(&self, ids: &[i32]) -> SqlResult<()> {
+        self.transaccion(|db| ids.iter().try_for_each(|&id| db.actualizar_tiempo(id, 0)))
+    }
+}
@@ -1,141 +1,430 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
 use eframe::egui::Ui;
 use egui::{Color32, FontFamily, FontId, Stroke, TextStyle};
-use pixi::{Db, TodoItem};
+use pixi::ajustes::{self, Ajustes, Tema};
+use pixi::copias::ErrorCopia;
+use pixi::i18n::rellenar;
+use pixi::recurrencia::hoy;
+use pixi::{
+    Db, Edicion, EstadoLista, Filtro, Idioma, Meta, Orden, Periodo, Prioridad, Recurrencia,
+    ResultadoEdicion, ResumenDia, ServicioTareas, Textos, TodoItem, Totales, formatear_duracion,
+};
+
+mod cli;
+mod markdown;
+mod palette;
+mod shortcuts;
+mod tui;
+#[cfg(feature = "cifrado")]
+mod unlock;
+
+use palette::{Palette, fuzzy_score};
+use shortcuts::{Action, KeyBindings};
+
+const EXPORT_PATH: &str = "tareas.md";
+
+/// Altura supuesta de una fila que aún no se ha dibujado
+const ROW_HEIGHT_ESTIMATE: f32 = 46.0;
+
+/// Frecuencia de refresco mientras hay algún temporizador activo
+const REPAINT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Cada cuánto mira el hilo vigilante si otra instancia ha cambiado la base
+/// de datos
+const EXTERNAL_CHANGES_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Cada cuánto se mira si ha terminado la copia de seguridad en curso
+const BACKUP_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
-const HEADING: &str = "📋 Lista de Tareas";
+/// Cada cuánto se sincroniza con la carpeta compartida, si hay una
+const SYNC_INTERVAL: Duration = Duration::from_secs(60);
 
+/// Nombre con el que eframe guarda el estado de la ventana entre sesiones
+const APP_ID: &str = "pixi";
+
+// Tamaño inicial; después se usa el guardado al cerrar
 const INNER_SIZE_X: f32 = 500.;
-// const INNER_SIZE_Y: f32 = 700.;
+const INNER_SIZE_Y: f32 = 700.;
 const INNER_SIZE_X_MIN: f32 = 400.;
 const INNER_SIZE_Y_MIN: f32 = 300.;
 const INNER_SIZE_X_MAX: f32 = 1000.;
 const INNER_SIZE_Y_MAX: f32 = 1000.;
 
-// cf. render_header
-const HEADER_Y: f32 = 43.;
-// cf. render_stats
-const STATS_Y: f32 = 72.;
-// cf. render_task_item
-const TASK_Y: f32 = 46.;
+fn main() -> Result<(), eframe::Error> {
+    let ruta_db = ajustes::ruta_db();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        // `pixi tui`: interfaz de terminal en lugar de la ventana
+        Some("tui") => {
+            if let Err(e) = tui::run(cli::open_database(&ruta_db)) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        // `pixi serve`: API HTTP local en lugar de la ventana
+        Some("serve") => {
+            let db = cli::open_database(&ruta_db);
+            std::process::exit(cli::serve(db, &args[1..]));
+        }
+        Some("hook") => {
+            let db = cli::open_database(&ruta_db);
+            std::process::exit(cli::hook(&db, &args[1..]));
+        }
+        Some(comando @ ("backup" | "restore" | "sync")) => {
+            let db = cli::open_database(&ruta_db);
+            std::process::exit(cli::run(db, comando, &args[1..]));
+        }
+        #[cfg(feature = "cifrado")]
+        Some(comando @ ("encrypt" | "decrypt")) => {
+            std::process::exit(cli::encryption::run(&ruta_db, comando));
+        }
+        _ => {}
+    }
+
+    // Cifrada, la ventana se abre pidiendo la clave
+    #[cfg(feature = "cifrado")]
+    if pixi::cifrado::esta_cifrada(std::path::Path::new(&ruta_db)) {
+        return eframe::run_native(
+            APP_ID,
+            native_options(Idioma::detectar().textos().titulo),
+            Box::new(move |_| Ok(Box::new(unlock::Unlock::new(ruta_db)))),
+        );
+    }
 
-// The above INNER_SIZE_Y get overwriten by resizing and are sort of useless
+    let db = Db::new(&ruta_db).unwrap();
+    let titulo = db.idioma().textos().titulo;
 
-fn main() -> Result<(), eframe::Error> {
-    let db = Db::new("tareas.db").unwrap();
-    let num_tareas = db.cargar_tareas().unwrap().len();
-    let needed_height = MyApp::needed_height(num_tareas);
+    eframe::run_native(
+        APP_ID,
+        native_options(titulo),
+        Box::new(move |cc| Ok(app_creator(cc, db, ruta_db))),
+    )
+}
 
-    let options = eframe::NativeOptions {
+fn native_options(titulo: &str) -> eframe::NativeOptions {
+    eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([INNER_SIZE_X, needed_height])
+            .with_title(titulo)
+            .with_app_id(APP_ID)
+            .with_inner_size([INNER_SIZE_X, INNER_SIZE_Y])
             .with_min_inner_size([INNER_SIZE_X_MIN, INNER_SIZE_Y_MIN])
             .with_max_inner_size([INNER_SIZE_X_MAX, INNER_SIZE_Y_MAX]),
         ..Default::default()
-    };
+    }
+}
 
-    eframe::run_native(
-        HEADING,
-        options,
-        // Box::new(|_cc| Ok(Box::<MyApp>::default())),
-        Box::new(|cc| Ok(app_creator(cc))),
-    )
+fn app_creator(cc: &eframe::CreationContext<'_>, db: Db, ruta_db: String) -> Box<dyn eframe::App> {
+    let app = MyApp::new(db, ruta_db, &cc.egui_ctx);
+    app.apply_settings(&cc.egui_ctx);
+    Box::new(app)
 }
 
-fn app_creator(cc: &eframe::CreationContext<'_>) -> Box<dyn eframe::App> {
-    cc.egui_ctx.set_visuals(egui::Visuals::dark());
+// Minutos y tareas de una meta; hasta `max_minutos` y 100 tareas
+fn goal_inputs(ui: &mut Ui, t: &Textos, meta: &mut Meta, max_minutos: i32) {
+    let mut minutos = meta.segundos / 60;
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::DragValue::new(&mut minutos)
+                .suffix(t.minutos_sufijo)
+                .range(0..=max_minutos),
+        );
+        ui.add(
+            egui::DragValue::new(&mut meta.tareas)
+                .suffix(t.tareas_sufijo)
+                .range(0..=100),
+        );
+    });
+    meta.segundos = minutos * 60;
+}
 
-    // Fonts
-    {
-        let mut style = (*cc.egui_ctx.style()).clone();
-        style.text_styles = [
-            (
-                TextStyle::Heading,
-                FontId::new(24.0, FontFamily::Proportional),
-            ),
-            (TextStyle::Body, FontId::new(16.0, FontFamily::Proportional)),
-            (
-                TextStyle::Button,
-                FontId::new(16.0, FontFamily::Proportional),
-            ),
-            (
-                TextStyle::Small,
-                FontId::new(12.0, FontFamily::Proportional),
-            ),
-            (
-                TextStyle::Monospace,
-                FontId::new(14.0, FontFamily::Monospace),
-            ),
-        ]
-        .into();
-        cc.egui_ctx.set_style(style);
+// Barras de progreso de las partes activas de una meta
+fn goal_progress(ui: &mut Ui, meta: &Meta, hecho: &ResumenDia) {
+    if meta.segundos > 0 {
+        ui.add(
+            egui::ProgressBar::new(hecho.segundos as f32 / meta.segundos as f32)
+                .desired_width(160.0)
+                .text(format!(
+                    "⏱ {} / {}",
+                    formatear_duracion(hecho.segundos),
+                    formatear_duracion(meta.segundos)
+                )),
+        );
+    }
+    if meta.tareas > 0 {
+        ui.add(
+            egui::ProgressBar::new(hecho.completadas as f32 / meta.tareas as f32)
+                .desired_width(160.0)
+                .text(format!("✅ {} / {}", hecho.completadas, meta.tareas)),
+        );
     }
+}
 
-    let app = MyApp::default();
-    Box::new(app)
+// Tamaños de letra base multiplicados por la escala elegida en los ajustes
+fn text_styles(escala: f32) -> std::collections::BTreeMap<TextStyle, FontId> {
+    [
+        (TextStyle::Heading, 24.0, FontFamily::Proportional),
+        (TextStyle::Body, 16.0, FontFamily::Proportional),
+        (TextStyle::Button, 16.0, FontFamily::Proportional),
+        (TextStyle::Small, 12.0, FontFamily::Proportional),
+        (TextStyle::Monospace, 14.0, FontFamily::Monospace),
+    ]
+    .into_iter()
+    .map(|(estilo, tamano, familia)| (estilo, FontId::new(tamano * escala, familia)))
+    .collect()
+}
+
+/// Acciones sobre todas las tareas seleccionadas
+#[derive(Clone, Copy)]
+enum Bulk {
+    Complete,
+    Uncomplete,
+    Delete,
+    Archive,
+    ResetTimer,
+}
+
+#[derive(Clone, Copy)]
+enum PaletteEntry {
+    AddTask,
+    Export,
+    ToggleTheme,
+    Settings,
+    ClearCompleted,
+    Archive,
+    Trash,
+    Order(Orden),
+    Reload,
+    Sync,
+    ClearSearch,
+    Run(Action),
+    Task(i32),
 }
 
 struct MyApp {
-    db: Db,
-    todos: Vec<TodoItem>,
+    servicio: ServicioTareas,
+    totales: Totales,
+    /// Altura medida de cada fila (por id de tarea) para la lista virtualizada
+    row_heights: HashMap<i32, f32>,
     nueva_tarea: String,
-    drag_index: Option<usize>,
-    editing_index: Option<usize>,
-    edit_text: String,
+    /// Orden, prioridad mínima y resultados de `busqueda`
+    filtro: Filtro,
+    busqueda: String,
+    /// Selección, edición, arrastre… por id de tarea, no por posición
+    estado: EstadoLista,
+    scroll_to_selected: bool,
+    edicion: Edicion,
+    /// Estimación en minutos mientras se edita (0 = sin estimación)
+    edit_estimacion: u32,
+    meta: Meta,
+    meta_semanal: Meta,
+    resumen_dias: Vec<ResumenDia>,
+    hoy: i64,
+    bindings: KeyBindings,
+    show_help: bool,
+    /// Acción cuyo atajo se está grabando en la ventana de ayuda
+    recording_shortcut: Option<Action>,
+    focus_new_task: bool,
+    focus_edit: bool,
+    palette: Option<Palette>,
+    idioma: Idioma,
+    textos: &'static Textos,
+    ajustes: Ajustes,
+    show_settings: bool,
+    /// Ruta de la base de datos mientras se edita en los ajustes
+    ruta_db: String,
+    /// Tareas (por id) a la espera de confirmar su borrado
+    pending_delete: Vec<i32>,
+    /// Tarea marcada o desmarcada en su fila, que se guarda tras dibujar la
+    /// lista: al desmarcarla puede quitarse otra
+    pending_check: Option<(i32, bool)>,
+    /// Tareas archivadas mientras la ventana del archivo está abierta
+    archivadas: Option<Vec<TodoItem>>,
+    /// Tareas de la papelera mientras su ventana está abierta
+    papelera: Option<Vec<TodoItem>>,
+    /// Mensaje breve bajo la cabecera (p. ej. resultado de exportar)
+    status: Option<String>,
+    /// Momento de la última copia de seguridad automática
+    last_backup: Instant,
+    /// Copia de seguridad automática en curso en su propio hilo
+    backup_job: Option<JoinHandle<Result<PathBuf, ErrorCopia>>>,
+    /// Carpeta de sincronización mientras se edita en los ajustes
+    carpeta_sync: String,
+    /// Si hay carpeta de sincronización guardada; se vuelve a leer cuando
+    /// cambia la base de datos, no en cada fotograma
+    sync_enabled: bool,
+    last_sync: Instant,
+    /// Hilo que despierta la ventana cuando cambia la base de datos
+    watcher: Option<DatabaseWatcher>,
 }
 
-impl Default for MyApp {
-    fn default() -> Self {
-        let db = Db::new("tareas.db").unwrap();
-        let todos = db.cargar_tareas().unwrap_or_else(|_| Vec::new());
-        Self {
-            db,
-            todos,
-            nueva_tarea: String::new(),
-            drag_index: None,
-            editing_index: None,
-            edit_text: String::new(),
-        }
+/// Para su hilo al soltarlo, p. ej. al abrir otra base de datos
+struct DatabaseWatcher {
+    stop: Arc<AtomicBool>,
+    /// Puesto por el hilo al ver un cambio, propio o de otra instancia
+    changed: Arc<AtomicBool>,
+}
+
+impl DatabaseWatcher {
+    // Sin entrada egui no vuelve a llamar a `update`, así que los cambios de
+    // otras instancias se vigilan desde otro hilo que solo pide repintar
+    fn start(db: &Db, ctx: &egui::Context) -> Option<Self> {
+        let mut vigilante = db.vigilar_cambios().ok().flatten()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let changed = Arc::new(AtomicBool::new(false));
+        let (parar, cambiada) = (Arc::clone(&stop), Arc::clone(&changed));
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            while !parar.load(Ordering::Relaxed) {
+                std::thread::sleep(EXTERNAL_CHANGES_INTERVAL);
+                match vigilante.hay_cambios() {
+                    Ok(true) => {
+                        cambiada.store(true, Ordering::Relaxed);
+                        ctx.request_repaint();
+                    }
+                    Ok(false) => {}
+                    Err(_) => break,
+                }
+            }
+        });
+        Some(Self { stop, changed })
+    }
+
+    // Si la base de datos ha cambiado desde la última llamada
+    fn take_changes(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl Drop for DatabaseWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
     }
 }
 
 impl MyApp {
+    fn new(db: Db, ruta_db: String, ctx: &egui::Context) -> Self {
+        let meta = db.cargar_meta(Periodo::Dia).unwrap_or_default();
+        let meta_semanal = db.cargar_meta(Periodo::Semana).unwrap_or_default();
+        let bindings = KeyBindings::load(&db);
+        let idioma = db.idioma();
+        let ajustes = db.cargar_ajustes().unwrap_or_default();
+        let carpeta_sync = db
+            .carpeta_sincronizacion()
+            .ok()
+            .flatten()
+            .map(|carpeta| carpeta.display().to_string())
+            .unwrap_or_default();
+        let sync_enabled = !carpeta_sync.is_empty();
+        let watcher = DatabaseWatcher::start(&db, ctx);
+        let mut app = Self {
+            servicio: ServicioTareas::new(db),
+            totales: Totales::default(),
+            row_heights: HashMap::new(),
+            nueva_tarea: String::new(),
+            filtro: Filtro::default(),
+            busqueda: String::new(),
+            estado: EstadoLista::default(),
+            scroll_to_selected: false,
+            edicion: Edicion::default(),
+            edit_estimacion: 0,
+            meta,
+            meta_semanal,
+            resumen_dias: Vec::new(),
+            hoy: 0,
+            bindings,
+            show_help: false,
+            recording_shortcut: None,
+            focus_new_task: false,
+            focus_edit: false,
+            palette: None,
+            idioma,
+            textos: idioma.textos(),
+            ajustes,
+            show_settings: false,
+            ruta_db,
+            pending_delete: Vec::new(),
+            pending_check: None,
+            archivadas: None,
+            papelera: None,
+            status: None,
+            last_backup: Instant::now(),
+            backup_job: None,
+            carpeta_sync,
+            sync_enabled,
+            last_sync: Instant::now(),
+            watcher,
+        };
+        app.backup();
+        app.sync(false);
+        app.refresh_stats();
+        app
+    }
+
     fn todo_at(&self, idx: usize) -> &TodoItem {
-        &self.todos[idx]
+        &self.servicio.tareas()[idx]
     }
 
-    fn needed_height(num_tareas: usize) -> f32 {
-        let task_height = TASK_Y;
-        let header_height = HEADER_Y;
-        let stats_height = STATS_Y;
-        let margin = 90.;
-        let needed_height =
-            header_height + (num_tareas as f32 * task_height) + stats_height + margin;
-        eprintln!("{}", needed_height);
-        needed_height
+    // Posición de la tarea `id` dentro de `visibles`
+    fn position_in(&self, visibles: &[usize], id: Option<i32>) -> Option<usize> {
+        let id = id?;
+        visibles.iter().position(|&idx| self.todo_at(idx).id == id)
     }
 
-    // Y := 32 + 5 + 1 + 5 = 43px
-    fn render_header(&self, ui: &mut egui::Ui) {
-        ui.heading(HEADING);
+    // Índices de las tareas a mostrar, filtradas y en el orden elegido
+    fn visible_order(&mut self) -> Vec<usize> {
+        self.servicio.visibles(&self.filtro)
+    }
+
+    fn render_header(&mut self, ui: &mut egui::Ui) {
+        let t = self.textos;
+        ui.horizontal(|ui| {
+            ui.heading(t.titulo);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("⚙").on_hover_text(t.ajustes_titulo).clicked() {
+                    self.show_settings = !self.show_settings;
+                }
+                if ui.button("♻").on_hover_text(t.papelera_titulo).clicked() {
+                    self.toggle_trash();
+                }
+                if ui.button("🗄").on_hover_text(t.archivo_titulo).clicked() {
+                    self.toggle_archive();
+                }
+                if ui.button("⌨").on_hover_text(t.atajos_teclado).clicked() {
+                    self.show_help = !self.show_help;
+                }
+            });
+        });
+        if let Some(status) = &self.status {
+            ui.label(egui::RichText::new(status).weak());
+        }
         ui.add_space(5.0);
         ui.separator();
         ui.add_space(5.0);
     }
 
     fn render_add_task(&mut self, ui: &mut egui::Ui) {
+        let t = self.textos;
         ui.horizontal(|ui| {
-            ui.label("Nueva tarea:");
+            ui.label(t.nueva_tarea);
             let text_edit = ui.text_edit_singleline(&mut self.nueva_tarea);
+            if std::mem::take(&mut self.focus_new_task) {
+                text_edit.request_focus();
+            }
 
             let should_add = (text_edit.lost_focus()
                 && ui.input(|i| i.key_pressed(egui::Key::Enter)))
-                || ui.button("➕ Agregar").clicked();
+                || ui.button(t.agregar).clicked();
 
-            if should_add && !self.nueva_tarea.trim().is_empty() {
-                if self.db.agregar_tarea(&self.nueva_tarea).is_ok() {
-                    self.nueva_tarea.clear();
-                    self.reload_tasks();
-                }
+            if should_add && self.add_task(&self.nueva_tarea.clone()) {
+                self.nueva_tarea.clear();
             }
         });
 
@@ -144,45 +433,62 @@ impl MyApp {
         ui.add_space(5.0);
     }
 
-    // Y :=
-    // Drag icon: 18 px
-    // Text: 16 px
-    // Frame vertical padding: 12 px
-    // Extra spacing (maybe ~3–6 px inside layouts)
-    // ~~ 46
     fn render_task_item(&mut self, ui: &mut egui::Ui, idx: usize) -> bool {
+        let t = self.textos;
         let mut should_delete = false;
 
-        let item_id = egui::Id::new("task").with(idx);
-        let hover_id = egui::Id::new("task_hover").with(idx);
-        let is_being_dragged = self.drag_index == Some(idx);
-        let is_editing = self.editing_index == Some(idx);
+        let id = self.todo_at(idx).id;
+        let item_id = egui::Id::new("task").with(id);
+        let is_being_dragged = self.estado.arrastrando == Some(id);
+        let is_editing = self.estado.editando == Some(id);
+        let is_expanded = self.estado.desplegada == Some(id);
+        let is_selected = self.estado.seleccionada == Some(id);
         let timer_active = self.todo_at(idx).temporizador_activo();
 
-        // Frame con fondo para la tarea - colores para tema oscuro
+        // Frame con fondo para la tarea, con los colores de los ajustes
+        let [r, g, b] = self.ajustes.color_arrastre;
+        let drag_color = Color32::from_rgb(r, g, b);
         let frame = if is_being_dragged {
             egui::Frame::none()
-                .fill(Color32::from_rgba_unmultiplied(70, 130, 180, 80))
-                .stroke(Stroke::new(2.0, Color32::from_rgb(100, 200, 255)))
+                .fill(Color32::from_rgba_unmultiplied(r, g, b, 60))
+                .stroke(Stroke::new(2.0, drag_color))
                 .rounding(5.0)
                 .inner_margin(egui::Margin::same(6.0))
         } else if timer_active {
+            let [r, g, b] = self.ajustes.color_temporizador;
+            let timer_color = Color32::from_rgb(r, g, b);
             egui::Frame::none()
-                .fill(Color32::from_rgb(40, 60, 40))
-                .stroke(Stroke::new(1.5, Color32::from_rgb(100, 200, 100)))
+                .fill(timer_color.gamma_multiply(0.25))
+                .stroke(Stroke::new(1.5, timer_color))
                 .rounding(5.0)
                 .inner_margin(egui::Margin::same(6.0))
         } else {
+            let fondo = if ui.visuals().dark_mode {
+                Color32::from_gray(40)
+            } else {
+                Color32::from_gray(225)
+            };
             egui::Frame::none()
-                .fill(Color32::from_gray(40))
+                .fill(fondo)
                 .rounding(5.0)
                 .inner_margin(egui::Margin::same(6.0))
         };
+        let frame = if self.estado.marcadas.contains(&id) && !is_being_dragged {
+            frame.fill(ui.visuals().selection.bg_fill.gamma_multiply(0.5))
+        } else {
+            frame
+        };
+        let frame = if is_selected && !is_being_dragged {
+            frame.stroke(Stroke::new(1.5, Color32::from_rgb(230, 200, 90)))
+        } else {
+            frame
+        };
 
         let frame_response = frame.show(ui, |ui| {
             ui.horizontal(|ui| {
-                // Ícono de arrastre más visible (solo si no está editando)
-                if !is_editing {
+                // Ícono de arrastre más visible (solo si no está editando
+                // y el orden es manual)
+                if !is_editing && self.filtro.orden == Orden::Manual {
                     ui.vertical(|ui| {
                         ui.add_space(2.0);
                         let drag_label = egui::RichText::new("⣿")
@@ -199,7 +505,7 @@ impl MyApp {
                         }
 
                         if drag_response.drag_started() {
-                            self.drag_index = Some(idx);
+                            self.estado.arrastrando = Some(id);
                         }
 
                         if drag_response.dragged() {
@@ -214,33 +520,56 @@ impl MyApp {
                     // Modo edición
                     let should_save = ui.button("💾").clicked();
                     let should_cancel = ui.button("❌").clicked();
-                    ui.text_edit_singleline(&mut self.edit_text);
+                    ui.add(
+                        egui::DragValue::new(&mut self.edit_estimacion)
+                            .prefix("⏳ ")
+                            .suffix(t.minutos_sufijo)
+                            .range(0..=10_000),
+                    )
+                    .on_hover_text(t.tiempo_estimado_ayuda);
+                    let text_edit = ui.text_edit_singleline(&mut self.edicion.descripcion);
+                    if std::mem::take(&mut self.focus_edit) {
+                        text_edit.request_focus();
+                    }
+                    let (enter, escape) = ui.input(|i| {
+                        (
+                            i.key_pressed(egui::Key::Enter),
+                            i.key_pressed(egui::Key::Escape),
+                        )
+                    });
+                    let should_save = should_save || (text_edit.lost_focus() && enter);
+                    let should_cancel = should_cancel || (text_edit.lost_focus() && escape);
 
                     if should_save {
-                        let new_text = self.edit_text.clone();
-                        if !new_text.trim().is_empty() {
-                            let todo = &mut self.todos[idx];
-                            let todo_id = todo.id;
-                            if self.db.actualizar_descripcion(todo_id, &new_text).is_ok() {
-                                todo.text = new_text;
+                        self.edicion.estimacion =
+                            (self.edit_estimacion > 0).then(|| self.edit_estimacion as i32 * 60);
+                        match self.servicio.editar(id, &self.edicion) {
+                            Ok(ResultadoEdicion::Guardada) => {
+                                self.estado.editando = None;
+                                self.refresh_search();
+                                self.refresh_stats();
+                            }
+                            // La fila ya muestra lo que guardó la otra instancia
+                            Ok(ResultadoEdicion::Conflicto) => {
+                                self.estado.editando = None;
+                                self.status = Some(t.conflicto_edicion.to_string());
+                                self.refresh_search();
                             }
-                            self.editing_index = None;
-                            self.edit_text.clear();
+                            Ok(ResultadoEdicion::Vacia) | Err(_) => {}
                         }
                     }
 
                     if should_cancel {
-                        self.editing_index = None;
-                        self.edit_text.clear();
+                        self.estado.editando = None;
                     }
                 } else {
                     // Modo normal
-                    let todo = &mut self.todos[idx];
-                    let checked_before = todo.checked;
+                    let mut checked = self.todo_at(idx).checked;
+                    let toggled = ui.checkbox(&mut checked, "").changed();
+                    let todo = self.todo_at(idx);
 
-                    ui.checkbox(&mut todo.checked, "");
-
-                    // add text label
+                    // add text label (clic para desplegar las notas)
+                    let mut text_clicked = false;
                     {
                         // Reserve space on the right for the controls (refresh/delete/etc.)
                         let reserved_for_controls = 220.0_f32;
@@ -256,15 +585,40 @@ impl MyApp {
                                 ui.with_layout(
                                     egui::Layout::left_to_right(egui::Align::Min),
                                     |ui| {
-                                        ui.add(egui::Label::new(todo.text.clone()).truncate());
+                                        render_due_badge(ui, t, todo);
+                                        if !todo.notas.is_empty() {
+                                            ui.label(egui::RichText::new("📝").small());
+                                        }
+                                        text_clicked = ui
+                                            .add(
+                                                egui::Label::new(todo.text.clone())
+                                                    .truncate()
+                                                    .sense(egui::Sense::click()),
+                                            )
+                                            .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                            .clicked();
                                     },
                                 );
                             },
                         );
                     }
 
-                    if checked_before != todo.checked {
-                        let _ = self.db.actualizar_tarea(todo.id, todo.checked);
+                    if toggled {
+                        // Se guarda en render_tasks, fuera del bucle
+                        self.pending_check = Some((id, checked));
+                    }
+
+                    if text_clicked {
+                        let modifiers = ui.input(|i| i.modifiers);
+                        if modifiers.command {
+                            self.toggle_mark(id);
+                        } else if modifiers.shift {
+                            self.mark_range(id);
+                        } else {
+                            self.estado.desplegada = if is_expanded { None } else { Some(id) };
+                            self.estado.seleccionada = Some(id);
+                            self.estado.marcadas.clear();
+                        }
                     }
 
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -272,35 +626,57 @@ impl MyApp {
                     });
                 }
             });
-        });
 
-        // Guardar la posición del rect para detección en tiempo real
-        ui.ctx().memory_mut(|mem| {
-            mem.data.insert_temp(hover_id, frame_response.response.rect);
+            if is_editing {
+                self.render_edit_details(ui);
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.edicion.notas)
+                        .hint_text(t.notas_ayuda)
+                        .desired_rows(4)
+                        .desired_width(f32::INFINITY),
+                );
+            } else {
+                self.render_estimate_progress(ui, idx);
+                if is_expanded {
+                    self.render_task_notes(ui, idx);
+                }
+            }
         });
 
-        // Detectar hover para reordenar con línea más visible y zona de drop
-        if let Some(drag_idx) = self.drag_index {
-            if frame_response.response.hovered() && drag_idx != idx {
-                let rect = frame_response.response.rect;
-
-                // Fondo semitransparente para toda el área de drop
-                ui.painter().rect_filled(
-                    rect,
-                    5.0,
-                    Color32::from_rgba_unmultiplied(100, 200, 255, 30),
-                );
+        // Franja de color a la izquierda según la prioridad
+        if let Some(color) = priority_color(self.todo_at(idx).prioridad) {
+            let rect = frame_response.response.rect;
+            ui.painter().rect_filled(
+                egui::Rect::from_min_size(rect.left_top(), egui::vec2(4.0, rect.height())),
+                egui::Rounding {
+                    nw: 5.0,
+                    sw: 5.0,
+                    ..Default::default()
+                },
+                color,
+            );
+        }
 
-                // Línea indicadora de posición de drop más gruesa y visible
-                ui.painter().rect_filled(
-                    egui::Rect::from_min_size(
-                        egui::pos2(rect.left(), rect.top() - 3.0),
-                        egui::vec2(rect.width(), 6.0),
-                    ),
-                    3.0,
-                    Color32::from_rgb(100, 200, 255),
-                );
-            }
+        // Detectar hover para reordenar con línea más visible y zona de drop
+        if let Some(drag_id) = self.estado.arrastrando
+            && frame_response.response.hovered()
+            && drag_id != id
+        {
+            let rect = frame_response.response.rect;
+
+            // Fondo semitransparente para toda el área de drop
+            ui.painter()
+                .rect_filled(rect, 5.0, Color32::from_rgba_unmultiplied(r, g, b, 30));
+
+            // Línea indicadora de posición de drop más gruesa y visible
+            ui.painter().rect_filled(
+                egui::Rect::from_min_size(
+                    egui::pos2(rect.left(), rect.top() - 3.0),
+                    egui::vec2(rect.width(), 6.0),
+                ),
+                3.0,
+                drag_color,
+            );
         }
 
         ui.add_space(3.0);
@@ -312,9 +688,7 @@ impl MyApp {
         let mut should_delete = false;
 
         if ui.button("✏️").clicked() {
-            let todo = self.todo_at(idx);
-            self.edit_text = todo.text.clone();
-            self.editing_index = Some(idx);
+            self.start_editing(self.todo_at(idx).id);
         }
 
         if ui.button("🗑").clicked() {
@@ -325,139 +699,1624 @@ impl MyApp {
         self.render_timer_controls(ui, idx);
 
         if ui.button("🔄").clicked() {
-            let todo = &mut self.todos[idx];
-            todo.resetear_temporizador(&self.db);
+            self.reset_timer(self.todo_at(idx).id);
         }
 
         should_delete
     }
 
-    fn render_timer_display(&self, ui: &mut Ui, idx: usize) {
-        let todo = self.todo_at(idx);
-        let tiempo_total = todo.tiempo_total();
-        let horas = tiempo_total / 3600;
-        let minutos = (tiempo_total % 3600) / 60;
-        let segundos = tiempo_total % 60;
+    fn add_task(&mut self, descripcion: &str) -> bool {
+        if !matches!(self.servicio.agregar(descripcion), Ok(Some(_))) {
+            return false;
+        }
+        self.refresh_stats();
+        self.refresh_search();
+        true
+    }
 
-        ui.label(format!("⏱ {:02}:{:02}:{:02}", horas, minutos, segundos));
+    fn export_tasks(&mut self) {
+        self.servicio.cargar_todo();
+        let contenido = pixi::exportar::a_markdown(self.servicio.tareas());
+        let t = self.textos;
+        self.status = Some(match std::fs::write(EXPORT_PATH, contenido) {
+            Ok(()) => rellenar(t.exportado, &[&EXPORT_PATH]),
+            Err(err) => rellenar(t.error_exportar, &[&err]),
+        });
     }
 
-    fn render_timer_controls(&mut self, ui: &mut Ui, idx: usize) {
-        let todo = &mut self.todos[idx];
-        if todo.temporizador_activo() {
-            if ui.button("⏸").clicked() {
-                todo.pausar_temporizador(&self.db);
-            }
+    fn set_language(&mut self, ctx: &egui::Context, idioma: Idioma) {
+        if self.servicio.db().guardar_idioma(idioma).is_ok() {
+            self.idioma = idioma;
+            self.textos = idioma.textos();
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(self.textos.titulo.to_string()));
+        }
+    }
+
+    fn toggle_theme(&mut self, ctx: &egui::Context) {
+        let tema = if ctx.style().visuals.dark_mode {
+            Tema::Claro
         } else {
-            if ui.button("▶").clicked() {
-                todo.iniciar_temporizador();
+            Tema::Oscuro
+        };
+        self.set_settings(
+            ctx,
+            Ajustes {
+                tema,
+                ..self.ajustes
+            },
+        );
+    }
+
+    fn apply_settings(&self, ctx: &egui::Context) {
+        ctx.set_theme(match self.ajustes.tema {
+            Tema::Oscuro => egui::ThemePreference::Dark,
+            Tema::Claro => egui::ThemePreference::Light,
+            Tema::Sistema => egui::ThemePreference::System,
+        });
+        let escala = self.ajustes.escala_fuente;
+        ctx.all_styles_mut(|style| style.text_styles = text_styles(escala));
+    }
+
+    fn set_settings(&mut self, ctx: &egui::Context, ajustes: Ajustes) {
+        if self.servicio.db().guardar_ajustes(&ajustes).is_ok() {
+            self.ajustes = ajustes;
+            self.apply_settings(ctx);
+        }
+    }
+
+    // Cambia a otra base de datos y la recuerda para el próximo arranque
+    fn open_database(&mut self, ctx: &egui::Context) {
+        let t = self.textos;
+        let ruta = self.ruta_db.trim().to_string();
+        let db = match Db::new(&ruta) {
+            Ok(db) => db,
+            Err(err) => {
+                self.status = Some(rellenar(t.error_abrir_db, &[&ruta, &err]));
+                return;
             }
+        };
+        self.servicio.pausar_todos();
+
+        *self = MyApp::new(db, ruta.clone(), ctx);
+        self.show_settings = true;
+        self.apply_settings(ctx);
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(self.textos.titulo.to_string()));
+        if let Err(err) = ajustes::guardar_ruta_db(&ruta) {
+            self.status = Some(rellenar(
+                self.textos.error_guardar_config,
+                &[&ajustes::RUTA_CONFIGURACION, &err],
+            ));
         }
     }
 
-    fn render_tasks(&mut self, ui: &mut egui::Ui) {
-        let mut tarea_a_eliminar: Option<usize> = None;
-        let mut hover_target: Option<usize> = None;
+    // Borra directamente o, si así se ha configurado, pide confirmación antes
+    fn request_delete(&mut self, ids: Vec<i32>) {
+        if self.ajustes.confirmar_borrado {
+            self.pending_delete = ids;
+        } else {
+            self.delete_tasks(&ids);
+        }
+    }
+
+    fn start_editing(&mut self, id: i32) {
+        let Some(todo) = self.servicio.tarea(id) else {
+            return;
+        };
+        self.edicion = Edicion::desde(todo);
+        self.edit_estimacion = todo.estimacion.map_or(0, |e| (e / 60) as u32);
+        self.estado.editando = Some(id);
+        self.focus_edit = true;
+    }
 
-        for idx in 0..self.todos.len() {
-            if self.render_task_item(ui, idx) {
-                tarea_a_eliminar = Some(idx);
+    // Marca la tarea `id` como completada o pendiente. Al desmarcarla
+    // puede quitarse otra tarea, así que no se llama mientras se recorre la
+    // lista.
+    fn set_checked(&mut self, id: i32, checked: bool) {
+        match self.servicio.marcar(id, checked) {
+            // Nueva ocurrencia de una tarea recurrente
+            Ok(Some(_)) => self.refresh_search(),
+            // Puede haberse quitado la que se creó al completarla
+            Ok(None) if !checked => {
+                self.forget_missing();
+                self.refresh_search();
+                self.refresh_trash();
             }
+            _ => {}
         }
+        self.refresh_stats();
+    }
 
-        // Detectar sobre qué tarea está el cursor mientras arrastra
-        if let Some(drag_idx) = self.drag_index {
-            for idx in 0..self.todos.len() {
-                // Obtener el rect de la tarea
-                let task_hover_id = egui::Id::new("task_hover").with(idx);
-                if let Some(rect) = ui
-                    .ctx()
-                    .memory(|mem| mem.data.get_temp::<egui::Rect>(task_hover_id))
-                {
-                    if let Some(hover_pos) = ui.input(|i| i.pointer.hover_pos()) {
-                        if rect.contains(hover_pos) {
-                            hover_target = Some(idx);
-                            break;
+    fn toggle_timer(&mut self, id: i32) {
+        if !self.servicio.alternar_temporizador(id) {
+            self.refresh_stats();
+        }
+    }
+
+    fn reset_timer(&mut self, id: i32) {
+        self.servicio.resetear_temporizador(id);
+        self.refresh_stats();
+    }
+
+    fn render_task_notes(&self, ui: &mut Ui, idx: usize) {
+        ui.separator();
+        let notas = &self.todo_at(idx).notas;
+        if notas.trim().is_empty() {
+            ui.label(egui::RichText::new(self.textos.sin_notas).weak());
+        } else {
+            markdown::render(ui, notas);
+        }
+    }
+
+    // Segunda fila del modo edición: prioridad y repetición
+    fn render_edit_details(&mut self, ui: &mut Ui) {
+        let t = self.textos;
+        ui.horizontal(|ui| {
+            priority_combo(ui, t, "prioridad", &mut self.edicion.prioridad);
+            ui.separator();
+
+            ui.label("🔁");
+            let actual = &mut self.edicion.recurrencia;
+
+            egui::ComboBox::from_id_salt("recurrencia")
+                .selected_text(recurrence_kind(t, actual.as_ref()))
+                .show_ui(ui, |ui| {
+                    let opciones = [
+                        None,
+                        Some(Recurrencia::Diaria),
+                        Some(Recurrencia::Semanal([
+                            true, true, true, true, true, false, false,
+                        ])),
+                        Some(Recurrencia::Mensual(1)),
+                        Some(Recurrencia::CadaNDias(7)),
+                    ];
+                    for opcion in opciones {
+                        let mismo_tipo = actual.as_ref().map(std::mem::discriminant)
+                            == opcion.as_ref().map(std::mem::discriminant);
+                        let texto = recurrence_kind(t, opcion.as_ref());
+                        if ui.selectable_label(mismo_tipo, texto).clicked() && !mismo_tipo {
+                            *actual = opcion;
                         }
                     }
+                });
+
+            match actual {
+                Some(Recurrencia::Semanal(dias)) => {
+                    for (dia, letra) in dias.iter_mut().zip(t.dias_semana) {
+                        ui.toggle_value(dia, letra);
+                    }
                 }
+                Some(Recurrencia::Mensual(dia)) => {
+                    ui.add(
+                        egui::DragValue::new(dia)
+                            .prefix(t.dia_prefijo)
+                            .range(1..=31),
+                    );
+                }
+                Some(Recurrencia::CadaNDias(n)) => {
+                    ui.add(
+                        egui::DragValue::new(n)
+                            .prefix(t.cada_prefijo)
+                            .suffix(t.dias_sufijo)
+                            .range(1..=365),
+                    );
+                }
+                Some(Recurrencia::Diaria) | None => {}
+            }
+        });
+    }
+
+    fn render_timer_display(&self, ui: &mut Ui, idx: usize) {
+        let todo = self.todo_at(idx);
+        ui.label(format!("⏱ {}", formatear_duracion(todo.tiempo_total())));
+    }
+
+    // Barra fina bajo la tarea; roja cuando se supera la estimación
+    fn render_estimate_progress(&self, ui: &mut Ui, idx: usize) {
+        let todo = self.todo_at(idx);
+        let (Some(estimacion), Some(progreso)) = (todo.estimacion, todo.progreso_estimacion())
+        else {
+            return;
+        };
+
+        let color = if todo.excede_estimacion() {
+            Color32::from_rgb(200, 60, 60)
+        } else {
+            Color32::from_rgb(70, 130, 180)
+        };
+
+        ui.add(
+            egui::ProgressBar::new(progreso.min(1.0))
+                .desired_height(4.0)
+                .fill(color),
+        )
+        .on_hover_text(format!(
+            "{} / {} ({:.0}%)",
+            formatear_duracion(todo.tiempo_total()),
+            formatear_duracion(estimacion),
+            progreso * 100.0
+        ));
+    }
+
+    fn render_timer_controls(&mut self, ui: &mut Ui, idx: usize) {
+        let icono = if self.todo_at(idx).temporizador_activo() {
+            "⏸"
+        } else {
+            "▶"
+        };
+        if ui.button(icono).clicked() {
+            self.toggle_timer(self.todo_at(idx).id);
+        }
+    }
+
+    fn render_list_controls(&mut self, ui: &mut egui::Ui) {
+        let t = self.textos;
+        ui.horizontal(|ui| {
+            ui.label(t.orden);
+            egui::ComboBox::from_id_salt("orden")
+                .selected_text(match self.filtro.orden {
+                    Orden::Manual => t.orden_manual,
+                    Orden::Prioridad => t.orden_prioridad,
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.filtro.orden, Orden::Manual, t.orden_manual);
+                    ui.selectable_value(
+                        &mut self.filtro.orden,
+                        Orden::Prioridad,
+                        t.orden_prioridad,
+                    );
+                });
+
+            ui.label(t.prioridad_minima);
+            priority_combo(ui, t, "filtro_prioridad", &mut self.filtro.prioridad_minima);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("🔍");
+            let busqueda =
+                ui.add(egui::TextEdit::singleline(&mut self.busqueda).hint_text(t.buscar_ayuda));
+            if !self.busqueda.is_empty() && ui.button("✖").clicked() {
+                self.busqueda.clear();
+                self.refresh_search();
             }
+            if busqueda.changed() {
+                self.refresh_search();
+            }
+        });
 
-            // Mover en tiempo real si hay hover sobre otra tarea
-            if let Some(target_idx) = hover_target {
-                if drag_idx != target_idx {
-                    let item = self.todos.remove(drag_idx);
-                    self.todos.insert(target_idx, item);
-                    // Actualizar el índice de drag a la nueva posición
-                    self.drag_index = Some(target_idx);
+        if !self.estado.marcadas.is_empty() {
+            self.render_bulk_actions(ui);
+        }
+
+        ui.add_space(5.0);
+    }
+
+    fn row_height(&self, idx: usize) -> f32 {
+        self.row_heights
+            .get(&self.todo_at(idx).id)
+            .copied()
+            .unwrap_or(ROW_HEIGHT_ESTIMATE)
+    }
+
+    // Solo se dibujan las filas que caen en la zona visible; el resto se
+    // sustituye por espacio vacío según la altura medida de cada una
+    fn render_tasks(&mut self, ui: &mut egui::Ui) {
+        let visibles = self.visible_order();
+        let alturas: Vec<f32> = visibles.iter().map(|&idx| self.row_height(idx)).collect();
+        let mut tarea_a_eliminar: Option<i32> = None;
+        // Filas dibujadas y su rect en pantalla, para el arrastre
+        let mut filas: Vec<(usize, egui::Rect)> = Vec::new();
+        let mut final_visible = false;
+
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show_viewport(ui, |ui, viewport| {
+                ui.set_min_height(alturas.iter().sum());
+                let origen = ui.max_rect().top();
+
+                if std::mem::take(&mut self.scroll_to_selected)
+                    && let Some(pos) = self.position_in(&visibles, self.estado.seleccionada)
+                {
+                    let arriba: f32 = alturas[..pos].iter().sum();
+                    let rect = egui::Rect::from_min_size(
+                        egui::pos2(ui.max_rect().left(), origen + arriba),
+                        egui::vec2(ui.max_rect().width(), alturas[pos]),
+                    );
+                    ui.scroll_to_rect(rect, None);
+                }
+
+                // Saltar las filas que quedan por encima
+                let mut pos = 0;
+                let mut saltado = 0.0;
+                while pos < visibles.len() && saltado + alturas[pos] < viewport.min.y {
+                    saltado += alturas[pos];
+                    pos += 1;
                 }
+                ui.add_space(saltado);
+
+                while pos < visibles.len() && ui.cursor().top() - origen < viewport.max.y {
+                    let idx = visibles[pos];
+                    let arriba = ui.cursor().top();
+                    if self.render_task_item(ui, idx) {
+                        tarea_a_eliminar = Some(self.todo_at(idx).id);
+                    }
+                    let rect = egui::Rect::from_x_y_ranges(
+                        ui.max_rect().x_range(),
+                        arriba..=ui.cursor().top(),
+                    );
+                    self.row_heights.insert(self.todo_at(idx).id, rect.height());
+                    filas.push((idx, rect.intersect(ui.clip_rect())));
+                    pos += 1;
+                }
+                final_visible = pos == visibles.len();
+            });
+
+        // Al asomar el final de la lista se lee la siguiente página
+        if final_visible && !self.servicio.todo_cargado() {
+            self.servicio.cargar_mas();
+            ui.ctx().request_repaint();
+        }
+
+        // Detectar sobre qué tarea está el cursor mientras arrastra
+        if let Some(drag_id) = self.estado.arrastrando {
+            let hover_target = ui.input(|i| i.pointer.hover_pos()).and_then(|hover_pos| {
+                filas
+                    .iter()
+                    .find(|(_, rect)| rect.contains(hover_pos))
+                    .map(|&(idx, _)| self.todo_at(idx).id)
+            });
+
+            // Mover en tiempo real si hay hover sobre otra tarea; el drag
+            // sigue apuntando a la misma tarea en su nueva posición
+            if let Some(target_id) = hover_target
+                && drag_id != target_id
+            {
+                self.servicio.mover(drag_id, target_id);
             }
 
             // Liberar cuando se suelta el mouse
             if ui.input(|i| i.pointer.any_released()) {
-                self.drag_index = None;
+                self.estado.arrastrando = None;
             }
         }
 
-        if let Some(idx) = tarea_a_eliminar {
-            self.delete_task(idx);
+        // Fuera del bucle, porque al desmarcar puede quitarse otra tarea
+        if let Some((id, checked)) = self.pending_check.take() {
+            self.set_checked(id, checked);
+        }
+        if let Some(id) = tarea_a_eliminar {
+            self.request_delete(vec![id]);
         }
     }
 
-    // Y := 10 + 1 + 5 + 4 * 18 = 72
     fn render_statistics(&mut self, ui: &mut egui::Ui) {
         ui.add_space(10.0);
         ui.separator();
         ui.add_space(5.0);
 
-        let tiempo_total_segundos: i32 = self.todos.iter().map(|t| t.tiempo_total()).sum();
-
-        let horas_total = tiempo_total_segundos / 3600;
-        let minutos_total = (tiempo_total_segundos % 3600) / 60;
-        let segundos_total = tiempo_total_segundos % 60;
+        ui.horizontal_top(|ui| {
+            ui.vertical(|ui| self.render_totals(ui));
+            ui.separator();
+            ui.vertical(|ui| self.render_goals(ui));
+        });
+    }
 
-        let total = self.todos.len();
-        let completed = self.todos.iter().filter(|t| t.checked).count();
+    fn render_totals(&mut self, ui: &mut egui::Ui) {
+        let t = self.textos;
+        let Totales {
+            tareas: total,
+            completadas: completed,
+            segundos: tiempo_total_segundos,
+            real,
+            estimado,
+        } = self.totales.con_sesiones(self.servicio.tareas());
         let pending = total - completed;
 
-        ui.label(format!("📊 Total: {}", total));
-        ui.label(format!("✅ Completadas: {}", completed));
-        ui.label(format!("⏳ Pendientes: {}", pending));
-        ui.label(format!(
-            "⏱️ Tiempo total: {:02}:{:02}:{:02}",
-            horas_total, minutos_total, segundos_total
+        ui.label(rellenar(t.total, &[&total]));
+        ui.label(rellenar(t.completadas, &[&completed]));
+        ui.label(rellenar(t.pendientes, &[&pending]));
+        ui.label(rellenar(
+            t.tiempo_total,
+            &[&formatear_duracion(tiempo_total_segundos)],
         ));
 
-        if ui.button("🔄 Recargar tareas").clicked() {
-            self.reload_tasks();
+        // Precisión de las estimaciones sobre las tareas completadas
+        if estimado > 0 {
+            ui.label(rellenar(
+                t.real_vs_estimado,
+                &[
+                    &formatear_duracion(real),
+                    &formatear_duracion(estimado),
+                    &format!("{:.0}", real as f32 / estimado as f32 * 100.0),
+                ],
+            ));
+        } else {
+            ui.label(t.real_vs_estimado_sin_datos);
         }
+
+        ui.horizontal(|ui| {
+            if ui.button(t.recargar).clicked() {
+                self.reload_tasks();
+            }
+            if ui.button(t.limpiar_completadas).clicked() {
+                self.clear_completed();
+            }
+        });
+    }
+
+    fn render_goals(&mut self, ui: &mut egui::Ui) {
+        let t = self.textos;
+        let hoy = self.today_summary();
+
+        ui.label(t.meta_diaria);
+        let mut meta = self.meta;
+        goal_inputs(ui, t, &mut meta, 24 * 60);
+        if meta != self.meta && self.servicio.db().guardar_meta(Periodo::Dia, &meta).is_ok() {
+            self.meta = meta;
+        }
+        goal_progress(ui, &meta, &hoy);
+        if meta.activa() {
+            let racha = meta.racha(Periodo::Dia, &self.resumen_dias, &hoy);
+            ui.label(rellenar(t.racha, &[&racha]));
+        }
+
+        ui.label(t.meta_semanal);
+        let mut meta = self.meta_semanal;
+        goal_inputs(ui, t, &mut meta, 7 * 24 * 60);
+        if meta != self.meta_semanal
+            && self
+                .servicio
+                .db()
+                .guardar_meta(Periodo::Semana, &meta)
+                .is_ok()
+        {
+            self.meta_semanal = meta;
+        }
+        let semana = Periodo::Semana.resumen(&self.resumen_dias, &hoy);
+        goal_progress(ui, &meta, &semana);
+        if meta.activa() {
+            let racha = meta.racha(Periodo::Semana, &self.resumen_dias, &hoy);
+            ui.label(rellenar(t.racha_semanas, &[&racha]));
+        }
+    }
+
+    // Progreso de hoy, incluyendo los temporizadores en marcha
+    fn today_summary(&self) -> ResumenDia {
+        let guardado = self.resumen_dias.iter().find(|r| r.dia == self.hoy);
+        let en_curso: i32 = self
+            .servicio
+            .tareas()
+            .iter()
+            .map(|t| t.tiempo_sesion())
+            .sum();
+        ResumenDia {
+            dia: self.hoy,
+            segundos: guardado.map_or(0, |r| r.segundos) + en_curso,
+            completadas: guardado.map_or(0, |r| r.completadas),
+        }
+    }
+
+    fn refresh_search(&mut self) {
+        self.filtro.coincidencias = if self.busqueda.trim().is_empty() {
+            None
+        } else {
+            Some(
+                self.servicio
+                    .db()
+                    .buscar_tareas(&self.busqueda)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect(),
+            )
+        };
+    }
+
+    fn refresh_stats(&mut self) {
+        self.totales = self.servicio.db().totales().unwrap_or_default();
+        self.resumen_dias = self.servicio.db().resumen_por_dia().unwrap_or_default();
+        self.hoy = self.servicio.db().dia_actual().unwrap_or(self.hoy);
     }
 
     fn reload_tasks(&mut self) {
-        self.todos = self.db.cargar_tareas().unwrap_or_else(|_| Vec::new());
+        self.servicio.recargar();
+        self.forget_missing();
+        self.refresh_stats();
+        self.refresh_search();
+        self.refresh_archive();
+        self.refresh_trash();
+    }
+
+    // La interfaz no puede seguir apuntando a tareas que ya no están
+    fn forget_missing(&mut self) {
+        self.estado.olvidar_ausentes(self.servicio.tareas());
+    }
+
+    // Archiva las tareas completadas; su tiempo sigue contando en las estadísticas
+    fn clear_completed(&mut self) {
+        let t = self.textos;
+        match self.servicio.archivar_completadas() {
+            Ok(n) => self.status = Some(rellenar(t.archivadas, &[&n])),
+            Err(e) => {
+                self.status = Some(e.to_string());
+                return;
+            }
+        }
+        self.forget_missing();
+        self.refresh_stats();
+        self.refresh_archive();
+    }
+
+    fn toggle_archive(&mut self) {
+        self.archivadas = match self.archivadas {
+            Some(_) => None,
+            None => Some(self.servicio.db().cargar_archivadas().unwrap_or_default()),
+        };
+    }
+
+    fn refresh_archive(&mut self) {
+        if self.archivadas.is_some() {
+            self.archivadas = Some(self.servicio.db().cargar_archivadas().unwrap_or_default());
+        }
+    }
+
+    fn restore_archived(&mut self, id: i32) {
+        if self.servicio.restaurar_archivada(id).is_ok() {
+            self.refresh_search();
+            self.refresh_archive();
+        }
+    }
+
+    // Copia de seguridad automática, en otro hilo para no detener la
+    // ventana; solo se informa si falla
+    fn backup(&mut self) {
+        self.last_backup = Instant::now();
+        if self.backup_job.is_some() {
+            return;
+        }
+        match self.servicio.db().preparar_copia_automatica() {
+            Ok(Some(copia)) => self.backup_job = Some(std::thread::spawn(move || copia.hacer())),
+            Ok(None) => {}
+            Err(e) => self.status = Some(rellenar(self.textos.error_copia, &[&e])),
+        }
+    }
+
+    // Recoge la copia en curso si ha terminado, o esperándola con `wait`
+    fn finish_backup(&mut self, wait: bool) {
+        if !self
+            .backup_job
+            .as_ref()
+            .is_some_and(|job| wait || job.is_finished())
+        {
+            return;
+        }
+        if let Some(Ok(Err(e))) = self.backup_job.take().map(JoinHandle::join) {
+            self.status = Some(rellenar(self.textos.error_copia, &[&e]));
+        }
+    }
+
+    // Sincroniza con la carpeta compartida, si hay una. Los conflictos se
+    // muestran siempre; el resumen, solo si se ha pedido
+    fn sync(&mut self, informar: bool) {
+        let t = self.textos;
+        self.last_sync = Instant::now();
+        match self.servicio.sincronizar() {
+            Ok(None) if informar => self.status = Some(t.sin_carpeta_sync.to_string()),
+            Ok(None) => {}
+            Ok(Some(informe)) => {
+                if informe.recibidos > 0 {
+                    self.reload_tasks();
+                }
+                let mut mensajes: Vec<String> = informe
+                    .conflictos
+                    .iter()
+                    .map(|c| rellenar(t.conflicto_sync, &[&c.id, &c.conservada, &c.descartada]))
+                    .collect();
+                if informar {
+                    mensajes.insert(
+                        0,
+                        rellenar(t.sincronizado, &[&informe.recibidos, &informe.enviados]),
+                    );
+                }
+                if !mensajes.is_empty() {
+                    self.status = Some(mensajes.join("\n"));
+                }
+            }
+            Err(e) => self.status = Some(rellenar(t.error_sync, &[&e])),
+        }
+    }
+
+    fn set_sync_folder(&mut self) {
+        let carpeta = self.carpeta_sync.trim();
+        if let Err(e) = self
+            .servicio
+            .db()
+            .guardar_carpeta_sincronizacion(std::path::Path::new(carpeta))
+        {
+            self.status = Some(rellenar(self.textos.error_sync, &[&e]));
+        }
+        self.refresh_sync_enabled();
+    }
+
+    fn refresh_sync_enabled(&mut self) {
+        self.sync_enabled = self
+            .servicio
+            .db()
+            .carpeta_sincronizacion()
+            .is_ok_and(|carpeta| carpeta.is_some());
+    }
+
+    // Tiempo hasta la próxima copia programada, si las hay
+    fn next_backup(&self) -> Option<Duration> {
+        if self.ajustes.copias_conservar == 0 || self.ajustes.intervalo_copias == 0 {
+            return None;
+        }
+        let intervalo = Duration::from_secs(u64::from(self.ajustes.intervalo_copias) * 60);
+        Some(intervalo.saturating_sub(self.last_backup.elapsed()))
+    }
+
+    fn toggle_trash(&mut self) {
+        self.papelera = match self.papelera {
+            Some(_) => None,
+            None => Some(self.servicio.db().cargar_papelera().unwrap_or_default()),
+        };
+    }
+
+    fn refresh_trash(&mut self) {
+        if self.papelera.is_some() {
+            self.papelera = Some(self.servicio.db().cargar_papelera().unwrap_or_default());
+        }
+    }
+
+    fn restore_deleted(&mut self, id: i32) {
+        if self.servicio.restaurar_eliminada(id).is_ok() {
+            self.refresh_stats();
+            self.refresh_search();
+            self.refresh_trash();
+        }
+    }
+
+    fn delete_tasks(&mut self, ids: &[i32]) {
+        let vecina = self.surviving_neighbour(ids);
+        if let Err(e) = self.servicio.eliminar_varias(ids) {
+            self.status = Some(e.to_string());
+            return;
+        }
+        self.forget_tasks(ids, vecina);
+        self.refresh_stats();
+        self.refresh_trash();
+    }
+
+    fn surviving_neighbour(&self, ids: &[i32]) -> Option<i32> {
+        self.estado
+            .vecina_superviviente(self.servicio.tareas(), ids)
+    }
+
+    // Olvida el estado de la interfaz de tareas que ya no están en la lista
+    fn forget_tasks(&mut self, ids: &[i32], vecina: Option<i32>) {
+        self.estado.olvidar(ids, vecina);
+        for id in ids {
+            self.row_heights.remove(id);
+        }
+    }
+
+    // Ctrl-clic: añade o quita una tarea de la selección múltiple
+    fn toggle_mark(&mut self, id: i32) {
+        // La tarea que ya estaba seleccionada entra también en la selección
+        if self.estado.marcadas.is_empty()
+            && let Some(actual) = self.estado.seleccionada
+            && actual != id
+        {
+            self.estado.marcadas.insert(actual);
+        }
+        if !self.estado.marcadas.remove(&id) {
+            self.estado.marcadas.insert(id);
+        }
+        self.estado.seleccionada = Some(id);
+    }
+
+    // Mayús-clic: selecciona las tareas visibles entre la seleccionada y `id`
+    fn mark_range(&mut self, id: i32) {
+        let visibles = self.visible_order();
+        let (Some(desde), Some(hasta)) = (
+            self.position_in(&visibles, self.estado.seleccionada),
+            self.position_in(&visibles, Some(id)),
+        ) else {
+            self.toggle_mark(id);
+            return;
+        };
+        let rango = desde.min(hasta)..=desde.max(hasta);
+        self.estado.marcadas = visibles[rango]
+            .iter()
+            .map(|&idx| self.todo_at(idx).id)
+            .collect();
+    }
+
+    // Tareas seleccionadas, en el orden en que se ven
+    fn bulk_targets(&mut self) -> Vec<i32> {
+        self.visible_order()
+            .into_iter()
+            .map(|idx| self.todo_at(idx).id)
+            .filter(|id| self.estado.marcadas.contains(id))
+            .collect()
+    }
+
+    fn run_bulk(&mut self, op: Bulk) {
+        let ids = self.bulk_targets();
+        if ids.is_empty() {
+            return;
+        }
+        let vecina = self.surviving_neighbour(&ids);
+        let resultado = match op {
+            Bulk::Complete => self.servicio.marcar_varias(&ids, true).map(drop),
+            Bulk::Uncomplete => self.servicio.marcar_varias(&ids, false).map(drop),
+            Bulk::Delete => {
+                self.request_delete(ids);
+                return;
+            }
+            Bulk::Archive => self.servicio.archivar_varias(&ids),
+            Bulk::ResetTimer => self.servicio.resetear_temporizadores(&ids),
+        };
+        if let Err(e) = resultado {
+            self.status = Some(e.to_string());
+            return;
+        }
+        match op {
+            Bulk::Archive => {
+                self.forget_tasks(&ids, vecina);
+                self.refresh_archive();
+            }
+            Bulk::Uncomplete => {
+                self.forget_missing();
+                self.refresh_trash();
+            }
+            _ => {}
+        }
+        self.refresh_stats();
+        self.refresh_search();
+    }
+
+    fn render_bulk_actions(&mut self, ui: &mut egui::Ui) {
+        let t = self.textos;
+        let mut op = None;
+        ui.horizontal_wrapped(|ui| {
+            ui.label(rellenar(t.seleccionadas, &[&self.estado.marcadas.len()]));
+            for (accion, texto) in [
+                (Bulk::Complete, t.lote_completar),
+                (Bulk::Uncomplete, t.lote_desmarcar),
+                (Bulk::Archive, t.lote_archivar),
+                (Bulk::ResetTimer, t.lote_reiniciar),
+                (Bulk::Delete, t.eliminar),
+            ] {
+                if ui.button(texto).clicked() {
+                    op = Some(accion);
+                }
+            }
+            if ui.button("✖").on_hover_text(t.quitar_seleccion).clicked() {
+                self.estado.marcadas.clear();
+            }
+        });
+        if let Some(op) = op {
+            self.run_bulk(op);
+        }
+    }
+
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if let Some(action) = self.recording_shortcut {
+            self.record_shortcut(ctx, action);
+            return;
+        }
+        if self.bindings.consume(ctx, Action::CommandPalette) {
+            self.run_action(Action::CommandPalette);
+        }
+        if self.palette.is_some() || !self.pending_delete.is_empty() || ctx.wants_keyboard_input() {
+            return;
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape)) {
+            if self.show_help {
+                self.show_help = false;
+            } else if !self.estado.marcadas.is_empty() {
+                self.estado.marcadas.clear();
+            } else {
+                self.estado.seleccionada = None;
+            }
+        }
+
+        for action in self.bindings.pressed(ctx) {
+            self.run_action(action);
+        }
+    }
+
+    fn run_action(&mut self, action: Action) {
+        match action {
+            Action::SelectNext => self.move_selection(1),
+            Action::SelectPrevious => self.move_selection(-1),
+            Action::FocusNewTask => self.focus_new_task = true,
+            Action::SelectAll => {
+                self.estado.marcadas = self
+                    .visible_order()
+                    .into_iter()
+                    .map(|idx| self.todo_at(idx).id)
+                    .collect();
+            }
+            // Con varias tareas seleccionadas, estas acciones se aplican a todas
+            Action::ToggleDone | Action::Delete | Action::ResetTimer
+                if !self.estado.marcadas.is_empty() =>
+            {
+                let op = match action {
+                    Action::ToggleDone => {
+                        let todas_hechas = self
+                            .estado
+                            .marcadas
+                            .iter()
+                            .filter_map(|&id| self.servicio.tarea(id))
+                            .all(|todo| todo.checked);
+                        if todas_hechas {
+                            Bulk::Uncomplete
+                        } else {
+                            Bulk::Complete
+                        }
+                    }
+                    Action::Delete => Bulk::Delete,
+                    _ => Bulk::ResetTimer,
+                };
+                self.run_bulk(op);
+            }
+            Action::ShowHelp => self.show_help = !self.show_help,
+            Action::CommandPalette => {
+                self.palette = match self.palette {
+                    Some(_) => None,
+                    None => Some(Palette::new()),
+                }
+            }
+            _ => {
+                let Some((id, checked)) = self
+                    .estado
+                    .seleccionada
+                    .and_then(|id| self.servicio.tarea(id))
+                    .map(|todo| (todo.id, todo.checked))
+                else {
+                    return;
+                };
+                match action {
+                    Action::ToggleDone => self.set_checked(id, !checked),
+                    Action::Edit => self.start_editing(id),
+                    Action::Delete => self.request_delete(vec![id]),
+                    Action::ToggleTimer => self.toggle_timer(id),
+                    Action::ResetTimer => self.reset_timer(id),
+                    Action::MoveUp => self.move_selected(-1),
+                    Action::MoveDown => self.move_selected(1),
+                    Action::ToggleMark => self.toggle_mark(id),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let visibles = self.visible_order();
+        if visibles.is_empty() {
+            return;
+        }
+        let nueva = match self.position_in(&visibles, self.estado.seleccionada) {
+            Some(pos) => pos.saturating_add_signed(delta).min(visibles.len() - 1),
+            None if delta > 0 => 0,
+            None => visibles.len() - 1,
+        };
+        self.estado.seleccionada = Some(self.todo_at(visibles[nueva]).id);
+        self.scroll_to_selected = true;
+    }
+
+    // Intercambia la tarea seleccionada con la visible anterior o siguiente
+    fn move_selected(&mut self, delta: isize) {
+        if self.filtro.orden != Orden::Manual {
+            return;
+        }
+        let visibles = self.visible_order();
+        let (Some(id), Some(pos)) = (
+            self.estado.seleccionada,
+            self.position_in(&visibles, self.estado.seleccionada),
+        ) else {
+            return;
+        };
+        if let Some(&destino) = pos.checked_add_signed(delta).and_then(|p| visibles.get(p)) {
+            self.servicio.mover(id, self.todo_at(destino).id);
+            self.scroll_to_selected = true;
+        }
+    }
+
+    fn record_shortcut(&mut self, ctx: &egui::Context, action: Action) {
+        let pulsada = ctx.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => Some((*key, *modifiers)),
+                _ => None,
+            })
+        });
+
+        if let Some((key, modifiers)) = pulsada {
+            if key != egui::Key::Escape {
+                let atajo = egui::KeyboardShortcut::new(modifiers, key);
+                self.bindings.set(self.servicio.db(), action, atajo);
+            }
+            self.recording_shortcut = None;
+        }
+    }
+
+    // Entradas de la paleta que coinciden con la consulta, mejores primero
+    fn palette_entries(&self, query: &str) -> Vec<(PaletteEntry, String)> {
+        let t = self.textos;
+        let mut comandos = vec![
+            (
+                PaletteEntry::Export,
+                rellenar(t.paleta_exportar, &[&EXPORT_PATH]),
+            ),
+            (PaletteEntry::ToggleTheme, t.paleta_tema.to_string()),
+            (PaletteEntry::Settings, t.ajustes_titulo.to_string()),
+            (
+                PaletteEntry::ClearCompleted,
+                t.limpiar_completadas.to_string(),
+            ),
+            (PaletteEntry::Archive, t.archivo_titulo.to_string()),
+            (PaletteEntry::Trash, t.papelera_titulo.to_string()),
+            (
+                PaletteEntry::Order(Orden::Manual),
+                t.paleta_orden_manual.to_string(),
+            ),
+            (
+                PaletteEntry::Order(Orden::Prioridad),
+                t.paleta_orden_prioridad.to_string(),
+            ),
+            (PaletteEntry::Reload, t.recargar.to_string()),
+            (PaletteEntry::Sync, t.sincronizar.to_string()),
+            (
+                PaletteEntry::ClearSearch,
+                t.paleta_limpiar_busqueda.to_string(),
+            ),
+        ];
+        comandos.extend(
+            Action::ALL
+                .into_iter()
+                .filter(|a| *a != Action::CommandPalette)
+                .map(|a| (PaletteEntry::Run(a), format!("⌨ {}", a.description(t)))),
+        );
+        comandos.extend(
+            self.servicio
+                .tareas()
+                .iter()
+                .map(|todo| (PaletteEntry::Task(todo.id), format!("📋 {}", todo.text))),
+        );
+
+        let mut coincidencias: Vec<(i32, PaletteEntry, String)> = comandos
+            .into_iter()
+            .filter_map(|(entrada, texto)| {
+                fuzzy_score(query, &texto).map(|puntos| (puntos, entrada, texto))
+            })
+            .collect();
+        coincidencias.sort_by_key(|(puntos, _, _)| std::cmp::Reverse(*puntos));
+
+        let mut entradas: Vec<(PaletteEntry, String)> = coincidencias
+            .into_iter()
+            .map(|(_, entrada, texto)| (entrada, texto))
+            .collect();
+        if !query.trim().is_empty() {
+            entradas.push((
+                PaletteEntry::AddTask,
+                rellenar(t.paleta_agregar, &[&query.trim()]),
+            ));
+        }
+        entradas
+    }
+
+    fn run_palette_entry(&mut self, ctx: &egui::Context, entrada: PaletteEntry, query: &str) {
+        match entrada {
+            PaletteEntry::AddTask => {
+                self.add_task(query.trim());
+            }
+            PaletteEntry::Export => self.export_tasks(),
+            PaletteEntry::ToggleTheme => self.toggle_theme(ctx),
+            PaletteEntry::Settings => self.show_settings = true,
+            PaletteEntry::ClearCompleted => self.clear_completed(),
+            PaletteEntry::Archive => {
+                if self.archivadas.is_none() {
+                    self.toggle_archive();
+                }
+            }
+            PaletteEntry::Trash => {
+                if self.papelera.is_none() {
+                    self.toggle_trash();
+                }
+            }
+            PaletteEntry::Order(orden) => self.filtro.orden = orden,
+            PaletteEntry::Reload => self.reload_tasks(),
+            PaletteEntry::Sync => self.sync(true),
+            PaletteEntry::ClearSearch => {
+                self.busqueda.clear();
+                self.refresh_search();
+            }
+            PaletteEntry::Run(action) => self.run_action(action),
+            PaletteEntry::Task(id) => {
+                // Si el filtro la ocultaba, se quita para poder mostrarla
+                let visibles = self.visible_order();
+                if self.position_in(&visibles, Some(id)).is_none() {
+                    self.filtro.prioridad_minima = Prioridad::Ninguna;
+                    self.busqueda.clear();
+                    self.refresh_search();
+                }
+                self.estado.seleccionada = Some(id);
+                self.scroll_to_selected = true;
+            }
+        }
+    }
+
+    fn render_palette(&mut self, ctx: &egui::Context) {
+        let t = self.textos;
+        let Some(palette) = &mut self.palette else {
+            return;
+        };
+
+        // Teclas de navegación antes de que las vea el campo de texto
+        let (arriba, abajo, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        if escape {
+            self.palette = None;
+            return;
+        }
+
+        let query = palette.query.clone();
+        let entradas = self.palette_entries(&query);
+        let Some(palette) = &mut self.palette else {
+            return;
+        };
+        if abajo {
+            palette.selected += 1;
+        }
+        if arriba {
+            palette.selected = palette.selected.saturating_sub(1);
+        }
+        palette.selected = palette.selected.min(entradas.len().saturating_sub(1));
+
+        let mut elegida = None;
+        egui::Window::new("palette")
+            .title_bar(false)
+            .resizable(false)
+            .fixed_size([360.0, 0.0])
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .show(ctx, |ui| {
+                let campo = ui.add(
+                    egui::TextEdit::singleline(&mut palette.query)
+                        .hint_text(t.paleta_ayuda)
+                        .desired_width(f32::INFINITY),
+                );
+                campo.request_focus();
+                if campo.changed() {
+                    palette.selected = 0;
+                }
+                ui.separator();
+
+                for (pos, (entrada, texto)) in entradas.iter().enumerate().take(10) {
+                    let etiqueta = ui.selectable_label(pos == palette.selected, texto);
+                    if etiqueta.clicked() {
+                        elegida = Some(*entrada);
+                    }
+                }
+                if enter {
+                    elegida = entradas.get(palette.selected).map(|(entrada, _)| *entrada);
+                }
+            });
+
+        if let Some(entrada) = elegida {
+            self.palette = None;
+            self.run_palette_entry(ctx, entrada, &query);
+        }
+    }
+
+    fn render_help(&mut self, ctx: &egui::Context) {
+        let t = self.textos;
+        let mut open = self.show_help;
+        egui::Window::new(t.ayuda_titulo)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                egui::Grid::new("atajos").striped(true).show(ui, |ui| {
+                    for action in Action::ALL {
+                        ui.label(action.description(t));
+                        let teclas: Vec<String> = self
+                            .bindings
+                            .shortcuts(action)
+                            .iter()
+                            .map(|s| ctx.format_shortcut(s))
+                            .collect();
+                        ui.label(egui::RichText::new(teclas.join(" / ")).monospace());
+
+                        let grabando = self.recording_shortcut == Some(action);
+                        let texto = if grabando { t.pulsa_tecla } else { t.cambiar };
+                        if ui.selectable_label(grabando, texto).clicked() {
+                            self.recording_shortcut = (!grabando).then_some(action);
+                        }
+                        ui.end_row();
+                    }
+
+                    ui.label(t.cerrar_ayuda);
+                    ui.label(egui::RichText::new("Esc").monospace());
+                    ui.end_row();
+                });
+
+                ui.add_space(5.0);
+                if ui.button(t.restablecer_atajos).clicked() {
+                    self.bindings.reset(self.servicio.db());
+                }
+            });
+
+        if !open {
+            self.show_help = false;
+            self.recording_shortcut = None;
+        }
+    }
+
+    fn render_settings(&mut self, ctx: &egui::Context) {
+        let t = self.textos;
+        let mut open = self.show_settings;
+        let mut ajustes = self.ajustes;
+        let mut idioma = self.idioma;
+        let mut abrir_db = false;
+        let mut sincronizar = false;
+
+        egui::Window::new(t.ajustes_titulo)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("ajustes").num_columns(2).show(ui, |ui| {
+                    ui.label(t.tema);
+                    ui.horizontal(|ui| {
+                        for tema in Tema::TODOS {
+                            ui.selectable_value(&mut ajustes.tema, tema, t.temas[tema as usize]);
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label(t.tamano_letra);
+                    ui.add(
+                        egui::Slider::new(
+                            &mut ajustes.escala_fuente,
+                            Ajustes::ESCALA_MINIMA..=Ajustes::ESCALA_MAXIMA,
+                        )
+                        .step_by(0.05)
+                        .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
+                    );
+                    ui.end_row();
+
+                    ui.label(t.color_temporizador);
+                    ui.color_edit_button_srgb(&mut ajustes.color_temporizador);
+                    ui.end_row();
+
+                    ui.label(t.color_arrastre);
+                    ui.color_edit_button_srgb(&mut ajustes.color_arrastre);
+                    ui.end_row();
+
+                    ui.label(t.confirmar_borrado);
+                    ui.checkbox(&mut ajustes.confirmar_borrado, "");
+                    ui.end_row();
+
+                    ui.label(t.dias_papelera);
+                    ui.add(
+                        egui::DragValue::new(&mut ajustes.dias_papelera)
+                            .range(0..=3650)
+                            .suffix(t.dias_sufijo),
+                    )
+                    .on_hover_text(t.dias_papelera_ayuda);
+                    ui.end_row();
+
+                    ui.label(t.copias_conservar);
+                    ui.add(egui::DragValue::new(&mut ajustes.copias_conservar).range(0..=100))
+                        .on_hover_text(t.copias_conservar_ayuda);
+                    ui.end_row();
+
+                    ui.label(t.intervalo_copias);
+                    ui.add_enabled(
+                        ajustes.copias_conservar > 0,
+                        egui::DragValue::new(&mut ajustes.intervalo_copias)
+                            .range(0..=24 * 60)
+                            .suffix(t.minutos_sufijo),
+                    )
+                    .on_hover_text(t.intervalo_copias_ayuda);
+                    ui.end_row();
+
+                    ui.label(t.limite_ganchos);
+                    ui.add(
+                        egui::DragValue::new(&mut ajustes.limite_ganchos)
+                            .range(1..=3600)
+                            .suffix(" s"),
+                    )
+                    .on_hover_text(t.limite_ganchos_ayuda);
+                    ui.end_row();
+
+                    ui.label(t.idioma);
+                    egui::ComboBox::from_id_salt("idioma")
+                        .selected_text(format!("🌐 {}", idioma.nombre()))
+                        .show_ui(ui, |ui| {
+                            for opcion in Idioma::TODOS {
+                                ui.selectable_value(&mut idioma, opcion, opcion.nombre());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label(t.base_datos);
+                    ui.horizontal(|ui| {
+                        let campo = ui.text_edit_singleline(&mut self.ruta_db);
+                        abrir_db = (campo.lost_focus()
+                            && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+                            || ui.button(t.abrir).clicked();
+                    });
+                    ui.end_row();
+
+                    ui.label(t.carpeta_sync);
+                    ui.horizontal(|ui| {
+                        let campo = ui
+                            .text_edit_singleline(&mut self.carpeta_sync)
+                            .on_hover_text(t.carpeta_sync_ayuda);
+                        sincronizar = (campo.lost_focus()
+                            && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+                            || ui.button(t.sincronizar).clicked();
+                    });
+                    ui.end_row();
+                });
+
+                ui.add_space(5.0);
+                if ui.button(t.restablecer_ajustes).clicked() {
+                    ajustes = Ajustes::default();
+                }
+            });
+
+        if ajustes != self.ajustes {
+            self.set_settings(ctx, ajustes);
+        }
+        if idioma != self.idioma {
+            self.set_language(ctx, idioma);
+        }
+        if abrir_db && !self.ruta_db.trim().is_empty() {
+            self.open_database(ctx);
+        }
+        if sincronizar {
+            self.set_sync_folder();
+            self.sync(true);
+        }
+        if !open {
+            self.show_settings = false;
+        }
     }
 
-    fn delete_task(&mut self, idx: usize) {
-        let tarea_id = self.todos[idx].id;
-        if self.db.eliminar_tarea(tarea_id).is_ok() {
-            self.todos.remove(idx);
+    fn render_archive(&mut self, ctx: &egui::Context) {
+        let t = self.textos;
+        let Some(archivadas) = &self.archivadas else {
+            return;
+        };
+        let mut open = true;
+        let mut restaurar = None;
+
+        egui::Window::new(t.archivo_titulo)
+            .open(&mut open)
+            .collapsible(false)
+            .default_height(300.0)
+            .show(ctx, |ui| {
+                if archivadas.is_empty() {
+                    ui.label(egui::RichText::new(t.archivo_vacio).weak());
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for todo in archivadas {
+                        ui.horizontal(|ui| {
+                            if ui.button(t.restaurar).clicked() {
+                                restaurar = Some(todo.id);
+                            }
+                            ui.label(format!("⏱ {}", formatear_duracion(todo.tiempo_total())));
+                            ui.label(&todo.text);
+                        });
+                    }
+                });
+            });
+
+        if let Some(id) = restaurar {
+            self.restore_archived(id);
+        }
+        if !open {
+            self.archivadas = None;
+        }
+    }
+
+    fn render_trash(&mut self, ctx: &egui::Context) {
+        let t = self.textos;
+        let Some(papelera) = &self.papelera else {
+            return;
+        };
+        let mut open = true;
+        let mut restaurar = None;
+        let mut eliminar = None;
+        let mut vaciar = false;
+
+        egui::Window::new(t.papelera_titulo)
+            .open(&mut open)
+            .collapsible(false)
+            .default_height(300.0)
+            .show(ctx, |ui| {
+                if papelera.is_empty() {
+                    ui.label(egui::RichText::new(t.papelera_vacia).weak());
+                } else {
+                    vaciar = ui.button(t.vaciar_papelera).clicked();
+                    ui.separator();
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for todo in papelera {
+                        ui.horizontal(|ui| {
+                            if ui.button(t.restaurar).clicked() {
+                                restaurar = Some(todo.id);
+                            }
+                            if ui
+                                .button("🗑")
+                                .on_hover_text(t.eliminar_definitivamente)
+                                .clicked()
+                            {
+                                eliminar = Some(todo.id);
+                            }
+                            ui.label(&todo.text);
+                        });
+                    }
+                });
+            });
+
+        if let Some(id) = restaurar {
+            self.restore_deleted(id);
+        }
+        if let Some(id) = eliminar
+            && self.servicio.db().eliminar_definitivamente(id).is_ok()
+        {
+            self.refresh_trash();
+        }
+        if vaciar && self.servicio.db().vaciar_papelera().is_ok() {
+            self.refresh_trash();
+        }
+        if !open {
+            self.papelera = None;
+        }
+    }
+
+    fn render_delete_confirmation(&mut self, ctx: &egui::Context) {
+        let t = self.textos;
+        let pregunta = match self.pending_delete.as_slice() {
+            [] => return,
+            [id] => match self.servicio.tarea(*id) {
+                Some(todo) => rellenar(t.confirmar_eliminar, &[&todo.text]),
+                None => {
+                    self.pending_delete.clear();
+                    return;
+                }
+            },
+            ids => rellenar(t.confirmar_eliminar_varias, &[&ids.len()]),
+        };
+
+        let (mut confirmar, mut cancelar) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        egui::Window::new("confirmar_borrado")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(pregunta);
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    confirmar |= ui.button(t.eliminar).clicked();
+                    cancelar |= ui.button(t.cancelar).clicked();
+                });
+            });
+
+        if confirmar {
+            let ids = std::mem::take(&mut self.pending_delete);
+            self.delete_tasks(&ids);
+        }
+        if cancelar {
+            self.pending_delete.clear();
         }
     }
 }
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        ctx.request_repaint();
+        // Cambios guardados por otra instancia. Mientras se edita una tarea
+        // se espera: si la otra también la editó, lo detecta `editar`. Solo
+        // se pregunta a la base de datos cuando el vigilante ha visto algo.
+        if self.estado.editando.is_none()
+            && self
+                .watcher
+                .as_ref()
+                .is_some_and(DatabaseWatcher::take_changes)
+        {
+            self.refresh_sync_enabled();
+            if self.servicio.hay_cambios_externos() {
+                self.reload_tasks();
+            }
+        }
+        if self.estado.editando.is_none() && self.last_sync.elapsed() >= SYNC_INTERVAL {
+            self.sync(false);
+        }
+        let errores = self.servicio.db().errores_ganchos();
+        if !errores.is_empty() {
+            let mensajes: Vec<String> = errores.iter().map(|e| e.describir(self.textos)).collect();
+            self.status = Some(mensajes.join("\n"));
+        }
 
-        egui::CentralPanel::default()
-            .frame(egui::Frame::none().inner_margin(egui::Margin::same(8.0)))
-            .show(ctx, |ui| {
-                ui.spacing_mut().item_spacing = egui::vec2(4.0, 2.0);
+        self.handle_shortcuts(ctx);
+
+        // Cabecera y estadísticas fijas; la lista ocupa el resto y se desplaza
+        let fill = ctx.style().visuals.panel_fill;
+        let item_spacing = egui::vec2(4.0, 2.0);
 
+        egui::TopBottomPanel::top("cabecera")
+            .show_separator_line(false)
+            .frame(egui::Frame::none().fill(fill).inner_margin(egui::Margin {
+                left: 8.0,
+                right: 8.0,
+                top: 8.0,
+                bottom: 0.0,
+            }))
+            .show(ctx, |ui| {
+                ui.spacing_mut().item_spacing = item_spacing;
                 self.render_header(ui);
                 self.render_add_task(ui);
-                self.render_tasks(ui);
+                self.render_list_controls(ui);
+            });
+
+        egui::TopBottomPanel::bottom("estadisticas")
+            .show_separator_line(false)
+            .frame(egui::Frame::none().fill(fill).inner_margin(egui::Margin {
+                left: 8.0,
+                right: 8.0,
+                top: 0.0,
+                bottom: 8.0,
+            }))
+            .show(ctx, |ui| {
+                ui.spacing_mut().item_spacing = item_spacing;
                 self.render_statistics(ui);
             });
+
+        egui::CentralPanel::default()
+            .frame(
+                egui::Frame::none()
+                    .fill(fill)
+                    .inner_margin(egui::Margin::symmetric(8.0, 0.0)),
+            )
+            .show(ctx, |ui| {
+                ui.spacing_mut().item_spacing = item_spacing;
+                self.render_tasks(ui);
+            });
+
+        self.render_help(ctx);
+        self.render_settings(ctx);
+        self.render_archive(ctx);
+        self.render_trash(ctx);
+        self.render_delete_confirmation(ctx);
+        self.render_palette(ctx);
+
+        // egui repinta con cada entrada; sin ella solo hace falta refrescar
+        // los temporizadores en marcha, una vez por segundo
+        if self.servicio.hay_temporizador_activo() {
+            ctx.request_repaint_after(REPAINT_INTERVAL);
+        }
+
+        // Y despertar a la hora de la siguiente sincronización y de la
+        // siguiente copia de seguridad
+        // (mientras se edita no se sincroniza; ya lo hará la entrada)
+        if self.estado.editando.is_none() && self.sync_enabled {
+            ctx.request_repaint_after(SYNC_INTERVAL.saturating_sub(self.last_sync.elapsed()));
+        }
+        self.finish_backup(false);
+        if self.backup_job.is_some() {
+            ctx.request_repaint_after(BACKUP_POLL_INTERVAL);
+        }
+        if let Some(espera) = self.next_backup() {
+            if espera.is_zero() {
+                self.backup();
+            } else {
+                ctx.request_repaint_after(espera);
+            }
+        }
+    }
+
+    fn on_exit(&mut self) {
+        self.servicio.pausar_todos();
+        self.sync(false);
+        // Ya no hay ventana que detener: la última copia se hace de una vez
+        self.finish_backup(true);
+        let _ = self.servicio.db().copia_automatica();
+    }
+}
+
+// Fecha de la ocurrencia de una tarea recurrente; en rojo si está atrasada
+fn render_due_badge(ui: &mut Ui, t: &Textos, todo: &TodoItem) {
+    let Some(fecha) = todo.fecha_proxima else {
+        return;
+    };
+
+    let color = if !todo.checked && fecha < hoy() {
+        Color32::from_rgb(220, 90, 90)
+    } else {
+        Color32::from_gray(150)
+    };
+    let badge = ui.label(
+        egui::RichText::new(format!("🔁 {}", fecha.format(t.formato_fecha)))
+            .small()
+            .color(color),
+    );
+    if let Some(recurrencia) = todo.recurrencia {
+        badge.on_hover_text(describe_recurrence(t, &recurrencia));
+    }
+}
+
+fn recurrence_kind(t: &Textos, recurrencia: Option<&Recurrencia>) -> &'static str {
+    match recurrencia {
+        None => t.sin_repeticion,
+        Some(Recurrencia::Diaria) => t.diaria,
+        Some(Recurrencia::Semanal(_)) => t.semanal,
+        Some(Recurrencia::Mensual(_)) => t.mensual,
+        Some(Recurrencia::CadaNDias(_)) => t.cada_n_dias,
+    }
+}
+
+fn describe_recurrence(t: &Textos, recurrencia: &Recurrencia) -> String {
+    match recurrencia {
+        Recurrencia::Diaria => t.todos_los_dias.to_string(),
+        Recurrencia::Semanal(dias) => {
+            let dias: Vec<&str> = t
+                .dias_semana
+                .iter()
+                .zip(dias)
+                .filter(|(_, activo)| **activo)
+                .map(|(letra, _)| *letra)
+                .collect();
+            rellenar(t.cada_semana, &[&dias.join(" ")])
+        }
+        Recurrencia::Mensual(dia) => rellenar(t.dia_del_mes, &[dia]),
+        Recurrencia::CadaNDias(n) => rellenar(t.dias_tras_completar, &[n]),
     }
 }
+
+fn priority_label(t: &Textos, prioridad: Prioridad) -> &'static str {
+    t.prioridades[prioridad as usize]
+}
+
+fn priority_color(prioridad: Prioridad) -> Option<Color32> {
+    match prioridad {
+        Prioridad::Ninguna => None,
+        Prioridad::Baja => Some(Color32::from_rgb(90, 140, 200)),
+        Prioridad::Media => Some(Color32::from_rgb(220, 190, 60)),
+        Prioridad::Alta => Some(Color32::from_rgb(230, 130, 40)),
+        Prioridad::Urgente => Some(Color32::from_rgb(220, 60, 60)),
+    }
+}
+
+fn priority_combo(ui: &mut Ui, t: &Textos, id: &str, prioridad: &mut Prioridad) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(priority_label(t, *prioridad))
+        .show_ui(ui, |ui| {
+            for opcion in Prioridad::TODAS {
+                let texto = egui::RichText::new(priority_label(t, opcion))
+                    .color(priority_color(opcion).unwrap_or(Color32::from_gray(180)));
+                ui.selectable_value(prioridad, opcion, texto);
+            }
+        });
+}
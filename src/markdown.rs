@@ -0,0 +1,143 @@
+//! Renderizado mínimo de Markdown para las notas de las tareas:
+//! títulos, listas, enlaces, código en línea y bloques de código.
+
+use egui::{Color32, RichText, Ui};
+
+enum Inline<'a> {
+    Texto(&'a str),
+    Codigo(&'a str),
+    Enlace { texto: &'a str, url: &'a str },
+}
+
+pub fn render(ui: &mut Ui, texto: &str) {
+    let mut en_bloque = false;
+    let mut bloque = String::new();
+
+    for linea in texto.lines() {
+        if linea.trim_start().starts_with("```") {
+            if en_bloque {
+                render_code_block(ui, &bloque);
+                bloque.clear();
+            }
+            en_bloque = !en_bloque;
+            continue;
+        }
+        if en_bloque {
+            bloque.push_str(linea);
+            bloque.push('\n');
+            continue;
+        }
+
+        let recortada = linea.trim_start();
+        let sangria = (linea.len() - recortada.len()) as f32 * 6.0;
+
+        if let Some(titulo) = recortada.strip_prefix("### ") {
+            render_inline(ui, titulo, |t| t.strong());
+        } else if let Some(titulo) = recortada.strip_prefix("## ") {
+            render_inline(ui, titulo, |t| t.size(18.0).strong());
+        } else if let Some(titulo) = recortada.strip_prefix("# ") {
+            render_inline(ui, titulo, |t| t.size(22.0).strong());
+        } else if let Some(elemento) = recortada
+            .strip_prefix("- ")
+            .or_else(|| recortada.strip_prefix("* "))
+        {
+            render_list_item(ui, sangria, "•", elemento);
+        } else if let Some((numero, elemento)) = recortada.split_once(". ")
+            && !numero.is_empty()
+            && numero.chars().all(|c| c.is_ascii_digit())
+        {
+            render_list_item(ui, sangria, &format!("{}.", numero), elemento);
+        } else if recortada.is_empty() {
+            ui.add_space(4.0);
+        } else {
+            render_inline(ui, recortada, |t| t);
+        }
+    }
+
+    // Bloque sin cerrar: se muestra igualmente
+    if en_bloque && !bloque.is_empty() {
+        render_code_block(ui, &bloque);
+    }
+}
+
+fn render_list_item(ui: &mut Ui, sangria: f32, marcador: &str, texto: &str) {
+    ui.horizontal(|ui| {
+        ui.add_space(sangria + 8.0);
+        ui.label(marcador);
+        render_inline(ui, texto, |t| t);
+    });
+}
+
+fn render_code_block(ui: &mut Ui, codigo: &str) {
+    egui::Frame::none()
+        .fill(Color32::from_gray(25))
+        .rounding(4.0)
+        .inner_margin(egui::Margin::same(6.0))
+        .show(ui, |ui| {
+            ui.label(RichText::new(codigo.trim_end()).monospace());
+        });
+}
+
+fn render_inline(ui: &mut Ui, linea: &str, estilo: impl Fn(RichText) -> RichText) {
+    ui.horizontal_wrapped(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        for parte in parse_inline(linea) {
+            match parte {
+                Inline::Texto(texto) => {
+                    ui.label(estilo(RichText::new(texto)));
+                }
+                Inline::Codigo(codigo) => {
+                    ui.label(RichText::new(codigo).code());
+                }
+                Inline::Enlace { texto, url } => {
+                    ui.hyperlink_to(estilo(RichText::new(texto)), url);
+                }
+            }
+        }
+    });
+}
+
+fn parse_inline(linea: &str) -> Vec<Inline<'_>> {
+    let mut partes = Vec::new();
+    let mut resto = linea;
+
+    while let Some(inicio) = resto.find(['`', '[']) {
+        let (antes, desde) = resto.split_at(inicio);
+
+        let especial = if let Some(codigo) = desde.strip_prefix('`') {
+            codigo
+                .find('`')
+                .map(|fin| (Inline::Codigo(&codigo[..fin]), fin + 2))
+        } else {
+            desde.find("](").and_then(|medio| {
+                desde[medio + 2..].find(')').map(|fin| {
+                    let enlace = Inline::Enlace {
+                        texto: &desde[1..medio],
+                        url: &desde[medio + 2..medio + 2 + fin],
+                    };
+                    (enlace, medio + 3 + fin)
+                })
+            })
+        };
+
+        match especial {
+            Some((parte, longitud)) => {
+                if !antes.is_empty() {
+                    partes.push(Inline::Texto(antes));
+                }
+                partes.push(parte);
+                resto = &desde[longitud..];
+            }
+            None => {
+                // No cierra: el carácter se muestra tal cual
+                partes.push(Inline::Texto(&resto[..inicio + 1]));
+                resto = &desde[1..];
+            }
+        }
+    }
+
+    if !resto.is_empty() {
+        partes.push(Inline::Texto(resto));
+    }
+    partes
+}
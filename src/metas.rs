@@ -0,0 +1,181 @@
+use rusqlite::Result as SqlResult;
+
+use crate::Db;
+
+/// Tiempo registrado y tareas completadas en un día (número de día juliano)
+pub struct ResumenDia {
+    pub dia: i64,
+    pub segundos: i32,
+    pub completadas: i32,
+}
+
+/// Número de semana, de lunes a domingo, del día `dia`. Los días juliano
+/// cuentan desde un lunes, pero `dia` es el que empieza a medianoche, uno
+/// menos que el de ese mediodía.
+pub fn semana(dia: i64) -> i64 {
+    (dia + 1).div_euclid(7)
+}
+
+/// Periodo de una [`Meta`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Periodo {
+    Dia,
+    /// De lunes a domingo
+    Semana,
+}
+
+impl Periodo {
+    /// Número del periodo en que cae el día `dia`
+    pub fn numero(self, dia: i64) -> i64 {
+        match self {
+            Periodo::Dia => dia,
+            Periodo::Semana => semana(dia),
+        }
+    }
+
+    // Nombre en las claves de los ajustes
+    fn clave(self) -> &'static str {
+        match self {
+            Periodo::Dia => "diaria",
+            Periodo::Semana => "semanal",
+        }
+    }
+
+    /// Totales del periodo de `hoy` (`dia` es su primer día), con `hoy` en
+    /// lugar de lo guardado ese día
+    pub fn resumen(self, dias: &[ResumenDia], hoy: &ResumenDia) -> ResumenDia {
+        self.suma(dias, self.numero(hoy.dia), hoy)
+    }
+
+    // Suma de los días del periodo `numero`, con `hoy` en lugar de lo
+    // guardado ese día
+    fn suma(self, dias: &[ResumenDia], numero: i64, hoy: &ResumenDia) -> ResumenDia {
+        let primero = match self {
+            Periodo::Dia => numero,
+            Periodo::Semana => numero * 7 - 1,
+        };
+        dias.iter()
+            .filter(|r| r.dia != hoy.dia)
+            .chain(std::iter::once(hoy))
+            .filter(|r| self.numero(r.dia) == numero)
+            .fold(
+                ResumenDia {
+                    dia: primero,
+                    segundos: 0,
+                    completadas: 0,
+                },
+                |mut total, r| {
+                    total.segundos += r.segundos;
+                    total.completadas += r.completadas;
+                    total
+                },
+            )
+    }
+}
+
+/// Objetivo de un día o de una semana; un valor de 0 desactiva esa parte
+/// de la meta
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Meta {
+    pub segundos: i32,
+    pub tareas: i32,
+}
+
+impl Meta {
+    pub fn activa(&self) -> bool {
+        self.segundos > 0 || self.tareas > 0
+    }
+
+    pub fn cumplida(&self, segundos: i32, completadas: i32) -> bool {
+        self.activa() && segundos >= self.segundos && completadas >= self.tareas
+    }
+
+    /// Periodos consecutivos cumpliendo la meta hasta el de `hoy` (que
+    /// puede incluir tiempo aún no guardado). Si el actual todavía no se ha
+    /// cumplido, la racha se cuenta desde el anterior.
+    pub fn racha(&self, periodo: Periodo, dias: &[ResumenDia], hoy: &ResumenDia) -> u32 {
+        let cumplido = |numero| {
+            let total = periodo.suma(dias, numero, hoy);
+            self.cumplida(total.segundos, total.completadas)
+        };
+
+        let actual = periodo.numero(hoy.dia);
+        let mut racha = 0;
+        if cumplido(actual) {
+            racha += 1;
+        }
+        let mut numero = actual - 1;
+        while cumplido(numero) {
+            racha += 1;
+            numero -= 1;
+        }
+        racha
+    }
+}
+
+impl Db {
+    pub fn cargar_meta(&self, periodo: Periodo) -> SqlResult<Meta> {
+        let leer = |parte| -> SqlResult<i32> {
+            Ok(self
+                .obtener_ajuste(&clave_meta(periodo, parte))?
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0))
+        };
+        Ok(Meta {
+            segundos: leer("segundos")?,
+            tareas: leer("tareas")?,
+        })
+    }
+
+    pub fn guardar_meta(&self, periodo: Periodo, meta: &Meta) -> SqlResult<()> {
+        self.guardar_ajuste(&clave_meta(periodo, "segundos"), &meta.segundos.to_string())?;
+        self.guardar_ajuste(&clave_meta(periodo, "tareas"), &meta.tareas.to_string())
+    }
+
+    /// Guarda un tramo de tiempo trabajado en una tarea, fechado hoy
+    pub fn registrar_sesion(&self, tarea_id: i32, segundos: i32) -> SqlResult<()> {
+        self.conn.execute(
+            "INSERT INTO sesiones (tarea_id, fecha, segundos)
+             VALUES (?1, date('now', 'localtime'), ?2)",
+            [tarea_id, segundos],
+        )?;
+        Ok(())
+    }
+
+    pub fn dia_actual(&self) -> SqlResult<i64> {
+        self.conn.query_row(
+            "SELECT CAST(julianday(date('now', 'localtime')) AS INTEGER)",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    /// Totales por día a partir de las sesiones y fechas de completado guardadas
+    pub fn resumen_por_dia(&self) -> SqlResult<Vec<ResumenDia>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT dia, SUM(segundos), SUM(completadas) FROM (
+                SELECT CAST(julianday(fecha) AS INTEGER) AS dia, segundos, 0 AS completadas
+                FROM sesiones
+                UNION ALL
+                SELECT CAST(julianday(date(completada_en)) AS INTEGER), 0, 1
+                FROM tareas WHERE completada_en IS NOT NULL AND eliminada_en IS NULL
+            )
+            GROUP BY dia ORDER BY dia",
+        )?;
+
+        let dias = stmt.query_map([], |row| {
+            Ok(ResumenDia {
+                dia: row.get(0)?,
+                segundos: row.get(1)?,
+                completadas: row.get(2)?,
+            })
+        })?;
+
+        dias.collect()
+    }
+}
+
+// Clave de los ajustes de una parte de la meta, p. ej. `meta_diaria_segundos`
+fn clave_meta(periodo: Periodo, parte: &str) -> String {
+    format!("meta_{}_{}", periodo.clave(), parte)
+}
@@ -0,0 +1,44 @@
+//! Paleta de comandos: coincidencia difusa sobre acciones y tareas.
+
+/// Puntuación de `patron` como subsecuencia de `texto` (sin distinguir
+/// mayúsculas). `None` si no coincide; más alto es mejor. Se premian las
+/// letras consecutivas y las que empiezan palabra.
+pub fn fuzzy_score(patron: &str, texto: &str) -> Option<i32> {
+    let texto: Vec<char> = texto.to_lowercase().chars().collect();
+    let mut puntuacion = 0;
+    let mut pos = 0;
+    let mut anterior: Option<usize> = None;
+
+    for c in patron.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let encontrado = pos + texto[pos..].iter().position(|&t| t == c)?;
+
+        puntuacion += 1;
+        if anterior.is_some_and(|a| a + 1 == encontrado) {
+            puntuacion += 5;
+        }
+        if encontrado == 0 || !texto[encontrado - 1].is_alphanumeric() {
+            puntuacion += 3;
+        }
+        // Penalizar huecos largos
+        puntuacion -= (encontrado - pos).min(5) as i32;
+
+        anterior = Some(encontrado);
+        pos = encontrado + 1;
+    }
+
+    Some(puntuacion)
+}
+
+pub struct Palette {
+    pub query: String,
+    pub selected: usize,
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Self {
+            query: String::new(),
+            selected: 0,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use rusqlite::Result as SqlResult;
+
+use crate::{COLUMNAS_TAREA, Db, TodoItem, tarea_desde_fila};
+
+impl Db {
+    /// Tareas en la papelera, de la última eliminada a la primera
+    pub fn cargar_papelera(&self) -> SqlResult<Vec<TodoItem>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tareas WHERE eliminada_en IS NOT NULL
+             ORDER BY eliminada_en DESC, id DESC",
+            COLUMNAS_TAREA
+        ))?;
+        let tareas = stmt.query_map([], tarea_desde_fila)?;
+        tareas.collect()
+    }
+
+    /// Saca una tarea de la papelera y la devuelve a donde estaba
+    pub fn restaurar_eliminada(&self, id: i32) -> SqlResult<()> {
+        self.conn
+            .execute("UPDATE tareas SET eliminada_en = NULL WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// Borra para siempre una tarea de la papelera
+    pub fn eliminar_definitivamente(&self, id: i32) -> SqlResult<()> {
+        self.conn.execute(
+            "DELETE FROM tareas WHERE id = ?1 AND eliminada_en IS NOT NULL",
+            [id],
+        )?;
+        Ok(())
+    }
+
+    /// Borra para siempre todas las tareas de la papelera y devuelve
+    /// cuántas eran
+    pub fn vaciar_papelera(&self) -> SqlResult<usize> {
+        self.transaccion(|db| {
+            db.conn
+                .execute("DELETE FROM tareas WHERE eliminada_en IS NOT NULL", [])
+        })
+    }
+
+    /// Borra las tareas que llevan más de `dias` días en la papelera y
+    /// devuelve cuántas eran; con 0 no se borra nada
+    pub fn purgar_papelera(&self, dias: u32) -> SqlResult<usize> {
+        if dias == 0 {
+            return Ok(0);
+        }
+        self.transaccion(|db| {
+            db.conn.execute(
+                "DELETE FROM tareas WHERE eliminada_en IS NOT NULL
+                    AND eliminada_en <= datetime('now', 'localtime', ?1)",
+                [format!("-{} days", dias)],
+            )
+        })
+    }
+}
@@ -0,0 +1,52 @@
+use rusqlite::Result as SqlResult;
+
+use crate::{Db, SOLO_ACTIVAS};
+
+/// Nivel de prioridad de una tarea, guardado como entero en `tareas.prioridad`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Prioridad {
+    #[default]
+    Ninguna = 0,
+    Baja = 1,
+    Media = 2,
+    Alta = 3,
+    Urgente = 4,
+}
+
+impl Prioridad {
+    pub const TODAS: [Prioridad; 5] = [
+        Prioridad::Ninguna,
+        Prioridad::Baja,
+        Prioridad::Media,
+        Prioridad::Alta,
+        Prioridad::Urgente,
+    ];
+
+    pub fn desde_entero(valor: i32) -> Self {
+        Self::TODAS
+            .into_iter()
+            .find(|p| *p as i32 == valor)
+            .unwrap_or_default()
+    }
+}
+
+impl Db {
+    pub fn actualizar_prioridad(&self, id: i32, prioridad: Prioridad) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE tareas SET prioridad = ?1 WHERE id = ?2",
+            [prioridad as i32, id],
+        )?;
+        Ok(())
+    }
+
+    /// Ids de las tareas activas de más a menos prioridad; a igual
+    /// prioridad, en orden de creación
+    pub fn ids_por_prioridad(&self) -> SqlResult<Vec<i32>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id FROM tareas WHERE {} ORDER BY prioridad DESC, id",
+            SOLO_ACTIVAS
+        ))?;
+        let ids = stmt.query_map([], |row| row.get(0))?;
+        ids.collect()
+    }
+}
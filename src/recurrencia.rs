@@ -0,0 +1,220 @@
+use chrono::{Datelike, Days, Local, NaiveDate};
+use rusqlite::{OptionalExtension, Result as SqlResult, params};
+
+use crate::Db;
+
+/// Regla de repetición de una tarea
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Recurrencia {
+    Diaria,
+    /// Días de la semana en que se repite, empezando por el lunes
+    Semanal([bool; 7]),
+    /// Día del mes (se ajusta al último día en meses más cortos)
+    Mensual(u32),
+    /// Cada N días contados desde que se completa
+    CadaNDias(u32),
+}
+
+impl Recurrencia {
+    /// Formato guardado en la columna `recurrencia`
+    pub fn codificar(&self) -> String {
+        match self {
+            Recurrencia::Diaria => "diaria".to_string(),
+            Recurrencia::Semanal(dias) => {
+                let dias: Vec<String> =
+                    (0..7).filter(|&i| dias[i]).map(|i| i.to_string()).collect();
+                format!("semanal:{}", dias.join(","))
+            }
+            Recurrencia::Mensual(dia) => format!("mensual:{}", dia),
+            Recurrencia::CadaNDias(n) => format!("cada:{}", n),
+        }
+    }
+
+    pub fn decodificar(texto: &str) -> Option<Self> {
+        let (tipo, valor) = texto.split_once(':').unwrap_or((texto, ""));
+        match tipo {
+            "diaria" => Some(Recurrencia::Diaria),
+            "semanal" => {
+                let mut dias = [false; 7];
+                for dia in valor.split(',').filter(|d| !d.is_empty()) {
+                    dias[dia.parse::<usize>().ok().filter(|&d| d < 7)?] = true;
+                }
+                Some(Recurrencia::Semanal(dias))
+            }
+            "mensual" => valor.parse().ok().map(Recurrencia::Mensual),
+            "cada" => valor.parse().ok().map(Recurrencia::CadaNDias),
+            _ => None,
+        }
+    }
+
+    /// Primera fecha estrictamente posterior a `desde` que cumple la regla
+    pub fn siguiente(&self, desde: NaiveDate) -> NaiveDate {
+        match *self {
+            Recurrencia::Diaria => desde + Days::new(1),
+            Recurrencia::Semanal(dias) => (1..=7)
+                .map(|n| desde + Days::new(n))
+                .find(|f| dias[f.weekday().num_days_from_monday() as usize])
+                .unwrap_or(desde + Days::new(7)),
+            Recurrencia::Mensual(dia) => {
+                let este_mes = dia_del_mes(desde.year(), desde.month(), dia);
+                if este_mes > desde {
+                    este_mes
+                } else if desde.month() == 12 {
+                    dia_del_mes(desde.year() + 1, 1, dia)
+                } else {
+                    dia_del_mes(desde.year(), desde.month() + 1, dia)
+                }
+            }
+            Recurrencia::CadaNDias(n) => desde + Days::new(n.max(1) as u64),
+        }
+    }
+
+    /// Primera fecha a partir de `hoy` (incluido) al activar la regla
+    pub fn primera(&self, hoy: NaiveDate) -> NaiveDate {
+        match self {
+            Recurrencia::CadaNDias(_) => hoy,
+            _ => self.siguiente(hoy - Days::new(1)),
+        }
+    }
+
+    /// Fecha de la próxima ocurrencia al completar una que vencía en `prevista`.
+    /// Las ocurrencias atrasadas no se acumulan: se salta directamente a la
+    /// siguiente después de hoy.
+    pub fn tras_completar(&self, prevista: Option<NaiveDate>, hoy: NaiveDate) -> NaiveDate {
+        match self {
+            Recurrencia::CadaNDias(_) => self.siguiente(hoy),
+            _ => self.siguiente(prevista.map_or(hoy, |p| p.max(hoy))),
+        }
+    }
+}
+
+fn dia_del_mes(anio: i32, mes: u32, dia: u32) -> NaiveDate {
+    (1..=dia.clamp(1, 31))
+        .rev()
+        .find_map(|d| NaiveDate::from_ymd_opt(anio, mes, d))
+        .expect("todos los meses tienen día 1")
+}
+
+pub fn hoy() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+impl Db {
+    /// Cambia la regla de una tarea y devuelve su nueva fecha prevista
+    pub fn actualizar_recurrencia(
+        &self,
+        id: i32,
+        recurrencia: Option<Recurrencia>,
+    ) -> SqlResult<Option<NaiveDate>> {
+        let fecha = recurrencia.map(|r| r.primera(hoy()));
+        self.conn.execute(
+            "UPDATE tareas SET recurrencia = ?1, fecha_proxima = ?2 WHERE id = ?3",
+            params![recurrencia.map(|r| r.codificar()), fecha, id],
+        )?;
+        Ok(fecha)
+    }
+
+    /// Crea la siguiente ocurrencia de una tarea recurrente recién completada.
+    /// La tarea completada se conserva como historial, sin regla y enlazada
+    /// a la nueva hasta que se desmarque.
+    pub(crate) fn generar_siguiente_ocurrencia(&self, id: i32) -> SqlResult<Option<i32>> {
+        let fila = self
+            .conn
+            .query_row(
+                "SELECT descripcion, tiempo_estimado, recurrencia, fecha_proxima, prioridad
+                 FROM tareas WHERE id = ?1 AND recurrencia IS NOT NULL",
+                [id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Option<i32>>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, Option<NaiveDate>>(3)?,
+                        row.get::<_, i32>(4)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let Some((descripcion, estimado, regla, prevista, prioridad)) = fila else {
+            return Ok(None);
+        };
+        let Some(recurrencia) = Recurrencia::decodificar(&regla) else {
+            return Ok(None);
+        };
+
+        let siguiente = recurrencia.tras_completar(prevista, hoy());
+        self.conn.execute(
+            "INSERT INTO tareas
+                (descripcion, tiempo_estimado, recurrencia, fecha_proxima, prioridad)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![descripcion, estimado, regla, siguiente, prioridad],
+        )?;
+        let nueva = self.conn.last_insert_rowid() as i32;
+        // Con la versión subida, una edición hecha sin ver esto no puede
+        // devolverle la regla
+        self.conn.execute(
+            "UPDATE tareas SET recurrencia = NULL, ocurrencia_siguiente = ?1,
+                version = version + 1
+             WHERE id = ?2",
+            [nueva, id],
+        )?;
+
+        Ok(Some(nueva))
+    }
+
+    /// Id de la ocurrencia que se creó al completar la tarea `id`, mientras
+    /// siga enlazada a ella
+    pub fn ocurrencia_siguiente(&self, id: i32) -> SqlResult<Option<i32>> {
+        self.conn
+            .query_row(
+                "SELECT ocurrencia_siguiente FROM tareas WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map(Option::flatten)
+    }
+
+    /// Deja la ocurrencia creada al completar la tarea `id` como cualquier
+    /// otra: al desmarcarla ya no se quitará
+    pub(crate) fn desenlazar_ocurrencia(&self, id: i32) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE tareas SET ocurrencia_siguiente = NULL WHERE id = ?1",
+            [id],
+        )?;
+        Ok(())
+    }
+
+    /// Al desmarcar la tarea `id`, manda a la papelera la ocurrencia que se
+    /// creó al completarla y le devuelve la regla, salvo que esa ocurrencia
+    /// ya se haya tocado (editado, completado, con tiempo, archivado o
+    /// borrado): entonces se queda con la regla y solo se desenlazan.
+    pub(crate) fn deshacer_siguiente_ocurrencia(&self, id: i32) -> SqlResult<()> {
+        let Some(siguiente) = self.ocurrencia_siguiente(id)? else {
+            return Ok(());
+        };
+        self.desenlazar_ocurrencia(id)?;
+        let regla = self
+            .conn
+            .query_row(
+                "SELECT recurrencia FROM tareas
+                 WHERE id = ?1 AND version = 0 AND NOT completada
+                    AND tiempo_acumulado = 0
+                    AND archivada_en IS NULL AND eliminada_en IS NULL",
+                [siguiente],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?;
+        let Some(regla) = regla else {
+            return Ok(());
+        };
+        // Si entretanto se le ha puesto otra regla, se queda con esa
+        self.conn.execute(
+            "UPDATE tareas SET recurrencia = ?1, version = version + 1
+             WHERE id = ?2 AND recurrencia IS NULL",
+            params![regla, id],
+        )?;
+        self.eliminar_tarea(siguiente)
+    }
+}
@@ -0,0 +1,609 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use rusqlite::{OptionalExtension, Result as SqlResult};
+
+use crate::sincronizacion::{ErrorSincronizacion, Informe};
+use crate::{Db, ListaTareas, Prioridad, Recurrencia, Timer, TodoItem};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Orden {
+    /// El de la lista, que el usuario puede cambiar arrastrando
+    #[default]
+    Manual,
+    Prioridad,
+}
+
+/// Qué tareas se muestran y en qué orden
+#[derive(Clone, Debug, Default)]
+pub struct Filtro {
+    pub orden: Orden,
+    /// Solo las tareas con al menos esta prioridad
+    pub prioridad_minima: Prioridad,
+    /// Ids que coinciden con una búsqueda; `None` si no hay búsqueda activa
+    pub coincidencias: Option<HashSet<i32>>,
+}
+
+/// Valores editables de una tarea, tal como quedan al guardar
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Edicion {
+    pub descripcion: String,
+    pub notas: String,
+    pub estimacion: Option<i32>,
+    pub prioridad: Prioridad,
+    pub recurrencia: Option<Recurrencia>,
+}
+
+impl Edicion {
+    pub fn desde(tarea: &TodoItem) -> Self {
+        Self {
+            descripcion: tarea.text.clone(),
+            notas: tarea.notas.clone(),
+            estimacion: tarea.estimacion,
+            prioridad: tarea.prioridad,
+            recurrencia: tarea.recurrencia,
+        }
+    }
+}
+
+/// Resultado de [`ServicioTareas::editar`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResultadoEdicion {
+    Guardada,
+    /// No se guardó nada porque la descripción quedaba vacía
+    Vacia,
+    /// Otra instancia editó la tarea desde que se cargó. No se guardó nada
+    /// y la tarea en memoria ya tiene los cambios de la otra.
+    Conflicto,
+}
+
+/// Reglas de negocio sobre la lista de tareas, sin depender de ninguna
+/// interfaz: guarda cada cambio en `Db` y mantiene al día la copia en
+/// memoria, incluidos los temporizadores en marcha.
+///
+/// Las tareas se leen por páginas en orden de id; las que aún no se han
+/// cargado llegan con [`ServicioTareas::cargar_mas`].
+///
+/// Si otra instancia guarda cambios en la misma base de datos,
+/// [`ServicioTareas::hay_cambios_externos`] lo indica hasta que se llama a
+/// [`ServicioTareas::recargar`].
+pub struct ServicioTareas {
+    db: Db,
+    tareas: ListaTareas,
+    /// Id de la última tarea leída por páginas
+    cargadas_hasta: i32,
+    todo_cargado: bool,
+    /// `data_version` de la base de datos al cargar las tareas
+    version_datos: i64,
+    /// Índices en `tareas` de todas las tareas por orden de prioridad, con
+    /// [`Db::cambios_guardados`] y el número de tareas al calcularlos
+    por_prioridad: Option<(u64, usize, Vec<usize>)>,
+}
+
+impl ServicioTareas {
+    pub const TAMANO_PAGINA: usize = 200;
+
+    /// Abre el servicio sobre `db` con la primera página de tareas cargada,
+    /// tras vaciar de la papelera lo que haya caducado
+    pub fn new(db: Db) -> Self {
+        let dias = db.cargar_ajustes().unwrap_or_default().dias_papelera;
+        let _ = db.purgar_papelera(dias);
+        let mut servicio = Self {
+            db,
+            tareas: ListaTareas::default(),
+            cargadas_hasta: 0,
+            todo_cargado: false,
+            version_datos: 0,
+            por_prioridad: None,
+        };
+        servicio.version_datos = servicio.db.version_datos().unwrap_or_default();
+        servicio.cargar_mas();
+        servicio
+    }
+
+    pub fn db(&self) -> &Db {
+        &self.db
+    }
+
+    pub fn tareas(&self) -> &ListaTareas {
+        &self.tareas
+    }
+
+    pub fn tarea(&self, id: i32) -> Option<&TodoItem> {
+        self.tareas.get(id)
+    }
+
+    pub fn todo_cargado(&self) -> bool {
+        self.todo_cargado
+    }
+
+    /// Añade la siguiente página de tareas a las ya cargadas
+    pub fn cargar_mas(&mut self) {
+        if self.todo_cargado {
+            return;
+        }
+        let pagina = self
+            .db
+            .cargar_pagina_tareas(self.cargadas_hasta, Self::TAMANO_PAGINA)
+            .unwrap_or_default();
+        self.todo_cargado = pagina.len() < Self::TAMANO_PAGINA;
+        if let Some(ultima) = pagina.last() {
+            self.cargadas_hasta = ultima.id;
+        }
+        self.tareas.extend(pagina);
+    }
+
+    pub fn cargar_todo(&mut self) {
+        while !self.todo_cargado {
+            self.cargar_mas();
+        }
+    }
+
+    /// Vuelve a leer de `db` al menos tantas tareas como había cargadas,
+    /// conservando los temporizadores en marcha y el orden de la lista
+    pub fn recargar(&mut self) {
+        self.version_datos = self.db.version_datos().unwrap_or(self.version_datos);
+        self.por_prioridad = None;
+        let orden: Vec<i32> = self.tareas.iter().map(|t| t.id).collect();
+        let temporizadores: Vec<(i32, Timer)> = self
+            .tareas
+            .iter_mut()
+            .filter(|t| t.temporizador_activo())
+            .filter_map(|t| Some((t.id, t.temporizador.take()?)))
+            .collect();
+        let cargadas = self.tareas.len().max(1);
+        let todas = self.todo_cargado;
+        self.tareas.clear();
+        self.cargadas_hasta = 0;
+        self.todo_cargado = false;
+        while !self.todo_cargado && (todas || self.tareas.len() < cargadas) {
+            self.cargar_mas();
+        }
+        self.tareas.ordenar_como(&orden);
+        for (id, temporizador) in temporizadores {
+            if let Some(tarea) = self.tareas.get_mut(id) {
+                tarea.temporizador = Some(temporizador);
+            }
+        }
+    }
+
+    /// Si otra instancia ha guardado cambios desde la última carga
+    pub fn hay_cambios_externos(&self) -> bool {
+        self.db
+            .version_datos()
+            .is_ok_and(|version| version != self.version_datos)
+    }
+
+    /// Sincroniza con la carpeta compartida, si se ha elegido una, y
+    /// recarga las tareas si llega algún cambio
+    pub fn sincronizar(&mut self) -> Result<Option<Informe>, ErrorSincronizacion> {
+        if self.db.carpeta_sincronizacion()?.is_none() {
+            return Ok(None);
+        }
+        let informe = self.db.sincronizar()?;
+        if informe.recibidos > 0 {
+            self.recargar();
+        }
+        Ok(Some(informe))
+    }
+
+    /// Índices en [`Self::tareas`] de las tareas que pasan el filtro, en su
+    /// orden. Ordenar por prioridad necesita tener todas cargadas.
+    pub fn visibles(&mut self, filtro: &Filtro) -> Vec<usize> {
+        let todas: Vec<usize>;
+        let orden = match filtro.orden {
+            Orden::Manual => {
+                todas = (0..self.tareas.len()).collect();
+                &todas
+            }
+            Orden::Prioridad => {
+                self.ordenar_por_prioridad();
+                &self.por_prioridad.as_ref().unwrap().2
+            }
+        };
+        orden
+            .iter()
+            .copied()
+            .filter(|&idx| self.tareas[idx].prioridad >= filtro.prioridad_minima)
+            .filter(|&idx| {
+                filtro
+                    .coincidencias
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&self.tareas[idx].id))
+            })
+            .collect()
+    }
+
+    // Calcula `por_prioridad` con el orden que da la base de datos. Solo se
+    // vuelve a pedir si se ha guardado algo o ha cambiado la lista desde la
+    // última vez.
+    fn ordenar_por_prioridad(&mut self) {
+        self.cargar_todo();
+        let clave = (self.db.cambios_guardados(), self.tareas.len());
+        if self
+            .por_prioridad
+            .as_ref()
+            .is_none_or(|(cambios, tareas, _)| (*cambios, *tareas) != clave)
+        {
+            let posiciones: HashMap<i32, usize> = self
+                .tareas
+                .iter()
+                .enumerate()
+                .map(|(idx, t)| (t.id, idx))
+                .collect();
+            let indices = self
+                .db
+                .ids_por_prioridad()
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|id| posiciones.get(&id).copied())
+                .collect();
+            self.por_prioridad = Some((clave.0, clave.1, indices));
+        }
+    }
+
+    /// Crea una tarea y devuelve su id; `None` si la descripción está vacía.
+    /// Si quedan páginas por cargar, aparecerá con ellas al final de la lista.
+    pub fn agregar(&mut self, descripcion: &str) -> SqlResult<Option<i32>> {
+        let descripcion = descripcion.trim();
+        if descripcion.is_empty() {
+            return Ok(None);
+        }
+        let id = self.db.agregar_tarea(descripcion)?;
+        if self.todo_cargado {
+            self.tareas.push(self.db.cargar_tarea(id)?);
+        }
+        Ok(Some(id))
+    }
+
+    /// Marca una tarea como completada o pendiente. Al completar una tarea
+    /// recurrente la regla pasa a su siguiente ocurrencia, cuyo id se devuelve;
+    /// al desmarcarla vuelve, y esa ocurrencia se quita si no se ha tocado.
+    pub fn marcar(&mut self, id: i32, completada: bool) -> SqlResult<Option<i32>> {
+        let tareas = &self.tareas;
+        let (siguiente, enlazadas) = self.db.transaccion(|db| {
+            let enlazadas = match completada {
+                true => Vec::new(),
+                false => desenlazar_en_uso(tareas, db, &[id])?,
+            };
+            Ok((db.actualizar_tarea(id, completada)?, enlazadas))
+        })?;
+        self.anotar_marcada(id, completada, siguiente)?;
+        self.anotar_desenlazadas(&[id], &enlazadas)?;
+        Ok(siguiente)
+    }
+
+    // Tras desmarcar `ids`, las que recuperaron la regla y las ocurrencias
+    // que se quitaron
+    fn anotar_desenlazadas(&mut self, ids: &[i32], enlazadas: &[i32]) -> SqlResult<()> {
+        if enlazadas.is_empty() {
+            return Ok(());
+        }
+        for &id in ids.iter().chain(enlazadas) {
+            self.refrescar(id)?;
+        }
+        Ok(())
+    }
+
+    // Lleva a la lista lo que `Db::actualizar_tarea` ya ha guardado
+    fn anotar_marcada(
+        &mut self,
+        id: i32,
+        completada: bool,
+        siguiente: Option<i32>,
+    ) -> SqlResult<()> {
+        if let Some(tarea) = self.tareas.get_mut(id) {
+            tarea.checked = completada;
+        }
+        if let Some(nueva_id) = siguiente {
+            // La regla ha pasado a la nueva, y la versión ha subido
+            self.refrescar(id)?;
+            if self.todo_cargado {
+                self.tareas.push(self.db.cargar_tarea(nueva_id)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Guarda los cambios de una tarea, salvo que la descripción quede
+    /// vacía o que otra instancia la haya editado desde que se cargó
+    pub fn editar(&mut self, id: i32, edicion: &Edicion) -> SqlResult<ResultadoEdicion> {
+        self.editar_y_marcar(id, edicion, None)
+    }
+
+    /// Como [`ServicioTareas::editar`], y en la misma transacción la marca
+    /// como completada o pendiente si `completada` no es `None`. Si no se
+    /// guarda la edición, tampoco se marca.
+    pub fn editar_y_marcar(
+        &mut self,
+        id: i32,
+        edicion: &Edicion,
+        completada: Option<bool>,
+    ) -> SqlResult<ResultadoEdicion> {
+        let descripcion = edicion.descripcion.trim();
+        if descripcion.is_empty() {
+            return Ok(ResultadoEdicion::Vacia);
+        }
+        let version = match self.tareas.get(id) {
+            Some(tarea) => tarea.version,
+            None => self.db.cargar_tarea(id)?.version,
+        };
+        let anterior = self.tareas.get(id).map(|t| t.recurrencia);
+        let cambia_recurrencia = anterior != Some(edicion.recurrencia);
+
+        // `None` si otra instancia la editó antes; entonces no se escribe nada
+        let tareas = &self.tareas;
+        let guardado = self.db.transaccion(|db| {
+            if !db.reservar_edicion(id, version)? {
+                return Ok(None);
+            }
+            db.actualizar_descripcion(id, descripcion)?;
+            db.actualizar_estimacion(id, edicion.estimacion)?;
+            db.actualizar_prioridad(id, edicion.prioridad)?;
+            db.actualizar_notas(id, &edicion.notas)?;
+            let fecha_proxima = cambia_recurrencia
+                .then(|| db.actualizar_recurrencia(id, edicion.recurrencia))
+                .transpose()?;
+            let enlazadas = match completada {
+                Some(false) => desenlazar_en_uso(tareas, db, &[id])?,
+                _ => Vec::new(),
+            };
+            let siguiente = match completada {
+                Some(completada) => db.actualizar_tarea(id, completada)?,
+                None => None,
+            };
+            Ok(Some((fecha_proxima, siguiente, enlazadas)))
+        })?;
+        let Some((fecha_proxima, siguiente, enlazadas)) = guardado else {
+            self.refrescar(id)?;
+            return Ok(ResultadoEdicion::Conflicto);
+        };
+
+        if let Some(tarea) = self.tareas.get_mut(id) {
+            tarea.text = descripcion.to_string();
+            tarea.estimacion = edicion.estimacion;
+            tarea.prioridad = edicion.prioridad;
+            tarea.notas = edicion.notas.clone();
+            tarea.version = version + 1;
+            if let Some(fecha) = fecha_proxima {
+                tarea.recurrencia = edicion.recurrencia;
+                tarea.fecha_proxima = fecha;
+            }
+        }
+        if let Some(completada) = completada {
+            self.anotar_marcada(id, completada, siguiente)?;
+            self.anotar_desenlazadas(&[id], &enlazadas)?;
+        }
+        Ok(ResultadoEdicion::Guardada)
+    }
+
+    // Vuelve a leer de `db` una tarea cargada, conservando su temporizador.
+    // Si otra instancia la ha borrado, sale de la lista.
+    fn refrescar(&mut self, id: i32) -> SqlResult<()> {
+        let nueva = self.db.cargar_tarea(id).optional()?;
+        let Some(tarea) = self.tareas.get_mut(id) else {
+            return Ok(());
+        };
+        match nueva {
+            Some(mut nueva) => {
+                nueva.temporizador = tarea.temporizador.take();
+                *tarea = nueva;
+            }
+            None => {
+                self.tareas.quitar(id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Mueve una tarea a la papelera, con el tiempo de su temporizador
+    /// guardado por si se restaura
+    pub fn eliminar(&mut self, id: i32) -> SqlResult<()> {
+        self.parando_temporizadores(&[id], |db| db.eliminar_tarea(id))?;
+        self.tareas.quitar(id);
+        Ok(())
+    }
+
+    /// Devuelve a la lista una tarea de la papelera
+    pub fn restaurar_eliminada(&mut self, id: i32) -> SqlResult<()> {
+        self.db.restaurar_eliminada(id)?;
+        self.reincorporar(id)
+    }
+
+    /// Archiva las tareas completadas, guardando antes el tiempo de las que
+    /// tuvieran el temporizador en marcha. Devuelve cuántas se archivaron.
+    pub fn archivar_completadas(&mut self) -> SqlResult<usize> {
+        let completadas: Vec<i32> = self
+            .tareas
+            .iter()
+            .filter(|t| t.checked)
+            .map(|t| t.id)
+            .collect();
+        let ids = self.parando_temporizadores(&completadas, |db| db.archivar_completadas())?;
+        for &id in &ids {
+            self.tareas.quitar(id);
+        }
+        Ok(ids.len())
+    }
+
+    /// Devuelve a la lista una tarea archivada
+    pub fn restaurar_archivada(&mut self, id: i32) -> SqlResult<()> {
+        self.db.restaurar_archivada(id)?;
+        self.reincorporar(id)
+    }
+
+    // Añade a la lista una tarea que vuelve a estar activa. Las páginas ya
+    // leídas no volverán a pasar por su id.
+    fn reincorporar(&mut self, id: i32) -> SqlResult<()> {
+        if (self.todo_cargado || id <= self.cargadas_hasta)
+            && self.tareas.get(id).is_none()
+            && let Some(tarea) = self.db.cargar_tarea(id).optional()?
+        {
+            self.tareas.push(tarea);
+        }
+        Ok(())
+    }
+
+    /// Mueve la tarea `id` al lugar de `destino`. Es solo en memoria, pero
+    /// se conserva al recargar.
+    pub fn mover(&mut self, id: i32, destino: i32) -> bool {
+        self.por_prioridad = None;
+        self.tareas.mover(id, destino)
+    }
+
+    /// Inicia o pausa el temporizador; devuelve si ha quedado en marcha
+    pub fn alternar_temporizador(&mut self, id: i32) -> bool {
+        let Some(tarea) = self.tareas.get_mut(id) else {
+            return false;
+        };
+        if tarea.temporizador_activo() {
+            tarea.pausar_temporizador(&self.db);
+            false
+        } else {
+            tarea.iniciar_temporizador(&self.db);
+            true
+        }
+    }
+
+    pub fn resetear_temporizador(&mut self, id: i32) {
+        if let Some(tarea) = self.tareas.get_mut(id) {
+            tarea.resetear_temporizador(&self.db);
+        }
+    }
+
+    pub fn hay_temporizador_activo(&self) -> bool {
+        self.tareas.iter().any(TodoItem::temporizador_activo)
+    }
+
+    /// Marca varias tareas a la vez; devuelve los ids de las nuevas
+    /// ocurrencias de las recurrentes
+    pub fn marcar_varias(&mut self, ids: &[i32], completada: bool) -> SqlResult<Vec<i32>> {
+        let tareas = &self.tareas;
+        let (nuevas, enlazadas) = self.db.transaccion(|db| {
+            let enlazadas = match completada {
+                true => Vec::new(),
+                false => desenlazar_en_uso(tareas, db, ids)?,
+            };
+            Ok((db.marcar_tareas(ids, completada)?, enlazadas))
+        })?;
+        self.anotar_desenlazadas(ids, &enlazadas)?;
+        for &id in ids {
+            if let Some(tarea) = self.tareas.get_mut(id) {
+                tarea.checked = completada;
+            }
+        }
+        if !nuevas.is_empty() {
+            // La regla pasó a las nuevas ocurrencias, y su versión ha subido
+            for &id in ids {
+                if self
+                    .tareas
+                    .get(id)
+                    .is_some_and(|t| t.checked && t.recurrencia.is_some())
+                {
+                    self.refrescar(id)?;
+                }
+            }
+            if self.todo_cargado {
+                for &id in &nuevas {
+                    self.tareas.push(self.db.cargar_tarea(id)?);
+                }
+            }
+        }
+        Ok(nuevas)
+    }
+
+    /// Mueve varias tareas a la papelera
+    pub fn eliminar_varias(&mut self, ids: &[i32]) -> SqlResult<()> {
+        self.parando_temporizadores(ids, |db| db.eliminar_tareas(ids))?;
+        for &id in ids {
+            self.tareas.quitar(id);
+        }
+        Ok(())
+    }
+
+    /// Archiva varias tareas, estén completadas o no
+    pub fn archivar_varias(&mut self, ids: &[i32]) -> SqlResult<()> {
+        self.parando_temporizadores(ids, |db| db.archivar_tareas(ids))?;
+        for &id in ids {
+            self.tareas.quitar(id);
+        }
+        Ok(())
+    }
+
+    pub fn resetear_temporizadores(&mut self, ids: &[i32]) -> SqlResult<()> {
+        self.db.resetear_tiempos(ids)?;
+        for &id in ids {
+            if let Some(tarea) = self.tareas.get_mut(id) {
+                tarea.tiempo_acumulado = 0;
+                tarea.temporizador = None;
+            }
+        }
+        Ok(())
+    }
+
+    // Ejecuta `operacion`, que quita `ids` de la lista, en una transacción
+    // en la que antes se paran sus temporizadores en marcha, guardando su
+    // sesión y con su gancho. Si falla, siguen en marcha como estaban.
+    fn parando_temporizadores<T>(
+        &mut self,
+        ids: &[i32],
+        operacion: impl FnOnce(&Db) -> SqlResult<T>,
+    ) -> SqlResult<T> {
+        let en_marcha: Vec<(i32, i32, Instant)> = ids
+            .iter()
+            .filter_map(|&id| self.tareas.get(id))
+            .filter(|t| t.temporizador_activo())
+            .filter_map(|t| Some((t.id, t.tiempo_acumulado, t.temporizador.as_ref()?.inicio)))
+            .collect();
+        let tareas = &mut self.tareas;
+        let resultado = self.db.transaccion(|db| {
+            for &(id, ..) in &en_marcha {
+                if let Some(tarea) = tareas.get_mut(id) {
+                    tarea.pausar_temporizador(db);
+                }
+            }
+            operacion(db)
+        });
+        if resultado.is_err() {
+            for (id, tiempo_acumulado, inicio) in en_marcha {
+                if let Some(tarea) = self.tareas.get_mut(id) {
+                    tarea.tiempo_acumulado = tiempo_acumulado;
+                    tarea.temporizador = Some(Timer {
+                        inicio,
+                        activo: true,
+                    });
+                }
+            }
+        }
+        resultado
+    }
+
+    /// Pausa y guarda todos los temporizadores en marcha
+    pub fn pausar_todos(&mut self) {
+        for tarea in self.tareas.iter_mut() {
+            if tarea.temporizador_activo() {
+                tarea.pausar_temporizador(&self.db);
+            }
+        }
+    }
+}
+
+// Ocurrencias creadas al completar `ids`, que al desmarcarlas se quitan si no
+// se han tocado. Las que tienen el temporizador en marcha están en uso aunque
+// aún no hayan guardado tiempo: se desenlazan antes para que se queden.
+fn desenlazar_en_uso(tareas: &ListaTareas, db: &Db, ids: &[i32]) -> SqlResult<Vec<i32>> {
+    let mut enlazadas = Vec::new();
+    for &id in ids {
+        let Some(siguiente) = db.ocurrencia_siguiente(id)? else {
+            continue;
+        };
+        if tareas
+            .get(siguiente)
+            .is_some_and(TodoItem::temporizador_activo)
+        {
+            db.desenlazar_ocurrencia(id)?;
+        }
+        enlazadas.push(siguiente);
+    }
+    Ok(enlazadas)
+}
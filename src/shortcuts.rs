@@ -0,0 +1,212 @@
+//! Atajos de teclado configurables. Se guardan en la tabla `ajustes`
+//! como texto ("Ctrl+Shift+J"), varios por acción separados por comas.
+
+use egui::{Key, KeyboardShortcut, Modifiers};
+use pixi::{Db, Textos};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    SelectNext,
+    SelectPrevious,
+    ToggleDone,
+    Edit,
+    Delete,
+    ToggleTimer,
+    ResetTimer,
+    MoveUp,
+    MoveDown,
+    ToggleMark,
+    SelectAll,
+    FocusNewTask,
+    ShowHelp,
+    CommandPalette,
+}
+
+impl Action {
+    pub const ALL: [Action; 14] = [
+        Action::SelectNext,
+        Action::SelectPrevious,
+        Action::ToggleDone,
+        Action::Edit,
+        Action::Delete,
+        Action::ToggleTimer,
+        Action::ResetTimer,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::ToggleMark,
+        Action::SelectAll,
+        Action::FocusNewTask,
+        Action::ShowHelp,
+        Action::CommandPalette,
+    ];
+
+    pub fn description(self, t: &Textos) -> &'static str {
+        match self {
+            Action::SelectNext => t.accion_siguiente,
+            Action::SelectPrevious => t.accion_anterior,
+            Action::ToggleDone => t.accion_marcar,
+            Action::Edit => t.accion_editar,
+            Action::Delete => t.accion_eliminar,
+            Action::ToggleTimer => t.accion_temporizador,
+            Action::ResetTimer => t.accion_reiniciar,
+            Action::MoveUp => t.accion_mover_arriba,
+            Action::MoveDown => t.accion_mover_abajo,
+            Action::ToggleMark => t.accion_marcar_seleccion,
+            Action::SelectAll => t.accion_seleccionar_todo,
+            Action::FocusNewTask => t.accion_nueva_tarea,
+            Action::ShowHelp => t.accion_ayuda,
+            Action::CommandPalette => t.accion_paleta,
+        }
+    }
+
+    fn setting_key(self) -> String {
+        format!("atajo.{:?}", self)
+    }
+
+    fn defaults(self) -> Vec<KeyboardShortcut> {
+        let none = Modifiers::NONE;
+        let shift = Modifiers::SHIFT;
+        let keys: &[(Modifiers, Key)] = match self {
+            Action::SelectNext => &[(none, Key::J), (none, Key::ArrowDown)],
+            Action::SelectPrevious => &[(none, Key::K), (none, Key::ArrowUp)],
+            Action::ToggleDone => &[(none, Key::Space), (none, Key::X)],
+            Action::Edit => &[(none, Key::E), (none, Key::F2)],
+            Action::Delete => &[(none, Key::Delete)],
+            Action::ToggleTimer => &[(none, Key::T)],
+            Action::ResetTimer => &[(none, Key::R)],
+            Action::MoveUp => &[(shift, Key::K), (shift, Key::ArrowUp)],
+            Action::MoveDown => &[(shift, Key::J), (shift, Key::ArrowDown)],
+            Action::ToggleMark => &[(none, Key::M)],
+            Action::SelectAll => &[(Modifiers::COMMAND, Key::A)],
+            Action::FocusNewTask => &[(none, Key::N)],
+            Action::ShowHelp => &[(none, Key::F1), (none, Key::Questionmark)],
+            Action::CommandPalette => &[(Modifiers::COMMAND, Key::P), (Modifiers::COMMAND, Key::K)],
+        };
+        keys.iter()
+            .map(|&(modifiers, key)| KeyboardShortcut::new(modifiers, key))
+            .collect()
+    }
+}
+
+pub struct KeyBindings {
+    bindings: Vec<(Action, Vec<KeyboardShortcut>)>,
+}
+
+impl KeyBindings {
+    pub fn load(db: &Db) -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let guardados = db
+                    .obtener_ajuste(&action.setting_key())
+                    .ok()
+                    .flatten()
+                    .map(|texto| texto.split(',').filter_map(parse_shortcut).collect());
+                (action, guardados.unwrap_or_else(|| action.defaults()))
+            })
+            .collect();
+        Self { bindings }
+    }
+
+    pub fn shortcuts(&self, action: Action) -> &[KeyboardShortcut] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map_or(&[], |(_, atajos)| atajos.as_slice())
+    }
+
+    /// Sustituye los atajos de una acción por uno solo
+    pub fn set(&mut self, db: &Db, action: Action, shortcut: KeyboardShortcut) {
+        if let Some((_, atajos)) = self.bindings.iter_mut().find(|(a, _)| *a == action) {
+            *atajos = vec![shortcut];
+        }
+        self.save(db, action);
+    }
+
+    pub fn reset(&mut self, db: &Db) {
+        for (action, atajos) in &mut self.bindings {
+            *atajos = action.defaults();
+        }
+        for action in Action::ALL {
+            self.save(db, action);
+        }
+    }
+
+    fn save(&self, db: &Db, action: Action) {
+        let texto: Vec<String> = self.shortcuts(action).iter().map(format_shortcut).collect();
+        let _ = db.guardar_ajuste(&action.setting_key(), &texto.join(","));
+    }
+
+    /// Consume los atajos de una sola acción; útil para las que deben
+    /// funcionar aunque haya un campo de texto con el foco
+    pub fn consume(&self, ctx: &egui::Context, action: Action) -> bool {
+        ctx.input_mut(|input| {
+            self.shortcuts(action)
+                .iter()
+                .any(|s| input.consume_shortcut(s))
+        })
+    }
+
+    /// Acciones cuyos atajos se han pulsado en este frame.
+    /// Los atajos con más modificadores se comprueban antes, para que
+    /// Shift+J no active también la acción de J.
+    pub fn pressed(&self, ctx: &egui::Context) -> Vec<Action> {
+        let mut atajos: Vec<(Action, &KeyboardShortcut)> = self
+            .bindings
+            .iter()
+            .flat_map(|(action, atajos)| atajos.iter().map(move |s| (*action, s)))
+            .collect();
+        atajos.sort_by_key(|(_, s)| std::cmp::Reverse(modifier_count(s.modifiers)));
+
+        ctx.input_mut(|input| {
+            atajos
+                .into_iter()
+                .filter(|(_, s)| input.consume_shortcut(s))
+                .map(|(action, _)| action)
+                .collect()
+        })
+    }
+}
+
+fn modifier_count(modifiers: Modifiers) -> u32 {
+    [
+        modifiers.ctrl || modifiers.command,
+        modifiers.shift,
+        modifiers.alt,
+    ]
+    .into_iter()
+    .filter(|m| *m)
+    .count() as u32
+}
+
+fn format_shortcut(shortcut: &KeyboardShortcut) -> String {
+    let mut partes = Vec::new();
+    if shortcut.modifiers.ctrl || shortcut.modifiers.command {
+        partes.push("Ctrl");
+    }
+    if shortcut.modifiers.alt {
+        partes.push("Alt");
+    }
+    if shortcut.modifiers.shift {
+        partes.push("Shift");
+    }
+    partes.push(shortcut.logical_key.name());
+    partes.join("+")
+}
+
+fn parse_shortcut(texto: &str) -> Option<KeyboardShortcut> {
+    let mut modifiers = Modifiers::NONE;
+    let mut partes = texto.trim().split('+').peekable();
+    while let Some(parte) = partes.next() {
+        if partes.peek().is_none() {
+            return Key::from_name(parte).map(|key| KeyboardShortcut::new(modifiers, key));
+        }
+        match parte {
+            "Ctrl" => modifiers = modifiers | Modifiers::COMMAND,
+            "Alt" => modifiers = modifiers | Modifiers::ALT,
+            "Shift" => modifiers = modifiers | Modifiers::SHIFT,
+            _ => return None,
+        }
+    }
+    None
+}
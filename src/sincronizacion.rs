@@ -0,0 +1,647 @@
+//! Sincronización entre equipos a través de una carpeta compartida, por
+//! ejemplo una carpeta de Dropbox o Syncthing.
+//!
+//! Cada equipo escribe sus cambios solo en su propio diario,
+//! `<dispositivo>.cambios`, y lee los de los demás, así que nunca hay dos
+//! equipos escribiendo el mismo fichero. Desde la primera sincronización,
+//! unos disparadores anotan en la tabla `cambios` cada cambio local hasta
+//! que se copia al diario.
+//!
+//! Cada cambio lleva su marca (instante y equipo) y la del valor que
+//! sustituía. Si un cambio recibido no parte del valor que hay aquí, los
+//! dos equipos cambiaron la tarea sin ver el cambio del otro, y se fusionan:
+//! - `descripcion`: gana el más reciente y se informa del conflicto.
+//! - `completada`: gana completarla, llegue en el orden que llegue.
+//! - `tiempo_acumulado`: se anotan incrementos, así que se suman los dos.
+//! - la papelera: gana el más reciente.
+//! - borrar para siempre: gana siempre, y la tarea no vuelve aunque su
+//!   creación llegue después.
+//!
+//! Solo se sincronizan estos campos; las notas, la prioridad y demás
+//! siguen siendo de cada equipo.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use rusqlite::{OptionalExtension, Result as SqlResult, params};
+
+use crate::Db;
+use crate::i18n::Idioma;
+
+const EXTENSION: &str = "cambios";
+
+const CLAVE_DISPOSITIVO: &str = "sync_dispositivo";
+const CLAVE_CARPETA: &str = "sync_carpeta";
+/// Seguida del dispositivo, el número del último cambio suyo ya aplicado
+const PREFIJO_VISTO: &str = "sync_visto:";
+/// Seguida del dispositivo, hasta qué byte de su diario se ha leído
+const PREFIJO_POSICION: &str = "sync_posicion:";
+
+/// Marca de un cambio: instante en UTC y equipo que lo hizo. Comparadas
+/// como texto, ordenan por tiempo y desempatan por equipo.
+const MARCA: &str = "strftime('%Y-%m-%dT%H:%M:%f', 'now') || '@' || \
+    (SELECT valor FROM ajustes WHERE clave = 'sync_dispositivo')";
+
+/// Instante de la marca de una descripción de ejemplo que nadie ha
+/// cambiado: anterior a cualquier otro, para que gane cualquier edición
+const ANTES_DE_TODO: &str = "0000-00-00T00:00:00.000";
+
+/// Campos que se fusionan comparando marcas, con la expresión de su valor
+/// en la fila `NEW` de un disparador
+const CAMPOS: [(&str, &str, &str); 3] = [
+    ("descripcion", "descripcion", "NEW.descripcion"),
+    ("completada", "completada", "NEW.completada"),
+    (
+        "eliminada",
+        "eliminada_en",
+        "COALESCE(NEW.eliminada_en, '')",
+    ),
+];
+
+#[derive(Debug)]
+pub enum ErrorSincronizacion {
+    Sql(rusqlite::Error),
+    Io(io::Error),
+    /// No se ha elegido carpeta
+    SinCarpeta,
+}
+
+impl fmt::Display for ErrorSincronizacion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorSincronizacion::Sql(e) => write!(f, "{}", e),
+            ErrorSincronizacion::Io(e) => write!(f, "{}", e),
+            ErrorSincronizacion::SinCarpeta => write!(f, "no sync folder"),
+        }
+    }
+}
+
+impl std::error::Error for ErrorSincronizacion {}
+
+impl From<rusqlite::Error> for ErrorSincronizacion {
+    fn from(e: rusqlite::Error) -> Self {
+        ErrorSincronizacion::Sql(e)
+    }
+}
+
+impl From<io::Error> for ErrorSincronizacion {
+    fn from(e: io::Error) -> Self {
+        ErrorSincronizacion::Io(e)
+    }
+}
+
+/// Descripciones cambiadas a la vez en dos equipos
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflicto {
+    pub id: i32,
+    pub conservada: String,
+    pub descartada: String,
+}
+
+/// Resultado de [`Db::sincronizar`]
+#[derive(Clone, Debug, Default)]
+pub struct Informe {
+    pub enviados: usize,
+    pub recibidos: usize,
+    pub conflictos: Vec<Conflicto>,
+}
+
+// Una línea de un diario
+struct Cambio {
+    /// Número del cambio en su equipo; crece siempre, aunque el reloj del
+    /// equipo retroceda
+    seq: i64,
+    marca: String,
+    base: Option<String>,
+    uid: String,
+    campo: String,
+    valor: String,
+}
+
+impl Cambio {
+    fn linea(&self) -> String {
+        [
+            self.seq.to_string().as_str(),
+            &self.marca,
+            self.base.as_deref().unwrap_or(""),
+            &self.uid,
+            &self.campo,
+            &self.valor,
+        ]
+        .map(escapar)
+        .join("\t")
+    }
+
+    fn desde_linea(linea: &str) -> Option<Self> {
+        let mut partes = linea.split('\t').map(desescapar);
+        let seq = partes.next()?.parse().ok()?;
+        let (marca, base) = (partes.next()?, partes.next()?);
+        let (uid, campo, valor) = (partes.next()?, partes.next()?, partes.next()?);
+        Some(Cambio {
+            seq,
+            marca,
+            base: (!base.is_empty()).then_some(base),
+            uid,
+            campo,
+            valor,
+        })
+    }
+}
+
+fn escapar(texto: &str) -> String {
+    texto
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn desescapar(texto: &str) -> String {
+    let mut salida = String::with_capacity(texto.len());
+    let mut caracteres = texto.chars();
+    while let Some(c) = caracteres.next() {
+        if c != '\\' {
+            salida.push(c);
+            continue;
+        }
+        match caracteres.next() {
+            Some('t') => salida.push('\t'),
+            Some('n') => salida.push('\n'),
+            Some('r') => salida.push('\r'),
+            Some(otro) => salida.push(otro),
+            None => {}
+        }
+    }
+    salida
+}
+
+impl Db {
+    /// Carpeta compartida con los demás equipos, si se ha elegido
+    pub fn carpeta_sincronizacion(&self) -> SqlResult<Option<PathBuf>> {
+        Ok(self
+            .obtener_ajuste(CLAVE_CARPETA)?
+            .filter(|carpeta| !carpeta.is_empty())
+            .map(PathBuf::from))
+    }
+
+    /// Elige la carpeta compartida; una ruta vacía desactiva la sincronización
+    pub fn guardar_carpeta_sincronizacion(&self, carpeta: &Path) -> SqlResult<()> {
+        self.guardar_ajuste(CLAVE_CARPETA, &carpeta.to_string_lossy())
+    }
+
+    /// Envía a la carpeta elegida los cambios locales pendientes y aplica
+    /// los que hayan dejado allí los demás equipos
+    pub fn sincronizar(&self) -> Result<Informe, ErrorSincronizacion> {
+        let carpeta = self
+            .carpeta_sincronizacion()?
+            .ok_or(ErrorSincronizacion::SinCarpeta)?;
+        self.sincronizar_con(&carpeta)
+    }
+
+    /// Como [`Db::sincronizar`], con la carpeta `carpeta`
+    pub fn sincronizar_con(&self, carpeta: &Path) -> Result<Informe, ErrorSincronizacion> {
+        fs::create_dir_all(carpeta)?;
+        let dispositivo = self.activar_sincronizacion()?;
+        let enviados = self.enviar(carpeta, &dispositivo)?;
+        let (recibidos, conflictos) = self.recibir(carpeta, &dispositivo)?;
+        Ok(Informe {
+            enviados,
+            recibidos,
+            conflictos,
+        })
+    }
+
+    /// Identificador de este equipo en la carpeta compartida
+    pub fn dispositivo(&self) -> SqlResult<Option<String>> {
+        self.obtener_ajuste(CLAVE_DISPOSITIVO)
+    }
+
+    // Prepara la base de datos la primera vez: identificador del equipo,
+    // tablas, disparadores y el estado actual de las tareas como cambios
+    // pendientes de enviar. Devuelve el identificador.
+    fn activar_sincronizacion(&self) -> Result<String, ErrorSincronizacion> {
+        self.transaccion_sincronizacion(|db| {
+            if let Some(dispositivo) = db.dispositivo()? {
+                return Ok(dispositivo);
+            }
+            let dispositivo = nuevo_dispositivo()?;
+            db.guardar_ajuste(CLAVE_DISPOSITIVO, &dispositivo)?;
+            db.conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS cambios (
+                    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                    uid TEXT NOT NULL,
+                    campo TEXT NOT NULL,
+                    valor TEXT NOT NULL,
+                    marca TEXT NOT NULL,
+                    base TEXT
+                );
+                CREATE TABLE IF NOT EXISTS sync_campos (
+                    uid TEXT NOT NULL,
+                    campo TEXT NOT NULL,
+                    marca TEXT NOT NULL,
+                    PRIMARY KEY (uid, campo)
+                );
+                -- Con una fila, los disparadores no anotan lo que se recibe
+                CREATE TABLE IF NOT EXISTS sync_aplicando (x INTEGER);
+                CREATE UNIQUE INDEX IF NOT EXISTS tareas_uid ON tareas (uid);",
+            )?;
+            db.anotar_existentes(&dispositivo)?;
+            db.crear_disparadores()?;
+            Ok(dispositivo)
+        })
+    }
+
+    // Da identificador global a las tareas que aún no lo tienen y anota
+    // todas como creadas, con su estado actual. Los ejemplos ya lo tienen,
+    // igual en todos los equipos, y también se anotan: así llegan a los
+    // demás los cambios que se les hicieron antes de sincronizar.
+    fn anotar_existentes(&self, dispositivo: &str) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE tareas SET uid = ?1 || '-' || id WHERE uid IS NULL",
+            [dispositivo],
+        )?;
+        self.conn.execute_batch(&format!(
+            "INSERT INTO cambios (uid, campo, valor, marca)
+             SELECT uid, 'creada', '', {MARCA} FROM tareas
+             UNION ALL SELECT uid, 'descripcion', descripcion, {MARCA} FROM tareas
+             UNION ALL SELECT uid, 'completada', completada, {MARCA}
+                FROM tareas WHERE completada
+             UNION ALL SELECT uid, 'tiempo', tiempo_acumulado, {MARCA}
+                FROM tareas WHERE tiempo_acumulado != 0
+             UNION ALL SELECT uid, 'eliminada', eliminada_en, {MARCA}
+                FROM tareas WHERE eliminada_en IS NOT NULL"
+        ))?;
+        // Un ejemplo sin cambiar, en cualquier idioma, pierde frente a
+        // cualquier edición, y entre dos sin cambiar gana siempre el mismo
+        let marca = format!("{}@{}", ANTES_DE_TODO, dispositivo);
+        for n in 0..Idioma::Es.textos().ejemplos.len() {
+            for idioma in Idioma::TODOS {
+                self.conn.execute(
+                    "UPDATE cambios SET marca = ?1
+                     WHERE uid = ?2 AND campo = 'descripcion' AND valor = ?3",
+                    params![marca, uid_ejemplo(n), idioma.textos().ejemplos[n]],
+                )?;
+            }
+        }
+        self.conn.execute_batch(
+            "INSERT OR REPLACE INTO sync_campos (uid, campo, marca)
+             SELECT uid, campo, marca FROM cambios
+             WHERE campo IN ('descripcion', 'completada', 'eliminada')",
+        )?;
+        Ok(())
+    }
+
+    fn crear_disparadores(&self) -> SqlResult<()> {
+        let recibiendo = "EXISTS (SELECT 1 FROM sync_aplicando)";
+        self.conn.execute_batch(&format!(
+            "CREATE TRIGGER IF NOT EXISTS sync_creada AFTER INSERT ON tareas
+             WHEN NOT {recibiendo}
+             BEGIN
+                UPDATE tareas SET uid = (SELECT valor FROM ajustes
+                    WHERE clave = 'sync_dispositivo') || '-' || NEW.id
+                WHERE id = NEW.id;
+                INSERT INTO cambios (uid, campo, valor, marca)
+                    SELECT uid, 'creada', '', {MARCA} FROM tareas WHERE id = NEW.id
+                    UNION ALL SELECT uid, 'descripcion', descripcion, {MARCA}
+                        FROM tareas WHERE id = NEW.id;
+                INSERT OR REPLACE INTO sync_campos (uid, campo, marca)
+                    SELECT uid, 'descripcion', {MARCA} FROM tareas WHERE id = NEW.id;
+             END;
+             CREATE TRIGGER IF NOT EXISTS sync_tiempo AFTER UPDATE OF tiempo_acumulado ON tareas
+             WHEN OLD.tiempo_acumulado IS NOT NEW.tiempo_acumulado
+                AND NEW.uid IS NOT NULL AND NOT {recibiendo}
+             BEGIN
+                INSERT INTO cambios (uid, campo, valor, marca) VALUES (NEW.uid, 'tiempo',
+                    NEW.tiempo_acumulado - OLD.tiempo_acumulado, {MARCA});
+             END;
+             CREATE TRIGGER IF NOT EXISTS sync_borrada AFTER DELETE ON tareas
+             WHEN OLD.uid IS NOT NULL AND NOT {recibiendo}
+             BEGIN
+                INSERT INTO cambios (uid, campo, valor, marca)
+                VALUES (OLD.uid, 'borrada', '', {MARCA});
+                INSERT OR REPLACE INTO sync_campos (uid, campo, marca)
+                VALUES (OLD.uid, 'borrada', {MARCA});
+             END;"
+        ))?;
+        for (campo, columna, valor) in CAMPOS {
+            self.conn.execute_batch(&format!(
+                "CREATE TRIGGER IF NOT EXISTS sync_{campo} AFTER UPDATE OF {columna} ON tareas
+                 WHEN OLD.{columna} IS NOT NEW.{columna}
+                    AND NEW.uid IS NOT NULL AND NOT {recibiendo}
+                 BEGIN
+                    INSERT INTO cambios (uid, campo, valor, marca, base)
+                    VALUES (NEW.uid, '{campo}', {valor}, {MARCA}, (SELECT marca
+                        FROM sync_campos WHERE uid = NEW.uid AND campo = '{campo}'));
+                    INSERT OR REPLACE INTO sync_campos (uid, campo, marca)
+                    VALUES (NEW.uid, '{campo}', {MARCA});
+                 END;"
+            ))?;
+        }
+        Ok(())
+    }
+
+    // Añade los cambios pendientes al diario de este equipo; devuelve cuántos.
+    //
+    // Se quitan de `cambios` en la misma transacción en que se escriben, así
+    // que otra instancia que comparta la base de datos espera a que termine
+    // y ya no los encuentra. Si algo falla a medias, se vuelven a enviar y
+    // los demás los descartan por su número.
+    fn enviar(&self, carpeta: &Path, dispositivo: &str) -> Result<usize, ErrorSincronizacion> {
+        self.transaccion_sincronizacion(|db| {
+            let pendientes: Vec<Cambio> = db
+                .conn
+                .prepare("DELETE FROM cambios RETURNING seq, marca, base, uid, campo, valor")?
+                .query_map([], |row| {
+                    Ok(Cambio {
+                        seq: row.get(0)?,
+                        marca: row.get(1)?,
+                        base: row.get(2)?,
+                        uid: row.get(3)?,
+                        campo: row.get(4)?,
+                        valor: row.get(5)?,
+                    })
+                })?
+                .collect::<SqlResult<_>>()?;
+            if pendientes.is_empty() {
+                return Ok(0);
+            }
+            let mut pendientes = pendientes;
+            pendientes.sort_by_key(|c| c.seq);
+
+            let mut texto = String::new();
+            for cambio in &pendientes {
+                texto.push_str(&cambio.linea());
+                texto.push('\n');
+            }
+            anadir_al_diario(&diario(carpeta, dispositivo), texto.as_bytes())?;
+            Ok(pendientes.len())
+        })
+    }
+
+    // Como `transaccion_inmediata`, pero con errores de sincronización
+    fn transaccion_sincronizacion<T>(
+        &self,
+        operacion: impl FnOnce(&Db) -> Result<T, ErrorSincronizacion>,
+    ) -> Result<T, ErrorSincronizacion> {
+        let mut error = None;
+        let resultado = self.transaccion_inmediata(|db| {
+            operacion(db).map_err(|e| {
+                error = Some(e);
+                // Solo para deshacer la transacción; se devuelve `error`
+                rusqlite::Error::InvalidQuery
+            })
+        });
+        match (resultado, error) {
+            (_, Some(e)) => Err(e),
+            (resultado, None) => Ok(resultado?),
+        }
+    }
+
+    // Aplica los cambios nuevos de los diarios de los demás equipos. De
+    // cada diario se lee solo lo añadido desde la última vez, y de ello
+    // solo los cambios con número mayor que el último aplicado.
+    //
+    // Todo va en una transacción que reserva la escritura desde el
+    // principio: otra instancia que comparta la base de datos espera y
+    // después ve ya guardado hasta dónde se ha aplicado cada diario.
+    fn recibir(
+        &self,
+        carpeta: &Path,
+        dispositivo: &str,
+    ) -> Result<(usize, Vec<Conflicto>), ErrorSincronizacion> {
+        self.transaccion_sincronizacion(|db| {
+            db.conn
+                .execute("INSERT INTO sync_aplicando VALUES (1)", [])?;
+            let mut recibidos = 0;
+            let mut conflictos = Vec::new();
+            for entrada in fs::read_dir(carpeta)? {
+                let ruta = entrada?.path();
+                let Some(otro) = ruta
+                    .file_stem()
+                    .and_then(|nombre| nombre.to_str())
+                    .filter(|_| ruta.extension().is_some_and(|e| e == EXTENSION))
+                    .filter(|&otro| otro != dispositivo)
+                else {
+                    continue;
+                };
+                let clave_visto = format!("{}{}", PREFIJO_VISTO, otro);
+                let clave_posicion = format!("{}{}", PREFIJO_POSICION, otro);
+                let mut visto: i64 = db
+                    .obtener_ajuste(&clave_visto)?
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0);
+                let posicion: u64 = db
+                    .obtener_ajuste(&clave_posicion)?
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0);
+
+                let (lineas, posicion) = leer_desde(&ruta, posicion)?;
+                for linea in lineas.split(|&c| c == b'\n') {
+                    let Some(cambio) = Cambio::desde_linea(&String::from_utf8_lossy(linea)) else {
+                        continue;
+                    };
+                    // Las repeticiones y lo ya aplicado quedan por debajo de `visto`
+                    if cambio.seq > visto {
+                        visto = cambio.seq;
+                        db.aplicar(&cambio, &mut conflictos)?;
+                        recibidos += 1;
+                    }
+                }
+                db.guardar_ajuste(&clave_visto, &visto.to_string())?;
+                db.guardar_ajuste(&clave_posicion, &posicion.to_string())?;
+            }
+            db.conn.execute("DELETE FROM sync_aplicando", [])?;
+            Ok((recibidos, conflictos))
+        })
+    }
+
+    fn aplicar(&self, cambio: &Cambio, conflictos: &mut Vec<Conflicto>) -> SqlResult<()> {
+        let id: Option<i32> = self
+            .conn
+            .query_row(
+                "SELECT id FROM tareas WHERE uid = ?1",
+                [&cambio.uid],
+                |row| row.get(0),
+            )
+            .optional()?;
+        // Lo borrado para siempre queda anotado para que no vuelva a crearse
+        let borrada = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM sync_campos WHERE uid = ?1 AND campo = 'borrada'",
+                [&cambio.uid],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        match (cambio.campo.as_str(), id) {
+            ("borrada", _) => {
+                if let Some(id) = id {
+                    self.conn
+                        .execute("DELETE FROM tareas WHERE id = ?1", [id])?;
+                }
+                self.conn.execute(
+                    "INSERT OR REPLACE INTO sync_campos (uid, campo, marca) VALUES (?1, ?2, ?3)",
+                    [&cambio.uid, &cambio.campo, &cambio.marca],
+                )?;
+            }
+            ("creada", None) if !borrada => {
+                self.conn.execute(
+                    "INSERT INTO tareas (descripcion, uid) VALUES ('', ?1)",
+                    [&cambio.uid],
+                )?;
+            }
+            ("tiempo", Some(id)) => {
+                self.conn.execute(
+                    "UPDATE tareas SET tiempo_acumulado = tiempo_acumulado + ?1 WHERE id = ?2",
+                    params![cambio.valor.parse::<i32>().unwrap_or(0), id],
+                )?;
+            }
+            ("descripcion" | "completada" | "eliminada", Some(id)) => {
+                self.fusionar(id, cambio, conflictos)?
+            }
+            // Cambios de tareas que aquí no existen, o ya aplicados
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Aplica el cambio de un campo si gana según las reglas de fusión
+    fn fusionar(&self, id: i32, cambio: &Cambio, conflictos: &mut Vec<Conflicto>) -> SqlResult<()> {
+        let local: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT marca FROM sync_campos WHERE uid = ?1 AND campo = ?2",
+                [&cambio.uid, &cambio.campo],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let actual: String = self.conn.query_row(
+            "SELECT CASE ?2 WHEN 'descripcion' THEN descripcion
+                WHEN 'completada' THEN CAST(completada AS TEXT)
+                ELSE COALESCE(eliminada_en, '') END
+             FROM tareas WHERE id = ?1",
+            params![id, cambio.campo],
+            |row| row.get(0),
+        )?;
+
+        let Some(local) = local.filter(|local| Some(local) != cambio.base.as_ref()) else {
+            // Parte de lo que hay aquí: se aplica sin más
+            return self.escribir(id, cambio);
+        };
+        // Con el mismo valor no hay nada que fusionar. Se conserva la marca
+        // local para que lo que llegue después basado en la otra no se tome
+        // por una continuación de lo que hay aquí.
+        if cambio.valor == actual {
+            return Ok(());
+        }
+        let gana = match cambio.campo.as_str() {
+            "completada" => cambio.valor == "1",
+            _ => cambio.marca > local,
+        };
+        // Un ejemplo que nadie ha cambiado no choca con nada
+        let cambiadas = ![local.as_str(), cambio.marca.as_str()]
+            .iter()
+            .any(|marca| marca.starts_with(ANTES_DE_TODO));
+        if cambio.campo == "descripcion" && cambiadas {
+            let (conservada, descartada) = if gana {
+                (&cambio.valor, &actual)
+            } else {
+                (&actual, &cambio.valor)
+            };
+            conflictos.push(Conflicto {
+                id,
+                conservada: conservada.clone(),
+                descartada: descartada.clone(),
+            });
+        }
+        if gana {
+            self.escribir(id, cambio)?;
+        }
+        Ok(())
+    }
+
+    fn escribir(&self, id: i32, cambio: &Cambio) -> SqlResult<()> {
+        let sql = match cambio.campo.as_str() {
+            "descripcion" => {
+                "UPDATE tareas SET descripcion = ?1, version = version + 1 WHERE id = ?2"
+            }
+            "completada" => {
+                "UPDATE tareas SET completada = (?1 = '1'),
+                    completada_en = CASE WHEN ?1 = '1' THEN datetime('now', 'localtime') END
+                 WHERE id = ?2"
+            }
+            _ => "UPDATE tareas SET eliminada_en = NULLIF(?1, '') WHERE id = ?2",
+        };
+        self.conn.execute(sql, params![cambio.valor, id])?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO sync_campos (uid, campo, marca) VALUES (?1, ?2, ?3)",
+            [&cambio.uid, &cambio.campo, &cambio.marca],
+        )?;
+        Ok(())
+    }
+}
+
+/// Identificador global de la tarea de ejemplo número `n`
+pub(crate) fn uid_ejemplo(n: usize) -> String {
+    format!("ejemplo-{}", n + 1)
+}
+
+fn diario(carpeta: &Path, dispositivo: &str) -> PathBuf {
+    carpeta.join(format!("{}.{}", dispositivo, EXTENSION))
+}
+
+// Añade `lineas` al diario escribiendo una copia completa y poniéndola en
+// su lugar, para que quien lo lea nunca vea una escritura a medias
+fn anadir_al_diario(ruta: &Path, lineas: &[u8]) -> io::Result<()> {
+    let mut contenido = match fs::read(ruta) {
+        Ok(contenido) => contenido,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    // Si una copia anterior quedó cortada, la línea nueva empieza aparte
+    if contenido.last().is_some_and(|&c| c != b'\n') {
+        contenido.push(b'\n');
+    }
+    contenido.extend_from_slice(lineas);
+
+    let mut nombre = ruta.as_os_str().to_owned();
+    nombre.push(".tmp");
+    let temporal = PathBuf::from(nombre);
+    let mut fichero = fs::File::create(&temporal)?;
+    fichero.write_all(&contenido)?;
+    fichero.sync_all()?;
+    fs::rename(&temporal, ruta)
+}
+
+// Las líneas completas que haya en `ruta` desde el byte `posicion`, y el
+// byte en que acaban. Una última línea sin terminar aún se está copiando a
+// esta carpeta y se deja para la próxima vez. Si el fichero es más corto
+// que `posicion`, se ha sustituido por otro y se lee desde el principio.
+fn leer_desde(ruta: &Path, posicion: u64) -> io::Result<(Vec<u8>, u64)> {
+    let mut fichero = fs::File::open(ruta)?;
+    let posicion = if fichero.metadata()?.len() < posicion {
+        0
+    } else {
+        posicion
+    };
+    fichero.seek(SeekFrom::Start(posicion))?;
+    let mut bytes = Vec::new();
+    fichero.read_to_end(&mut bytes)?;
+    let completas = bytes
+        .iter()
+        .rposition(|&c| c == b'\n')
+        .map_or(0, |fin| fin + 1);
+    bytes.truncate(completas);
+    Ok((bytes, posicion + completas as u64))
+}
+
+// Identificador aleatorio para un equipo nuevo, con 64 bits al azar del
+// sistema operativo
+fn nuevo_dispositivo() -> io::Result<String> {
+    let mut bytes = [0u8; 8];
+    getrandom::fill(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
@@ -0,0 +1,89 @@
+//! Escrituras de varios pasos que se aplican enteras o no se aplican.
+//!
+//! [`Db::transaccion`] usa puntos de guardado de SQLite, así que puede
+//! anidarse: una operación compuesta puede llamar a otras que ya abren su
+//! propia transacción, y si la de fuera falla se deshacen todas. Los
+//! ganchos disparados dentro se ejecutan al confirmar la de fuera.
+
+use rusqlite::Result as SqlResult;
+
+use crate::Db;
+
+impl Db {
+    /// Ejecuta `operacion` en una transacción. Si devuelve un error, o si
+    /// entra en pánico, se deshace todo lo que haya escrito.
+    pub fn transaccion<T>(&self, operacion: impl FnOnce(&Db) -> SqlResult<T>) -> SqlResult<T> {
+        self.transaccion_con(false, operacion)
+    }
+
+    /// Como [`Db::transaccion`], pero si no está dentro de otra reserva la
+    /// escritura desde el principio, así que ninguna otra instancia puede
+    /// escribir entre lo que lee y lo que escribe `operacion`
+    pub(crate) fn transaccion_inmediata<T>(
+        &self,
+        operacion: impl FnOnce(&Db) -> SqlResult<T>,
+    ) -> SqlResult<T> {
+        self.transaccion_con(true, operacion)
+    }
+
+    fn transaccion_con<T>(
+        &self,
+        inmediata: bool,
+        operacion: impl FnOnce(&Db) -> SqlResult<T>,
+    ) -> SqlResult<T> {
+        // Dentro de otra transacción el bloqueo ya es el de la de fuera
+        let inmediata = inmediata && self.conn.is_autocommit();
+        self.conn.execute_batch(if inmediata {
+            "BEGIN IMMEDIATE"
+        } else {
+            "SAVEPOINT transaccion"
+        })?;
+        let mut pendiente = Pendiente {
+            db: self,
+            marca: self.ganchos.entrar(),
+            inmediata,
+            abierta: true,
+        };
+        let resultado = operacion(self)?;
+        self.conn.execute_batch(if inmediata {
+            "COMMIT"
+        } else {
+            "RELEASE transaccion"
+        })?;
+        pendiente.abierta = false;
+        Ok(resultado)
+    }
+
+    /// Guarda una sesión del temporizador: suma su tiempo a la tarea y la
+    /// anota para las estadísticas. Devuelve el nuevo total de la tarea.
+    pub fn guardar_sesion(&self, id: i32, segundos: i32) -> SqlResult<i32> {
+        self.transaccion(|db| {
+            let total = db.sumar_tiempo(id, segundos)?;
+            db.registrar_sesion(id, segundos)?;
+            Ok(total)
+        })
+    }
+}
+
+// Deshace el punto de guardado si no se llega a confirmar
+struct Pendiente<'a> {
+    db: &'a Db,
+    /// Ganchos ya pendientes al abrirla; ver [`crate::ganchos`]
+    marca: usize,
+    /// Empezó con `BEGIN IMMEDIATE` en lugar de un punto de guardado
+    inmediata: bool,
+    abierta: bool,
+}
+
+impl Drop for Pendiente<'_> {
+    fn drop(&mut self) {
+        if self.abierta {
+            let _ = self.db.conn.execute_batch(if self.inmediata {
+                "ROLLBACK"
+            } else {
+                "ROLLBACK TO transaccion; RELEASE transaccion"
+            });
+        }
+        self.db.ganchos.salir(self.marca, !self.abierta);
+    }
+}
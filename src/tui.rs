@@ -0,0 +1,396 @@
+//! Interfaz de terminal (`pixi tui`) para cuando no se puede abrir una
+//! ventana, por ejemplo por SSH. Usa el mismo [`ServicioTareas`] que la
+//! interfaz gráfica, así que las reglas y los temporizadores son los mismos.
+
+use std::io;
+use std::time::Duration;
+
+use pixi::i18n::rellenar;
+use pixi::{Db, Edicion, Filtro, ResultadoEdicion, ServicioTareas, Textos, formatear_duracion};
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Position};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+
+/// Cada cuánto se redibuja y se buscan cambios hechos por otra instancia
+const TICK: Duration = Duration::from_secs(1);
+
+enum Mode {
+    Normal,
+    /// Escribiendo una tarea nueva
+    Adding(String),
+    /// Cambiando la descripción de la tarea `id`, partiendo de `Edicion`
+    Editing(i32, Edicion),
+    ConfirmDelete(i32),
+}
+
+struct TuiApp {
+    servicio: ServicioTareas,
+    textos: &'static Textos,
+    /// Tarea seleccionada, por id como en la interfaz gráfica
+    selected_id: Option<i32>,
+    mode: Mode,
+    confirmar_borrado: bool,
+    color_temporizador: Color,
+    status: Option<String>,
+    quit: bool,
+}
+
+/// Abre la interfaz de terminal sobre `db` hasta que el usuario sale.
+/// Los temporizadores en marcha se pausan y guardan al salir.
+pub fn run(db: Db) -> io::Result<()> {
+    let ajustes = db.cargar_ajustes().unwrap_or_default();
+    let [r, g, b] = ajustes.color_temporizador;
+    let mut app = TuiApp {
+        textos: db.idioma().textos(),
+        servicio: ServicioTareas::new(db),
+        selected_id: None,
+        mode: Mode::Normal,
+        confirmar_borrado: ajustes.confirmar_borrado,
+        color_temporizador: Color::Rgb(r, g, b),
+        status: None,
+        quit: false,
+    };
+    app.selected_id = app.servicio.tareas().first().map(|t| t.id);
+
+    app.backup();
+    app.sync();
+    let mut terminal = ratatui::init();
+    let resultado = app.run(&mut terminal);
+    ratatui::restore();
+    app.servicio.pausar_todos();
+    app.sync();
+    app.backup();
+    if let Some(status) = app.status {
+        eprintln!("{}", status);
+    }
+    resultado
+}
+
+impl TuiApp {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        while !self.quit {
+            // Mientras se edita se espera: si la otra instancia también editó
+            // la tarea, lo detecta `editar`
+            if !matches!(self.mode, Mode::Editing(..)) && self.servicio.hay_cambios_externos() {
+                self.reload();
+            }
+            let errores = self.servicio.db().errores_ganchos();
+            if !errores.is_empty() {
+                let mensajes: Vec<String> =
+                    errores.iter().map(|e| e.describir(self.textos)).collect();
+                self.status = Some(mensajes.join("\n"));
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(TICK)? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                self.handle_key(key);
+            }
+        }
+        Ok(())
+    }
+
+    // Copia de seguridad automática, al abrir y al salir
+    fn backup(&mut self) {
+        if let Err(e) = self.servicio.db().copia_automatica() {
+            self.status = Some(rellenar(self.textos.error_copia, &[&e]));
+        }
+    }
+
+    // Sincroniza con la carpeta compartida, si hay una, al abrir y al salir.
+    // Solo se informa de los conflictos y los errores.
+    fn sync(&mut self) {
+        let t = self.textos;
+        match self.servicio.sincronizar() {
+            Ok(Some(informe)) if !informe.conflictos.is_empty() => {
+                let conflictos: Vec<String> = informe
+                    .conflictos
+                    .iter()
+                    .map(|c| rellenar(t.conflicto_sync, &[&c.id, &c.conservada, &c.descartada]))
+                    .collect();
+                self.status = Some(conflictos.join("\n"));
+            }
+            Ok(_) => {}
+            Err(e) => self.status = Some(rellenar(t.error_sync, &[&e])),
+        }
+    }
+
+    // Vuelve a leer las tareas; si la seleccionada ya no está, pasa a la primera
+    fn reload(&mut self) {
+        self.servicio.recargar();
+        if self
+            .selected_id
+            .is_none_or(|id| self.servicio.tarea(id).is_none())
+        {
+            self.selected_id = self.visible_ids().first().copied();
+        }
+    }
+
+    fn visible_ids(&mut self) -> Vec<i32> {
+        self.servicio
+            .visibles(&Filtro::default())
+            .into_iter()
+            .map(|idx| self.servicio.tareas()[idx].id)
+            .collect()
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+        match std::mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Normal => self.handle_normal(key.code),
+            Mode::Adding(texto) => {
+                self.mode = match edit_line(texto, key.code) {
+                    Input::Typing(texto) => Mode::Adding(texto),
+                    Input::Done(texto) => {
+                        self.add_task(&texto);
+                        Mode::Normal
+                    }
+                    Input::Cancelled => Mode::Normal,
+                }
+            }
+            Mode::Editing(id, mut edicion) => {
+                self.mode = match edit_line(edicion.descripcion, key.code) {
+                    Input::Typing(texto) => {
+                        edicion.descripcion = texto;
+                        Mode::Editing(id, edicion)
+                    }
+                    Input::Done(texto) => {
+                        edicion.descripcion = texto;
+                        self.save_edit(id, &edicion);
+                        Mode::Normal
+                    }
+                    Input::Cancelled => Mode::Normal,
+                }
+            }
+            Mode::ConfirmDelete(id) => match key.code {
+                KeyCode::Enter | KeyCode::Char('y' | 's') => self.delete_task(id),
+                KeyCode::Esc | KeyCode::Char('n') => {}
+                _ => self.mode = Mode::ConfirmDelete(id),
+            },
+        }
+    }
+
+    fn handle_normal(&mut self, code: KeyCode) {
+        self.status = None;
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Home | KeyCode::Char('g') => self.move_selection(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => {
+                self.servicio.cargar_todo();
+                self.move_selection(isize::MAX);
+            }
+            KeyCode::Char('a') => self.mode = Mode::Adding(String::new()),
+            _ => {
+                let Some(todo) = self.selected_id.and_then(|id| self.servicio.tarea(id)) else {
+                    return;
+                };
+                let (id, checked, edicion) = (todo.id, todo.checked, Edicion::desde(todo));
+                match code {
+                    KeyCode::Char(' ' | 'x') => {
+                        let resultado = self.servicio.marcar(id, !checked);
+                        self.report(resultado);
+                    }
+                    KeyCode::Char('e') | KeyCode::Enter => self.mode = Mode::Editing(id, edicion),
+                    KeyCode::Char('d') | KeyCode::Delete if self.confirmar_borrado => {
+                        self.mode = Mode::ConfirmDelete(id)
+                    }
+                    KeyCode::Char('d') | KeyCode::Delete => self.delete_task(id),
+                    KeyCode::Char('t') => {
+                        self.servicio.alternar_temporizador(id);
+                    }
+                    KeyCode::Char('r') => self.servicio.resetear_temporizador(id),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Desplaza la selección `delta` filas; se satura en los extremos
+    fn move_selection(&mut self, delta: isize) {
+        let visibles = self.visible_ids();
+        if visibles.is_empty() {
+            self.selected_id = None;
+            return;
+        }
+        let pos = self
+            .selected_id
+            .and_then(|id| visibles.iter().position(|&v| v == id))
+            .unwrap_or(0);
+        let nueva = pos.saturating_add_signed(delta).min(visibles.len() - 1);
+        self.selected_id = Some(visibles[nueva]);
+        // Al llegar al final se lee la siguiente página
+        if nueva + 1 == visibles.len() {
+            self.servicio.cargar_mas();
+        }
+    }
+
+    fn add_task(&mut self, descripcion: &str) {
+        match self.servicio.agregar(descripcion) {
+            Ok(Some(id)) if self.servicio.tarea(id).is_some() => self.selected_id = Some(id),
+            resultado => self.report(resultado),
+        }
+    }
+
+    fn save_edit(&mut self, id: i32, edicion: &Edicion) {
+        match self.servicio.editar(id, edicion) {
+            Ok(ResultadoEdicion::Conflicto) => {
+                self.status = Some(self.textos.conflicto_edicion.to_string())
+            }
+            resultado => self.report(resultado),
+        }
+    }
+
+    fn delete_task(&mut self, id: i32) {
+        let vecina = self.servicio.tareas().vecina(id);
+        match self.servicio.eliminar(id) {
+            Ok(()) => self.selected_id = vecina,
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+
+    // Muestra en la barra de estado el error de una operación, si lo hubo
+    fn report<T>(&mut self, resultado: rusqlite::Result<T>) {
+        if let Err(e) = resultado {
+            self.status = Some(e.to_string());
+        }
+    }
+
+    fn draw(&mut self, frame: &mut ratatui::Frame) {
+        let t = self.textos;
+        let [cabecera, lista, entrada, pie] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(2),
+        ])
+        .areas(frame.area());
+
+        frame.render_widget(
+            Paragraph::new(t.titulo).style(Style::new().add_modifier(Modifier::BOLD)),
+            cabecera,
+        );
+
+        let visibles = self.visible_ids();
+        let seleccion = self
+            .selected_id
+            .and_then(|id| visibles.iter().position(|&v| v == id));
+        let filas: Vec<ListItem> = visibles
+            .iter()
+            .filter_map(|&id| self.servicio.tarea(id))
+            .map(|todo| {
+                let casilla = if todo.checked { "[x] " } else { "[ ] " };
+                let mut texto = Span::raw(todo.text.as_str());
+                if todo.checked {
+                    texto = texto.add_modifier(Modifier::CROSSED_OUT | Modifier::DIM);
+                }
+                let mut tiempo =
+                    Span::raw(format!("  ⏱ {}", formatear_duracion(todo.tiempo_total())));
+                if todo.temporizador_activo() {
+                    tiempo = tiempo.style(Style::new().fg(self.color_temporizador));
+                } else if todo.excede_estimacion() {
+                    tiempo = tiempo.style(Style::new().fg(Color::Red));
+                }
+                ListItem::new(Line::from(vec![Span::raw(casilla), texto, tiempo]))
+            })
+            .collect();
+        let mut estado = ListState::default().with_selected(seleccion);
+        frame.render_stateful_widget(
+            List::new(filas)
+                .block(Block::bordered())
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            lista,
+            &mut estado,
+        );
+
+        match &self.mode {
+            Mode::Adding(texto) => self.draw_input(frame, entrada, t.nueva_tarea, texto),
+            Mode::Editing(_, edicion) => {
+                self.draw_input(frame, entrada, t.editar_tarea, &edicion.descripcion)
+            }
+            Mode::ConfirmDelete(id) => {
+                let nombre = self
+                    .servicio
+                    .tarea(*id)
+                    .map_or("", |todo| todo.text.as_str());
+                let pregunta = format!(
+                    "{}  {}",
+                    rellenar(t.confirmar_eliminar, &[&nombre]),
+                    t.tui_confirmar
+                );
+                frame.render_widget(Paragraph::new(pregunta), entrada);
+            }
+            Mode::Normal => {
+                if let Some(status) = &self.status {
+                    frame.render_widget(
+                        Paragraph::new(status.as_str()).style(Style::new().fg(Color::Red)),
+                        entrada,
+                    );
+                }
+            }
+        }
+
+        let totales = self
+            .servicio
+            .db()
+            .totales()
+            .unwrap_or_default()
+            .con_sesiones(self.servicio.tareas());
+        let resumen = [
+            rellenar(t.total, &[&totales.tareas]),
+            rellenar(t.completadas, &[&totales.completadas]),
+            rellenar(t.tiempo_total, &[&formatear_duracion(totales.segundos)]),
+        ]
+        .join("  ");
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::raw(resumen),
+                Line::styled(t.tui_ayuda, Style::new().add_modifier(Modifier::DIM)),
+            ]),
+            pie,
+        );
+    }
+
+    fn draw_input(
+        &self,
+        frame: &mut ratatui::Frame,
+        area: ratatui::layout::Rect,
+        etiqueta: &str,
+        texto: &str,
+    ) {
+        let linea = format!("{} {}", etiqueta, texto);
+        let ancho = linea.chars().count() as u16;
+        frame.render_widget(Paragraph::new(linea), area);
+        frame.set_cursor_position(Position::new(area.x + ancho.min(area.width), area.y));
+    }
+}
+
+enum Input {
+    Typing(String),
+    Done(String),
+    Cancelled,
+}
+
+// Aplica una tecla a una línea de texto en edición
+fn edit_line(mut texto: String, code: KeyCode) -> Input {
+    match code {
+        KeyCode::Enter => return Input::Done(texto),
+        KeyCode::Esc => return Input::Cancelled,
+        KeyCode::Backspace => {
+            texto.pop();
+        }
+        KeyCode::Char(c) => texto.push(c),
+        _ => {}
+    }
+    Input::Typing(texto)
+}
@@ -0,0 +1,81 @@
+//! Pantalla que pide la clave de una base de datos cifrada antes de abrir
+//! la aplicación (característica `cifrado`).
+
+use pixi::cifrado::clave_incorrecta;
+use pixi::i18n::rellenar;
+use pixi::{Db, Idioma, Textos};
+
+use crate::MyApp;
+
+pub struct Unlock {
+    ruta_db: String,
+    clave: String,
+    error: Option<String>,
+    textos: &'static Textos,
+    /// La aplicación, una vez abierta la base de datos
+    app: Option<MyApp>,
+}
+
+impl Unlock {
+    pub fn new(ruta_db: String) -> Self {
+        Self {
+            ruta_db,
+            clave: String::new(),
+            error: None,
+            // Hasta abrirla no se sabe el idioma guardado en la base de datos
+            textos: Idioma::detectar().textos(),
+            app: None,
+        }
+    }
+
+    fn try_unlock(&mut self, ctx: &egui::Context) {
+        let clave = std::mem::take(&mut self.clave);
+        match Db::abrir_cifrada(&self.ruta_db, &clave) {
+            Ok(db) => {
+                let app = MyApp::new(db, self.ruta_db.clone(), ctx);
+                app.apply_settings(ctx);
+                ctx.send_viewport_cmd(egui::ViewportCommand::Title(app.textos.titulo.to_string()));
+                self.app = Some(app);
+            }
+            Err(e) if clave_incorrecta(&e) => {
+                self.error = Some(self.textos.clave_incorrecta.to_string())
+            }
+            Err(e) => self.error = Some(rellenar(self.textos.error_abrir_db, &[&self.ruta_db, &e])),
+        }
+    }
+}
+
+impl eframe::App for Unlock {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if let Some(app) = &mut self.app {
+            app.update(ctx, frame);
+            return;
+        }
+
+        let t = self.textos;
+        let mut unlock = false;
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(ui.available_height() / 3.0);
+                ui.heading(rellenar(t.base_cifrada, &[&self.ruta_db]));
+                ui.label(t.pedir_clave);
+                let campo = ui.add(egui::TextEdit::singleline(&mut self.clave).password(true));
+                campo.request_focus();
+                let enter = campo.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                unlock = ui.button(t.desbloquear).clicked() || enter;
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            });
+        });
+        if unlock {
+            self.try_unlock(ctx);
+        }
+    }
+
+    fn on_exit(&mut self) {
+        if let Some(app) = &mut self.app {
+            app.on_exit();
+        }
+    }
+}
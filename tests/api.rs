@@ -0,0 +1,285 @@
+mod common;
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+
+use common::{borrar, ruta_db};
+use pixi::Db;
+use pixi::api::ServidorApi;
+use serde_json::{Value, json};
+
+const TOKEN: &str = "secreto";
+
+// Arranca el servidor en un puerto libre en su propio hilo
+fn servidor(db: Db) -> SocketAddr {
+    let mut servidor = ServidorApi::new(db, "127.0.0.1:0", TOKEN).unwrap();
+    let direccion = servidor.direccion().unwrap();
+    std::thread::spawn(move || servidor.atender());
+    direccion
+}
+
+// Hace una petición y devuelve el estado y el cuerpo (`Null` si no hay)
+fn peticion(
+    direccion: SocketAddr,
+    metodo: &str,
+    ruta: &str,
+    token: Option<&str>,
+    cuerpo: Option<Value>,
+) -> (u16, Value) {
+    let cuerpo = cuerpo.map(|c| c.to_string()).unwrap_or_default();
+    let mut cabeceras = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        metodo,
+        ruta,
+        direccion,
+        cuerpo.len()
+    );
+    if let Some(token) = token {
+        cabeceras.push_str(&format!("Authorization: Bearer {}\r\n", token));
+    }
+    let mut conexion = TcpStream::connect(direccion).unwrap();
+    write!(conexion, "{}\r\n{}", cabeceras, cuerpo).unwrap();
+    let mut respuesta = String::new();
+    conexion.read_to_string(&mut respuesta).unwrap();
+
+    let (cabecera, cuerpo) = respuesta.split_once("\r\n\r\n").unwrap();
+    let estado = cabecera.split(' ').nth(1).unwrap().parse().unwrap();
+    let cuerpo = if cuerpo.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_str(cuerpo).unwrap()
+    };
+    (estado, cuerpo)
+}
+
+fn con_token(
+    direccion: SocketAddr,
+    metodo: &str,
+    ruta: &str,
+    cuerpo: Option<Value>,
+) -> (u16, Value) {
+    peticion(direccion, metodo, ruta, Some(TOKEN), cuerpo)
+}
+
+#[test]
+fn sin_el_token_no_se_atiende_nada() {
+    let direccion = servidor(Db::new(":memory:").unwrap());
+
+    for token in [None, Some("otro")] {
+        let (estado, cuerpo) = peticion(direccion, "GET", "/tareas", token, None);
+        assert_eq!(estado, 401);
+        assert!(cuerpo["error"].is_string());
+    }
+    let (estado, _) = peticion(
+        direccion,
+        "POST",
+        "/tareas",
+        None,
+        Some(json!({ "descripcion": "intrusa" })),
+    );
+    assert_eq!(estado, 401);
+    let (_, tareas) = con_token(direccion, "GET", "/tareas", None);
+    assert!(
+        tareas
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|t| t["descripcion"] != "intrusa")
+    );
+}
+
+#[test]
+fn crear_modificar_y_borrar_una_tarea() {
+    let direccion = servidor(Db::new(":memory:").unwrap());
+    let (_, antes) = con_token(direccion, "GET", "/tareas", None);
+
+    let (estado, tarea) = con_token(
+        direccion,
+        "POST",
+        "/tareas",
+        Some(json!({ "descripcion": "  desde la API " })),
+    );
+    assert_eq!(estado, 201);
+    assert_eq!(tarea["descripcion"], "desde la API");
+    assert_eq!(tarea["completada"], false);
+    let ruta = format!("/tareas/{}", tarea["id"]);
+
+    let (_, tareas) = con_token(direccion, "GET", "/tareas", None);
+    assert_eq!(
+        tareas.as_array().unwrap().len(),
+        antes.as_array().unwrap().len() + 1
+    );
+
+    let (estado, tarea) = con_token(
+        direccion,
+        "PATCH",
+        &ruta,
+        Some(
+            json!({ "descripcion": "cambiada", "prioridad": 3, "estimacion": 600, "completada": true }),
+        ),
+    );
+    assert_eq!(estado, 200);
+    assert_eq!(tarea["descripcion"], "cambiada");
+    assert_eq!(tarea["prioridad"], 3);
+    assert_eq!(tarea["estimacion"], 600);
+    assert_eq!(tarea["completada"], true);
+    assert_eq!(con_token(direccion, "GET", &ruta, None).1, tarea);
+
+    let (estado, cuerpo) = con_token(direccion, "DELETE", &ruta, None);
+    assert_eq!((estado, cuerpo), (204, Value::Null));
+    assert_eq!(con_token(direccion, "GET", &ruta, None).0, 404);
+    assert_eq!(con_token(direccion, "DELETE", &ruta, None).0, 404);
+}
+
+#[test]
+fn las_peticiones_mal_formadas_dan_400() {
+    let direccion = servidor(Db::new(":memory:").unwrap());
+    let (_, tarea) = con_token(
+        direccion,
+        "POST",
+        "/tareas",
+        Some(json!({ "descripcion": "x" })),
+    );
+    let ruta = format!("/tareas/{}", tarea["id"]);
+
+    for cuerpo in [
+        json!({ "descripcion": "   " }),
+        json!({}),
+        json!(["no", "es", "un", "objeto"]),
+    ] {
+        assert_eq!(con_token(direccion, "POST", "/tareas", Some(cuerpo)).0, 400);
+    }
+    for cuerpo in [
+        json!({ "prioridad": 9 }),
+        json!({ "completada": "sí" }),
+        json!({ "estimacion": -1 }),
+        json!({ "color": "rojo" }),
+        json!({ "descripcion": "" }),
+    ] {
+        assert_eq!(con_token(direccion, "PATCH", &ruta, Some(cuerpo)).0, 400);
+    }
+    // Nada de lo anterior ha cambiado la tarea
+    assert_eq!(con_token(direccion, "GET", &ruta, None).1, tarea);
+
+    assert_eq!(con_token(direccion, "GET", "/tareas/abc", None).0, 404);
+    assert_eq!(con_token(direccion, "GET", "/otra", None).0, 404);
+}
+
+#[test]
+fn iniciar_y_parar_el_temporizador() {
+    let direccion = servidor(Db::new(":memory:").unwrap());
+    let (_, tarea) = con_token(
+        direccion,
+        "POST",
+        "/tareas",
+        Some(json!({ "descripcion": "x" })),
+    );
+    let ruta = format!("/tareas/{}/temporizador", tarea["id"]);
+
+    // Pedirlo dos veces no lo para
+    for _ in 0..2 {
+        let (estado, tarea) = con_token(direccion, "POST", &ruta, None);
+        assert_eq!(estado, 200);
+        assert_eq!(tarea["temporizador_activo"], true);
+    }
+    for _ in 0..2 {
+        let (estado, tarea) = con_token(direccion, "DELETE", &ruta, None);
+        assert_eq!(estado, 200);
+        assert_eq!(tarea["temporizador_activo"], false);
+    }
+    assert_eq!(
+        con_token(direccion, "POST", "/tareas/999999/temporizador", None).0,
+        404
+    );
+}
+
+#[test]
+fn estadisticas() {
+    let direccion = servidor(Db::new(":memory:").unwrap());
+    let (_, tareas) = con_token(direccion, "GET", "/tareas", None);
+    let id = tareas[0]["id"].clone();
+    con_token(
+        direccion,
+        "PATCH",
+        &format!("/tareas/{}", id),
+        Some(json!({ "completada": true })),
+    );
+
+    let (estado, estadisticas) = con_token(direccion, "GET", "/estadisticas", None);
+    assert_eq!(estado, 200);
+    assert_eq!(estadisticas["tareas"], tareas.as_array().unwrap().len());
+    assert!(estadisticas["completadas"].as_i64().unwrap() >= 1);
+    assert!(estadisticas["hoy"]["completadas"].as_i64().unwrap() >= 1);
+    assert!(estadisticas["meta_diaria"]["racha"].is_u64());
+    assert!(
+        estadisticas["semana"]["completadas"].as_i64().unwrap()
+            >= estadisticas["hoy"]["completadas"].as_i64().unwrap()
+    );
+    assert_eq!(estadisticas["meta_semanal"]["cumplida"], false);
+}
+
+#[test]
+fn ve_los_cambios_de_otras_instancias() {
+    let ruta = ruta_db("api-instancias");
+    let texto = ruta.to_str().unwrap();
+
+    let direccion = servidor(Db::new(texto).unwrap());
+    let otra = Db::new(texto).unwrap();
+    let id = otra.agregar_tarea("desde la ventana").unwrap();
+
+    let (estado, tarea) = con_token(direccion, "GET", &format!("/tareas/{}", id), None);
+    assert_eq!(estado, 200);
+    assert_eq!(tarea["descripcion"], "desde la ventana");
+
+    // Y lo que se cambia por la API queda guardado para las demás
+    con_token(
+        direccion,
+        "PATCH",
+        &format!("/tareas/{}", id),
+        Some(json!({ "notas": "desde la API" })),
+    );
+    assert_eq!(otra.cargar_tarea(id).unwrap().notas, "desde la API");
+
+    drop(otra);
+    borrar(ruta);
+}
+
+#[test]
+fn el_token_nuevo_es_aleatorio_y_se_guarda() {
+    let db = Db::new(":memory:").unwrap();
+    assert_eq!(db.token_api().unwrap(), None);
+
+    let token = db.crear_token_api().unwrap();
+    assert_eq!(token.len(), 32);
+    assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(db.token_api().unwrap(), Some(token.clone()));
+    assert_ne!(db.crear_token_api().unwrap(), token);
+}
+
+#[test]
+fn al_parar_el_servidor_se_guardan_los_temporizadores() {
+    let ruta = ruta_db("api-parar");
+    let texto = ruta.to_str().unwrap();
+
+    let db = Db::new(texto).unwrap();
+    let id = db.agregar_tarea("cronometrada").unwrap();
+    let mut servidor = ServidorApi::new(db, "127.0.0.1:0", TOKEN).unwrap();
+    let direccion = servidor.direccion().unwrap();
+    let cliente = std::thread::spawn(move || {
+        con_token(
+            direccion,
+            "POST",
+            &format!("/tareas/{}/temporizador", id),
+            None,
+        )
+    });
+    assert!(servidor.atender_siguiente());
+    assert_eq!(cliente.join().unwrap().0, 200);
+
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    drop(servidor);
+    let tarea = Db::new(texto).unwrap().cargar_tarea(id).unwrap();
+    assert!(tarea.tiempo_total() >= 1);
+
+    borrar(ruta);
+}
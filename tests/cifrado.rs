@@ -0,0 +1,145 @@
+#![cfg(feature = "cifrado")]
+
+mod common;
+
+use std::path::Path;
+
+use common::{borrar, ruta_db};
+use pixi::Db;
+use pixi::cifrado::{cifrar, clave_incorrecta, descifrar, esta_cifrada};
+use pixi::copias::{ErrorCopia, directorio_copias, listar_copias};
+
+const CLAVE: &str = "clave de prueba";
+
+// Si el texto aparece tal cual en algún fichero de la carpeta
+fn en_claro(ruta: &Path, texto: &str) -> bool {
+    let carpeta = ruta.parent().unwrap();
+    [carpeta.to_path_buf(), directorio_copias(ruta)]
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entrada| std::fs::read(entrada.unwrap().path()).ok())
+        .any(|bytes| bytes.windows(texto.len()).any(|w| w == texto.as_bytes()))
+}
+
+#[test]
+fn solo_se_abre_con_la_clave() {
+    let ruta = ruta_db("clave");
+    let texto = ruta.to_str().unwrap();
+    let db = Db::abrir_cifrada(texto, CLAVE).unwrap();
+    db.agregar_tarea("cliente secreto").unwrap();
+    drop(db);
+
+    assert!(esta_cifrada(&ruta));
+    assert!(!en_claro(&ruta, "cliente secreto"));
+
+    let error = Db::abrir_cifrada(texto, "otra").err().unwrap();
+    assert!(clave_incorrecta(&error));
+    assert!(Db::new(texto).is_err());
+
+    let db = Db::abrir_cifrada(texto, CLAVE).unwrap();
+    assert!(
+        db.cargar_tareas()
+            .unwrap()
+            .iter()
+            .any(|t| t.text == "cliente secreto")
+    );
+    drop(db);
+    borrar(ruta);
+}
+
+#[test]
+fn cifrar_y_descifrar_una_base_existente_y_sus_copias() {
+    let ruta = ruta_db("cifrar");
+    let texto = ruta.to_str().unwrap();
+    let db = Db::new(texto).unwrap();
+    db.agregar_tarea("cliente secreto").unwrap();
+    db.copia_automatica().unwrap();
+    drop(db);
+    assert!(en_claro(&ruta, "cliente secreto"));
+
+    cifrar(&ruta, CLAVE).unwrap();
+    assert!(esta_cifrada(&ruta));
+    assert!(!en_claro(&ruta, "cliente secreto"));
+    let copias = listar_copias(&directorio_copias(&ruta)).unwrap();
+    assert!(copias.iter().all(|copia| esta_cifrada(copia)));
+    let db = Db::abrir_cifrada(texto, CLAVE).unwrap();
+    assert_eq!(
+        db.cargar_tareas().unwrap().last().unwrap().text,
+        "cliente secreto"
+    );
+    drop(db);
+
+    // Con otra clave no se toca nada
+    assert!(descifrar(&ruta, "otra").is_err());
+    assert!(esta_cifrada(&ruta));
+
+    descifrar(&ruta, CLAVE).unwrap();
+    assert!(!esta_cifrada(&ruta));
+    let db = Db::new(texto).unwrap();
+    assert_eq!(
+        db.cargar_tareas().unwrap().last().unwrap().text,
+        "cliente secreto"
+    );
+    drop(db);
+    borrar(ruta);
+}
+
+#[test]
+fn las_copias_de_una_base_cifrada_se_cifran_y_se_restauran() {
+    let ruta = ruta_db("copias-cifradas");
+    let mut db = Db::abrir_cifrada(ruta.to_str().unwrap(), CLAVE).unwrap();
+    let antes = db.cargar_tareas().unwrap().len();
+    let copia = db.copia_automatica().unwrap().unwrap();
+    assert!(esta_cifrada(&copia));
+
+    db.agregar_tarea("después de la copia").unwrap();
+    db.restaurar_copia(&copia).unwrap();
+    assert_eq!(db.cargar_tareas().unwrap().len(), antes);
+    assert!(!en_claro(&ruta, "después de la copia"));
+    drop(db);
+    borrar(ruta);
+}
+
+#[test]
+fn no_se_cifra_una_base_abierta_en_otra_instancia() {
+    let ruta = ruta_db("cifrar-abierta");
+    let db = Db::new(ruta.to_str().unwrap()).unwrap();
+    db.agregar_tarea("aún en el WAL").unwrap();
+    assert!(
+        std::fs::metadata(ruta.with_extension("db-wal"))
+            .unwrap()
+            .len()
+            > 0
+    );
+
+    // Ni abierta sin hacer nada, ni leyendo
+    assert!(matches!(cifrar(&ruta, CLAVE), Err(ErrorCopia::EnUso)));
+    assert!(!esta_cifrada(&ruta));
+    let lectura = rusqlite::Connection::open(&ruta).unwrap();
+    drop(db);
+    lectura
+        .execute_batch("BEGIN; SELECT COUNT(*) FROM tareas;")
+        .unwrap();
+    assert!(matches!(cifrar(&ruta, CLAVE), Err(ErrorCopia::EnUso)));
+    drop(lectura);
+
+    // Cerrada, se cifra con lo que había en el WAL
+    cifrar(&ruta, CLAVE).unwrap();
+    assert!(esta_cifrada(&ruta));
+    assert!(!ruta.with_extension("db-cifrando").exists());
+    let db = Db::abrir_cifrada(ruta.to_str().unwrap(), CLAVE).unwrap();
+    assert!(
+        db.cargar_tareas()
+            .unwrap()
+            .iter()
+            .any(|t| t.text == "aún en el WAL")
+    );
+
+    // Y lo mismo al descifrar
+    assert!(matches!(descifrar(&ruta, CLAVE), Err(ErrorCopia::EnUso)));
+    drop(db);
+    descifrar(&ruta, CLAVE).unwrap();
+    assert!(!esta_cifrada(&ruta));
+    borrar(ruta);
+}
@@ -0,0 +1,23 @@
+//! Utilidades de las pruebas que usan ficheros. Cada prueba usa solo
+//! algunas.
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+/// Carpeta temporal propia de cada prueba, vacía al empezar
+pub fn carpeta(nombre: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pixi-{}-{}", nombre, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Base de datos en una carpeta temporal propia de cada prueba
+pub fn ruta_db(nombre: &str) -> PathBuf {
+    carpeta(nombre).join("tareas.db")
+}
+
+/// Borra la carpeta de una base de datos de [`ruta_db`]
+pub fn borrar(ruta: PathBuf) {
+    std::fs::remove_dir_all(ruta.parent().unwrap()).unwrap();
+}
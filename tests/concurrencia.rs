@@ -0,0 +1,216 @@
+mod common;
+
+use std::path::Path;
+
+use common::{borrar, ruta_db};
+use pixi::{Db, Edicion, Recurrencia, ResultadoEdicion, ServicioTareas};
+
+fn abrir(ruta: &Path) -> Db {
+    Db::new(ruta.to_str().unwrap()).unwrap()
+}
+
+#[test]
+fn los_ficheros_se_abren_en_modo_wal() {
+    let ruta = ruta_db("wal");
+    drop(abrir(&ruta));
+
+    let conn = rusqlite::Connection::open(&ruta).unwrap();
+    let modo: String = conn
+        .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(modo, "wal");
+    drop(conn);
+    borrar(ruta);
+}
+
+#[test]
+fn detecta_los_cambios_de_otra_instancia() {
+    let ruta = ruta_db("cambios");
+    let mut servicio = ServicioTareas::new(abrir(&ruta));
+    let otra = abrir(&ruta);
+    assert!(!servicio.hay_cambios_externos());
+
+    // Los cambios propios no cuentan
+    servicio.agregar("propia").unwrap();
+    assert!(!servicio.hay_cambios_externos());
+
+    let id = otra.agregar_tarea("de la otra").unwrap();
+    assert!(servicio.hay_cambios_externos());
+
+    servicio.recargar();
+    assert!(!servicio.hay_cambios_externos());
+    assert_eq!(servicio.tarea(id).unwrap().text, "de la otra");
+
+    drop((servicio, otra));
+    borrar(ruta);
+}
+
+#[test]
+fn editar_no_pisa_la_edicion_de_otra_instancia() {
+    let ruta = ruta_db("conflicto");
+    let mut primera = ServicioTareas::new(abrir(&ruta));
+    let mut segunda = ServicioTareas::new(abrir(&ruta));
+    let id = primera.tareas()[0].id;
+    let edicion = |servicio: &ServicioTareas, descripcion: &str| Edicion {
+        descripcion: descripcion.to_string(),
+        ..Edicion::desde(servicio.tarea(id).unwrap())
+    };
+
+    let de_la_segunda = edicion(&segunda, "de la segunda");
+    assert_eq!(
+        segunda.editar(id, &de_la_segunda).unwrap(),
+        ResultadoEdicion::Guardada
+    );
+
+    // La primera aún tenía la versión anterior: no guarda y se actualiza
+    let de_la_primera = edicion(&primera, "de la primera");
+    assert_eq!(
+        primera.editar(id, &de_la_primera).unwrap(),
+        ResultadoEdicion::Conflicto
+    );
+    assert_eq!(primera.tarea(id).unwrap().text, "de la segunda");
+    assert_eq!(primera.db().cargar_tarea(id).unwrap().text, "de la segunda");
+
+    // Ya al día, puede editar; y ahora es la segunda la que va atrasada
+    assert_eq!(
+        primera.editar(id, &de_la_primera).unwrap(),
+        ResultadoEdicion::Guardada
+    );
+    let otra_vez = edicion(&segunda, "otra vez");
+    assert_eq!(
+        segunda.editar(id, &otra_vez).unwrap(),
+        ResultadoEdicion::Conflicto
+    );
+    assert_eq!(segunda.tarea(id).unwrap().text, "de la primera");
+
+    // Si la edición no se guarda, tampoco se marca
+    let completada = edicion(&primera, "completada");
+    segunda.editar(id, &edicion(&segunda, "de nuevo")).unwrap();
+    assert_eq!(
+        primera
+            .editar_y_marcar(id, &completada, Some(true))
+            .unwrap(),
+        ResultadoEdicion::Conflicto
+    );
+    assert!(!primera.db().cargar_tarea(id).unwrap().checked);
+    assert_eq!(
+        primera
+            .editar_y_marcar(id, &completada, Some(true))
+            .unwrap(),
+        ResultadoEdicion::Guardada
+    );
+    assert!(primera.tarea(id).unwrap().checked);
+    assert!(primera.db().cargar_tarea(id).unwrap().checked);
+
+    drop((primera, segunda));
+    borrar(ruta);
+}
+
+#[test]
+fn el_tiempo_de_dos_instancias_se_suma() {
+    let ruta = ruta_db("tiempo");
+    let primera = abrir(&ruta);
+    let segunda = abrir(&ruta);
+    let id = primera.cargar_tareas().unwrap()[0].id;
+
+    assert_eq!(primera.sumar_tiempo(id, 60).unwrap(), 60);
+    assert_eq!(segunda.sumar_tiempo(id, 90).unwrap(), 150);
+    assert_eq!(primera.cargar_tarea(id).unwrap().tiempo_total(), 150);
+
+    drop((primera, segunda));
+    borrar(ruta);
+}
+
+#[test]
+fn recargar_conserva_el_orden_de_la_lista() {
+    let ruta = ruta_db("orden");
+    let mut servicio = ServicioTareas::new(abrir(&ruta));
+    let otra = abrir(&ruta);
+    let ids: Vec<i32> = servicio.tareas().iter().map(|t| t.id).collect();
+    let (primera, ultima) = (ids[0], *ids.last().unwrap());
+    assert!(servicio.mover(ultima, primera));
+
+    let nueva = otra.agregar_tarea("de la otra").unwrap();
+    servicio.recargar();
+
+    let orden: Vec<i32> = servicio.tareas().iter().map(|t| t.id).collect();
+    assert_eq!(orden[0], ultima);
+    assert_eq!(orden[1], primera);
+    assert_eq!(*orden.last().unwrap(), nueva);
+
+    drop((servicio, otra));
+    borrar(ruta);
+}
+
+#[test]
+fn el_vigilante_ve_los_cambios_de_cualquier_instancia() {
+    let ruta = ruta_db("vigilante");
+    let db = abrir(&ruta);
+    let otra = abrir(&ruta);
+    let mut vigilante = db.vigilar_cambios().unwrap().unwrap();
+    assert!(!vigilante.hay_cambios().unwrap());
+
+    otra.agregar_tarea("de la otra").unwrap();
+    assert!(vigilante.hay_cambios().unwrap());
+    assert!(!vigilante.hay_cambios().unwrap());
+    db.agregar_tarea("propia").unwrap();
+    assert!(vigilante.hay_cambios().unwrap());
+
+    // En memoria nadie más puede cambiarla
+    assert!(
+        Db::new(":memory:")
+            .unwrap()
+            .vigilar_cambios()
+            .unwrap()
+            .is_none()
+    );
+
+    drop((vigilante, db, otra));
+    borrar(ruta);
+}
+
+#[test]
+fn completar_una_recurrente_no_deja_que_otra_le_devuelva_la_regla() {
+    let ruta = ruta_db("recurrente");
+    let mut primera = ServicioTareas::new(abrir(&ruta));
+    let mut segunda = ServicioTareas::new(abrir(&ruta));
+    let id = primera.agregar("recurrente").unwrap().unwrap();
+    primera
+        .db()
+        .actualizar_recurrencia(id, Some(Recurrencia::Diaria))
+        .unwrap();
+    primera.recargar();
+    segunda.recargar();
+
+    // La regla pasa a la nueva ocurrencia
+    let siguiente = primera.marcar(id, true).unwrap().unwrap();
+    assert_eq!(primera.tarea(id).unwrap().recurrencia, None);
+
+    // La segunda aún la veía con regla: su edición no se guarda
+    let edicion = Edicion {
+        descripcion: "editada".to_string(),
+        ..Edicion::desde(segunda.tarea(id).unwrap())
+    };
+    assert_eq!(
+        segunda.editar(id, &edicion).unwrap(),
+        ResultadoEdicion::Conflicto
+    );
+    assert_eq!(primera.db().cargar_tarea(id).unwrap().recurrencia, None);
+    assert_eq!(
+        primera.db().cargar_tarea(siguiente).unwrap().recurrencia,
+        Some(Recurrencia::Diaria)
+    );
+
+    // Y la primera ya tiene la versión nueva
+    let edicion = Edicion {
+        descripcion: "editada".to_string(),
+        ..Edicion::desde(primera.tarea(id).unwrap())
+    };
+    assert_eq!(
+        primera.editar(id, &edicion).unwrap(),
+        ResultadoEdicion::Guardada
+    );
+
+    drop((primera, segunda));
+    borrar(ruta);
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::carpeta;
+use pixi::Db;
+use pixi::copias::{ErrorCopia, directorio_copias, listar_copias, validar_copia};
+
+#[test]
+fn la_rotacion_conserva_las_mas_recientes() {
+    let dir = carpeta("rotacion");
+    let db = Db::new(":memory:").unwrap();
+
+    let copias: Vec<PathBuf> = (0..4)
+        .map(|_| {
+            let copia = db.copia_rotativa(&dir, 2).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            copia
+        })
+        .collect();
+
+    assert_eq!(
+        listar_copias(&dir).unwrap(),
+        [copias[3].clone(), copias[2].clone()]
+    );
+    validar_copia(&copias[3]).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn la_copia_automatica_puede_hacerse_en_otro_hilo() {
+    let dir = carpeta("en-hilo");
+    let ruta = dir.join("tareas.db");
+    let db = Db::new(ruta.to_str().unwrap()).unwrap();
+    let id = db.agregar_tarea("antes de la copia").unwrap();
+
+    let copia = db.preparar_copia_automatica().unwrap().unwrap();
+    let hilo = std::thread::spawn(move || copia.hacer());
+    // Mientras, la base de datos sigue disponible
+    db.agregar_tarea("durante la copia").unwrap();
+    let copia = hilo.join().unwrap().unwrap();
+
+    assert_eq!(copia.parent().unwrap(), directorio_copias(&ruta));
+    validar_copia(&copia).unwrap();
+    let copiada = Db::new(copia.to_str().unwrap()).unwrap();
+    assert_eq!(copiada.cargar_tarea(id).unwrap().text, "antes de la copia");
+
+    let mut ajustes = db.cargar_ajustes().unwrap();
+    ajustes.copias_conservar = 0;
+    db.guardar_ajustes(&ajustes).unwrap();
+    assert!(db.preparar_copia_automatica().unwrap().is_none());
+    drop((db, copiada));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn la_copia_automatica_sigue_los_ajustes() {
+    let dir = carpeta("automatica");
+    let ruta = dir.join("tareas.db");
+    let db = Db::new(ruta.to_str().unwrap()).unwrap();
+
+    let copia = db.copia_automatica().unwrap().unwrap();
+    assert_eq!(copia.parent().unwrap(), directorio_copias(&ruta));
+
+    let mut ajustes = db.cargar_ajustes().unwrap();
+    ajustes.copias_conservar = 0;
+    db.guardar_ajustes(&ajustes).unwrap();
+    assert!(db.copia_automatica().unwrap().is_none());
+
+    // En memoria no hay fichero que copiar
+    assert!(
+        Db::new(":memory:")
+            .unwrap()
+            .copia_automatica()
+            .unwrap()
+            .is_none()
+    );
+    drop(db);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn restaurar_recupera_el_contenido_de_la_copia() {
+    let dir = carpeta("restaurar");
+    let ruta = dir.join("tareas.db");
+    let mut db = Db::new(ruta.to_str().unwrap()).unwrap();
+    let copia = dir.join("antes.db");
+    db.hacer_copia(&copia).unwrap();
+    let antes = db.cargar_tareas().unwrap().len();
+
+    db.agregar_tarea("después de la copia").unwrap();
+    db.restaurar_copia(&copia).unwrap();
+
+    assert_eq!(db.cargar_tareas().unwrap().len(), antes);
+    // Lo sustituido queda guardado por si acaso
+    let previa = &listar_copias(&directorio_copias(&ruta)).unwrap()[0];
+    let sustituida = Db::new(previa.to_str().unwrap()).unwrap();
+    assert_eq!(sustituida.cargar_tareas().unwrap().len(), antes + 1);
+    drop((db, sustituida));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn no_se_restaura_una_base_abierta_en_otra_instancia() {
+    let dir = carpeta("restaurar-abierta");
+    let ruta = dir.join("tareas.db");
+    let mut db = Db::new(ruta.to_str().unwrap()).unwrap();
+    let copia = dir.join("antes.db");
+    db.hacer_copia(&copia).unwrap();
+    let antes = db.cargar_tareas().unwrap().len();
+    db.agregar_tarea("después de la copia").unwrap();
+
+    let otra = Db::new(ruta.to_str().unwrap()).unwrap();
+    assert!(matches!(db.restaurar_copia(&copia), Err(ErrorCopia::EnUso)));
+    // Las dos siguen como estaban
+    assert_eq!(db.cargar_tareas().unwrap().len(), antes + 1);
+    assert_eq!(otra.cargar_tareas().unwrap().len(), antes + 1);
+
+    drop(otra);
+    db.restaurar_copia(&copia).unwrap();
+    assert_eq!(db.cargar_tareas().unwrap().len(), antes);
+    drop(db);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn restaurar_rechaza_copias_no_validas() {
+    let dir = carpeta("invalidas");
+    let mut db = Db::new(":memory:").unwrap();
+    let antes = db.cargar_tareas().unwrap().len();
+
+    let basura = dir.join("basura.db");
+    std::fs::write(&basura, "esto no es una base de datos").unwrap();
+    assert!(matches!(
+        db.restaurar_copia(&basura),
+        Err(ErrorCopia::Sql(_))
+    ));
+
+    let ajena = dir.join("ajena.db");
+    rusqlite::Connection::open(&ajena)
+        .unwrap()
+        .execute("CREATE TABLE otra (x INTEGER)", [])
+        .unwrap();
+    assert!(matches!(
+        db.restaurar_copia(&ajena),
+        Err(ErrorCopia::SinTareas)
+    ));
+
+    assert!(matches!(
+        db.restaurar_copia(&dir.join("no-existe.db")),
+        Err(ErrorCopia::Io(_))
+    ));
+
+    assert_eq!(db.cargar_tareas().unwrap().len(), antes);
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,275 @@
+// Los ganchos de prueba son órdenes de `sh`
+#![cfg(unix)]
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use common::carpeta;
+use pixi::ganchos::{Evento, FalloGancho};
+use pixi::{Ajustes, Db, Idioma, ServicioTareas};
+
+struct Directorio(PathBuf);
+
+impl Directorio {
+    fn new(nombre: &str) -> Self {
+        Directorio(carpeta(nombre))
+    }
+}
+
+impl Drop for Directorio {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// Todos los eventos anotan en `registro` una línea `<evento> <id>`
+fn registrar_eventos(db: &Db, registro: &Path) {
+    for evento in Evento::TODOS {
+        let orden = format!(
+            "echo \"$PIXI_EVENTO $PIXI_TAREA_ID\" >> '{}'",
+            registro.display()
+        );
+        db.guardar_gancho(evento, &orden).unwrap();
+    }
+}
+
+fn lineas(registro: &Path) -> Vec<String> {
+    std::fs::read_to_string(registro)
+        .unwrap_or_default()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn el_gancho_recibe_la_tarea_en_json() {
+    let dir = Directorio::new("ganchos-json");
+    let db = Db::new(":memory:").unwrap();
+    let stdin = dir.0.join("stdin.json");
+    db.guardar_gancho(Evento::Agregada, &format!("cat > '{}'", stdin.display()))
+        .unwrap();
+
+    let id = db.agregar_tarea("con \"comillas\" y ñ").unwrap();
+    db.esperar_ganchos();
+
+    let tarea: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(stdin).unwrap()).unwrap();
+    assert_eq!(tarea["id"], id);
+    assert_eq!(tarea["descripcion"], "con \"comillas\" y ñ");
+    assert_eq!(tarea["completada"], false);
+    assert!(db.errores_ganchos().is_empty());
+}
+
+#[test]
+fn cada_evento_dispara_su_gancho_en_orden() {
+    let dir = Directorio::new("ganchos-eventos");
+    let registro = dir.0.join("registro");
+    let mut servicio = ServicioTareas::new(Db::new(":memory:").unwrap());
+    registrar_eventos(servicio.db(), &registro);
+
+    let id = servicio.agregar("tarea").unwrap().unwrap();
+    servicio.alternar_temporizador(id);
+    servicio.alternar_temporizador(id);
+    servicio.marcar(id, true).unwrap();
+    // Desmarcarla no es ningún evento
+    servicio.marcar(id, false).unwrap();
+    servicio.eliminar(id).unwrap();
+    servicio.db().esperar_ganchos();
+
+    assert_eq!(
+        lineas(&registro),
+        ["add", "timer_start", "timer_stop", "complete", "delete"].map(|e| format!("{} {}", e, id))
+    );
+}
+
+#[test]
+fn sin_ganchos_no_se_ejecuta_nada() {
+    let db = Db::new(":memory:").unwrap();
+    let id = db.agregar_tarea("tarea").unwrap();
+    db.actualizar_tarea(id, true).unwrap();
+    db.eliminar_tarea(id).unwrap();
+    db.esperar_ganchos();
+    assert!(db.errores_ganchos().is_empty());
+
+    // Una orden vacía quita el gancho
+    db.guardar_gancho(Evento::Agregada, "exit 1").unwrap();
+    db.guardar_gancho(Evento::Agregada, "  ").unwrap();
+    assert_eq!(db.gancho(Evento::Agregada).unwrap(), None);
+}
+
+#[test]
+fn una_transaccion_deshecha_no_dispara_sus_ganchos() {
+    let dir = Directorio::new("ganchos-transaccion");
+    let registro = dir.0.join("registro");
+    let db = Db::new(":memory:").unwrap();
+    registrar_eventos(&db, &registro);
+
+    let deshecha = db.transaccion::<()>(|db| {
+        db.agregar_tarea("no llega")?;
+        Err(rusqlite::Error::InvalidQuery)
+    });
+    assert!(deshecha.is_err());
+
+    // Dentro de una que se confirma, solo se olvida la parte deshecha
+    let id = db
+        .transaccion(|db| {
+            let id = db.agregar_tarea("sí llega")?;
+            let _ = db.transaccion::<()>(|db| {
+                db.eliminar_tarea(id)?;
+                Err(rusqlite::Error::InvalidQuery)
+            });
+            db.actualizar_tarea(id, true)?;
+            Ok(id)
+        })
+        .unwrap();
+    db.esperar_ganchos();
+
+    assert_eq!(
+        lineas(&registro),
+        [format!("add {}", id), format!("complete {}", id)]
+    );
+}
+
+#[test]
+fn se_informa_de_los_ganchos_que_fallan() {
+    let db = Db::new(":memory:").unwrap();
+    db.guardar_gancho(Evento::Agregada, "echo 'algo fue mal' >&2; exit 3")
+        .unwrap();
+    db.guardar_gancho(Evento::Completada, "/no/existe/esta/orden")
+        .unwrap();
+
+    let id = db.agregar_tarea("tarea").unwrap();
+    db.actualizar_tarea(id, true).unwrap();
+    db.esperar_ganchos();
+
+    let errores = db.errores_ganchos();
+    assert_eq!(errores.len(), 2);
+    assert_eq!(
+        (errores[0].evento, errores[0].tarea),
+        (Evento::Agregada, id)
+    );
+    match &errores[0].fallo {
+        FalloGancho::Salida(estado, salida) => {
+            assert_eq!(estado.code(), Some(3));
+            assert_eq!(salida, "algo fue mal");
+        }
+        fallo => panic!("fallo inesperado: {:?}", fallo),
+    }
+    assert_eq!(errores[1].evento, Evento::Completada);
+    assert!(matches!(errores[1].fallo, FalloGancho::Salida(..)));
+    let mensaje = errores[0].describir(Idioma::Es.textos());
+    assert!(mensaje.contains("algo fue mal") && mensaje.contains("«add»"));
+    // Ya recogidos
+    assert!(db.errores_ganchos().is_empty());
+}
+
+#[test]
+fn los_ganchos_lentos_se_detienen() {
+    let db = Db::new(":memory:").unwrap();
+    db.guardar_ajustes(&Ajustes {
+        limite_ganchos: 1,
+        ..Ajustes::default()
+    })
+    .unwrap();
+    db.guardar_gancho(Evento::Agregada, "exec sleep 30")
+        .unwrap();
+
+    let inicio = Instant::now();
+    db.agregar_tarea("tarea").unwrap();
+    // Lanzarlo no espera a que termine
+    assert!(inicio.elapsed() < Duration::from_millis(500));
+    db.esperar_ganchos();
+    assert!(inicio.elapsed() < Duration::from_secs(10));
+
+    let errores = db.errores_ganchos();
+    assert_eq!(errores.len(), 1);
+    assert!(
+        matches!(errores[0].fallo, FalloGancho::Limite(limite) if limite == Duration::from_secs(1))
+    );
+    assert!(
+        errores[0]
+            .describir(Idioma::En.textos())
+            .ends_with("killed after 1 s")
+    );
+}
+
+#[test]
+fn un_gancho_que_no_lee_la_entrada_tambien_se_detiene() {
+    let db = Db::new(":memory:").unwrap();
+    db.guardar_ajustes(&Ajustes {
+        limite_ganchos: 1,
+        ..Ajustes::default()
+    })
+    .unwrap();
+    let id = db.agregar_tarea("tarea").unwrap();
+    // Más de lo que cabe en la tubería y en el entorno de un proceso
+    db.actualizar_notas(id, &"x".repeat(512 * 1024)).unwrap();
+    db.guardar_gancho(Evento::Completada, "exec sleep 30")
+        .unwrap();
+    db.guardar_gancho(Evento::Eliminada, "exec yes >&2")
+        .unwrap();
+
+    let inicio = Instant::now();
+    db.actualizar_tarea(id, true).unwrap();
+    db.eliminar_tarea(id).unwrap();
+    db.esperar_ganchos();
+    assert!(inicio.elapsed() < Duration::from_secs(10));
+
+    let errores = db.errores_ganchos();
+    assert_eq!(errores.len(), 2);
+    assert!(
+        errores
+            .iter()
+            .all(|e| matches!(e.fallo, FalloGancho::Limite(_)))
+    );
+}
+
+#[test]
+fn un_gancho_que_falla_no_espera_a_quien_tenga_su_salida_de_error() {
+    let db = Db::new(":memory:").unwrap();
+    // El proceso que lanza sigue con la salida de error abierta
+    db.guardar_gancho(Evento::Agregada, "sleep 30 & exit 1")
+        .unwrap();
+
+    let inicio = Instant::now();
+    db.agregar_tarea("tarea").unwrap();
+    db.esperar_ganchos();
+    assert!(inicio.elapsed() < Duration::from_secs(10));
+
+    let errores = db.errores_ganchos();
+    assert_eq!(errores.len(), 1);
+    assert!(
+        matches!(&errores[0].fallo, FalloGancho::Salida(estado, _) if estado.code() == Some(1))
+    );
+}
+
+#[test]
+fn quitar_una_tarea_con_el_temporizador_en_marcha_lo_para() {
+    let dir = Directorio::new("ganchos-quitar");
+    let registro = dir.0.join("registro");
+    let mut servicio = ServicioTareas::new(Db::new(":memory:").unwrap());
+    registrar_eventos(servicio.db(), &registro);
+
+    let borrada = servicio.agregar("a la papelera").unwrap().unwrap();
+    let archivada = servicio.agregar("al archivo").unwrap().unwrap();
+    servicio.alternar_temporizador(borrada);
+    servicio.alternar_temporizador(archivada);
+    servicio.eliminar(borrada).unwrap();
+    servicio.archivar_varias(&[archivada]).unwrap();
+    servicio.db().esperar_ganchos();
+
+    let lineas = lineas(&registro);
+    for id in [borrada, archivada] {
+        assert!(lineas.contains(&format!("timer_stop {}", id)));
+    }
+    assert!(
+        lineas
+            .iter()
+            .position(|l| *l == format!("timer_stop {}", borrada))
+            < lineas
+                .iter()
+                .position(|l| *l == format!("delete {}", borrada))
+    );
+}
@@ -0,0 +1,133 @@
+use pixi::{Db, EstadoLista, ListaTareas};
+
+fn lista(db: &Db) -> ListaTareas {
+    ListaTareas::new(db.cargar_tareas().unwrap())
+}
+
+// Arrastra la tarea `id` por encima de las de `destinos`, como hace la
+// ventana: mientras dura, la lista se reordena bajo el resto del estado
+fn arrastrar(tareas: &mut ListaTareas, estado: &mut EstadoLista, id: i32, destinos: &[usize]) {
+    estado.arrastrando = Some(id);
+    for &destino in destinos {
+        let destino = tareas[destino].id;
+        if estado.arrastrando != Some(destino) {
+            assert!(tareas.mover(id, destino));
+        }
+    }
+    estado.arrastrando = None;
+}
+
+#[test]
+fn editar_durante_reordenacion() {
+    let db = Db::new(":memory:").unwrap();
+    let mut tareas = lista(&db);
+    let mut estado = EstadoLista::default();
+    let editada = tareas[2].id;
+    let texto = tareas[2].text.clone();
+    estado.editando = Some(editada);
+    estado.seleccionada = Some(editada);
+
+    // Se arrastran otras tareas y la propia editada, con la edición abierta
+    let (primera, octava) = (tareas[0].id, tareas[7].id);
+    arrastrar(&mut tareas, &mut estado, primera, &[3, 4, 5]);
+    arrastrar(&mut tareas, &mut estado, editada, &[1, 0]);
+    arrastrar(&mut tareas, &mut estado, octava, &[1]);
+    assert_ne!(tareas.posicion(editada), Some(2));
+
+    // La caja de edición sigue en la misma tarea
+    let id = estado.editando.unwrap();
+    assert_eq!(id, editada);
+    assert_eq!(tareas.get(id).unwrap().text, texto);
+    assert_eq!(estado.seleccionada, Some(editada));
+
+    // Al guardar, el cambio va a la tarea editada y a ninguna otra
+    db.actualizar_descripcion(id, "editada").unwrap();
+    tareas.get_mut(id).unwrap().text = "editada".to_string();
+
+    let guardadas = lista(&db);
+    for tarea in guardadas.iter() {
+        if tarea.id == editada {
+            assert_eq!(tarea.text, "editada");
+        } else {
+            assert_eq!(tarea.text, tareas.get(tarea.id).unwrap().text);
+        }
+    }
+}
+
+#[test]
+fn borrar_durante_edicion() {
+    let db = Db::new(":memory:").unwrap();
+    let mut tareas = lista(&db);
+    let mut estado = EstadoLista::default();
+    let editada = tareas[3].id;
+    let texto = tareas[3].text.clone();
+    estado.editando = Some(editada);
+    estado.desplegada = Some(editada);
+
+    // Borrar una tarea anterior desplaza la editada, pero sigue siendo ella
+    let anterior = tareas[0].id;
+    estado.seleccionada = Some(anterior);
+    estado.marcadas.extend([anterior, editada]);
+    let vecina = estado.vecina_superviviente(&tareas, &[anterior]);
+    assert_eq!(vecina, Some(tareas[1].id));
+    db.eliminar_tarea(anterior).unwrap();
+    assert!(tareas.quitar(anterior).is_some());
+    estado.olvidar(&[anterior], vecina);
+
+    assert_eq!(estado.editando, Some(editada));
+    assert_eq!(estado.desplegada, Some(editada));
+    assert_eq!(tareas.get(editada).unwrap().text, texto);
+    assert_eq!(tareas.posicion(editada), Some(2));
+    assert_eq!(estado.seleccionada, vecina);
+    assert!(!estado.marcadas.contains(&anterior));
+
+    // Borrar la propia tarea editada cierra la edición
+    estado.seleccionada = Some(editada);
+    let siguiente = tareas[3].id;
+    let vecina = estado.vecina_superviviente(&tareas, &[editada]);
+    assert_eq!(vecina, Some(siguiente));
+    db.eliminar_tarea(editada).unwrap();
+    assert!(tareas.quitar(editada).is_some());
+    estado.olvidar(&[editada], vecina);
+
+    assert_eq!(estado.editando, None);
+    assert_eq!(estado.desplegada, None);
+    assert_eq!(estado.seleccionada, Some(siguiente));
+    assert!(estado.marcadas.is_empty());
+    assert!(!tareas.mover(editada, siguiente));
+    assert!(db.cargar_tarea(editada).is_err());
+}
+
+#[test]
+fn al_recargar_se_olvidan_las_tareas_que_ya_no_estan() {
+    let db = Db::new(":memory:").unwrap();
+    let tareas = lista(&db);
+    let mut estado = EstadoLista::default();
+    let (editada, borrada) = (tareas[1].id, tareas[4].id);
+    estado.editando = Some(editada);
+    estado.arrastrando = Some(borrada);
+    estado.seleccionada = Some(borrada);
+    estado.marcadas.extend([editada, borrada]);
+
+    // Otra instancia borra una y el resto se reordena
+    db.eliminar_tarea(borrada).unwrap();
+    let mut tareas = lista(&db);
+    tareas.reverse();
+    estado.olvidar_ausentes(&tareas);
+
+    assert_eq!(estado.editando, Some(editada));
+    assert_eq!(estado.arrastrando, None);
+    assert_eq!(estado.seleccionada, None);
+    assert_eq!(estado.marcadas.iter().collect::<Vec<_>>(), [&editada]);
+}
+
+#[test]
+fn vecina_de_la_ultima_es_la_anterior() {
+    let db = Db::new(":memory:").unwrap();
+    let tareas = lista(&db);
+    let n = tareas.len();
+
+    assert_eq!(tareas.vecina(tareas[n - 1].id), Some(tareas[n - 2].id));
+    assert_eq!(tareas.vecina(tareas[0].id), Some(tareas[1].id));
+    assert_eq!(tareas.vecina(-1), None);
+}
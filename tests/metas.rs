@@ -0,0 +1,181 @@
+mod common;
+
+use common::{borrar, ruta_db};
+use pixi::metas::semana;
+use pixi::{Db, Meta, Periodo, ResumenDia};
+
+// 2025-03-10, un lunes, tal como lo cuenta `Db::dia_actual`
+const LUNES: i64 = 2460744;
+
+fn dia(dia: i64, segundos: i32, completadas: i32) -> ResumenDia {
+    ResumenDia {
+        dia,
+        segundos,
+        completadas,
+    }
+}
+
+#[test]
+fn la_racha_diaria_se_corta_en_los_huecos() {
+    let meta = Meta {
+        segundos: 3600,
+        tareas: 0,
+    };
+    let hoy = LUNES + 10;
+    // Cumplida hace 1, 2 y 3 días; falta el 4 y antes cumplida otra vez
+    let dias = [
+        dia(hoy - 6, 7200, 0),
+        dia(hoy - 5, 3600, 0),
+        dia(hoy - 3, 3600, 2),
+        dia(hoy - 2, 5000, 0),
+        dia(hoy - 1, 3600, 0),
+    ];
+
+    // Hoy aún no se ha cumplido: la racha cuenta desde ayer
+    assert_eq!(meta.racha(Periodo::Dia, &dias, &dia(hoy, 1800, 0)), 3);
+    // Con lo de hoy, suma un día
+    assert_eq!(meta.racha(Periodo::Dia, &dias, &dia(hoy, 3600, 0)), 4);
+    // Un día sin nada rompe la racha, aunque hoy se cumpla
+    assert_eq!(meta.racha(Periodo::Dia, &dias[..4], &dia(hoy, 3600, 0)), 1);
+    assert_eq!(meta.racha(Periodo::Dia, &dias[..4], &dia(hoy, 0, 0)), 0);
+    // Un día por debajo de la meta también
+    let mut flojo = dias;
+    flojo[3].segundos = 3599;
+    assert_eq!(meta.racha(Periodo::Dia, &flojo, &dia(hoy, 0, 0)), 1);
+}
+
+#[test]
+fn hoy_cuenta_lo_que_se_pasa_y_no_lo_guardado() {
+    let meta = Meta {
+        segundos: 0,
+        tareas: 2,
+    };
+    let hoy = LUNES;
+    // Lo guardado de hoy ya va incluido en `hoy`, con las sesiones en curso
+    let dias = [dia(hoy - 1, 0, 2), dia(hoy, 0, 5)];
+    assert_eq!(meta.racha(Periodo::Dia, &dias, &dia(hoy, 0, 1)), 1);
+    assert_eq!(meta.racha(Periodo::Dia, &dias, &dia(hoy, 0, 2)), 2);
+
+    // Sin meta no hay racha
+    assert!(!Meta::default().activa());
+    assert_eq!(
+        Meta::default().racha(Periodo::Dia, &dias, &dia(hoy, 0, 2)),
+        0
+    );
+}
+
+#[test]
+fn las_semanas_van_de_lunes_a_domingo() {
+    assert_eq!(semana(LUNES), semana(LUNES + 6));
+    assert_eq!(semana(LUNES - 1) + 1, semana(LUNES));
+    assert_eq!(semana(LUNES + 7), semana(LUNES) + 1);
+
+    let dias = [
+        dia(LUNES - 1, 1000, 1),
+        dia(LUNES, 600, 1),
+        dia(LUNES + 2, 300, 2),
+        dia(LUNES + 3, 9999, 9),
+    ];
+    // El jueves se toma de `hoy`, no de lo guardado
+    let total = Periodo::Semana.resumen(&dias, &dia(LUNES + 3, 100, 1));
+    assert_eq!(total.dia, LUNES);
+    assert_eq!((total.segundos, total.completadas), (1000, 4));
+}
+
+#[test]
+fn la_racha_semanal_cuenta_semanas_seguidas() {
+    let meta = Meta {
+        segundos: 3600,
+        tareas: 3,
+    };
+    let hoy = LUNES + 2;
+    let dias = [
+        // Hace tres semanas, cumplida
+        dia(LUNES - 21, 3600, 3),
+        // Hace dos, no llega a las tres tareas
+        dia(LUNES - 14, 3600, 1),
+        dia(LUNES - 13, 0, 1),
+        // La anterior, repartida entre el lunes y el domingo
+        dia(LUNES - 7, 1800, 1),
+        dia(LUNES - 1, 1800, 2),
+        // Esta
+        dia(LUNES, 3000, 2),
+    ];
+
+    assert_eq!(meta.racha(Periodo::Semana, &dias, &dia(hoy, 0, 0)), 1);
+    assert_eq!(meta.racha(Periodo::Semana, &dias, &dia(hoy, 600, 1)), 2);
+
+    // Sin la semana que falló, la racha sigue hacia atrás
+    let mut cumplidas = dias;
+    cumplidas[2].completadas = 2;
+    assert_eq!(
+        meta.racha(Periodo::Semana, &cumplidas, &dia(hoy, 600, 1)),
+        4
+    );
+
+    // Una semana entera sin datos también corta la racha
+    let salteada = [dia(LUNES - 14, 3600, 3), dia(LUNES, 3600, 3)];
+    assert_eq!(meta.racha(Periodo::Semana, &salteada, &dia(hoy, 0, 0)), 1);
+}
+
+#[test]
+fn resumen_por_dia_junta_sesiones_y_completadas() {
+    let ruta = ruta_db("metas-resumen");
+    let db = Db::new(ruta.to_str().unwrap()).unwrap();
+    let ids: Vec<i32> = db.cargar_tareas().unwrap().iter().map(|t| t.id).collect();
+    let hoy = db.dia_actual().unwrap();
+
+    db.registrar_sesion(ids[0], 600).unwrap();
+    db.registrar_sesion(ids[1], 300).unwrap();
+    db.actualizar_tarea(ids[0], true).unwrap();
+    db.actualizar_tarea(ids[2], true).unwrap();
+    // Las de la papelera no cuentan; las desmarcadas tampoco
+    db.eliminar_tarea(ids[2]).unwrap();
+    db.actualizar_tarea(ids[4], true).unwrap();
+    db.actualizar_tarea(ids[4], false).unwrap();
+
+    // Días anteriores, con un hueco entre ellos
+    let conn = rusqlite::Connection::open(&ruta).unwrap();
+    conn.execute_batch(&format!(
+        "INSERT INTO sesiones (tarea_id, fecha, segundos) VALUES
+            ({0}, date('now', 'localtime', '-3 days'), 100),
+            ({0}, date('now', 'localtime', '-3 days'), 200),
+            ({1}, date('now', 'localtime', '-1 days'), 50);
+         UPDATE tareas SET completada = 1,
+            completada_en = datetime('now', 'localtime', '-3 days')
+         WHERE id = {1};",
+        ids[0], ids[1]
+    ))
+    .unwrap();
+    drop(conn);
+
+    let dias: Vec<(i64, i32, i32)> = db
+        .resumen_por_dia()
+        .unwrap()
+        .iter()
+        .map(|r| (r.dia, r.segundos, r.completadas))
+        .collect();
+    assert_eq!(dias, [(hoy - 3, 300, 1), (hoy - 1, 50, 0), (hoy, 900, 1)]);
+
+    drop(db);
+    borrar(ruta);
+}
+
+#[test]
+fn las_metas_se_guardan() {
+    let db = Db::new(":memory:").unwrap();
+    assert!(db.cargar_meta(Periodo::Semana).unwrap() == Meta::default());
+
+    let diaria = Meta {
+        segundos: 1800,
+        tareas: 2,
+    };
+    let semanal = Meta {
+        segundos: 5 * 3600,
+        tareas: 10,
+    };
+    db.guardar_meta(Periodo::Dia, &diaria).unwrap();
+    db.guardar_meta(Periodo::Semana, &semanal).unwrap();
+    assert!(db.cargar_meta(Periodo::Dia).unwrap() == diaria);
+    assert!(db.cargar_meta(Periodo::Semana).unwrap() == semanal);
+}
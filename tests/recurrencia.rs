@@ -0,0 +1,187 @@
+use chrono::NaiveDate;
+use pixi::{Db, Recurrencia};
+
+fn fecha(texto: &str) -> NaiveDate {
+    texto.parse().unwrap()
+}
+
+// Lunes a domingo
+fn semanal(dias: &[usize]) -> Recurrencia {
+    let mut semana = [false; 7];
+    for &dia in dias {
+        semana[dia] = true;
+    }
+    Recurrencia::Semanal(semana)
+}
+
+#[test]
+fn mensual_se_ajusta_al_final_de_mes() {
+    let dia_31 = Recurrencia::Mensual(31);
+    assert_eq!(dia_31.siguiente(fecha("2025-01-31")), fecha("2025-02-28"));
+    assert_eq!(dia_31.siguiente(fecha("2025-02-28")), fecha("2025-03-31"));
+    assert_eq!(dia_31.siguiente(fecha("2025-04-15")), fecha("2025-04-30"));
+    assert_eq!(dia_31.siguiente(fecha("2025-04-30")), fecha("2025-05-31"));
+
+    // Del 31 de diciembre pasa al año siguiente
+    assert_eq!(dia_31.siguiente(fecha("2025-12-31")), fecha("2026-01-31"));
+    assert_eq!(
+        Recurrencia::Mensual(15).siguiente(fecha("2025-12-15")),
+        fecha("2026-01-15")
+    );
+
+    // Fuera de rango se toma el día válido más cercano
+    assert_eq!(
+        Recurrencia::Mensual(0).siguiente(fecha("2025-03-01")),
+        fecha("2025-04-01")
+    );
+    assert_eq!(
+        Recurrencia::Mensual(40).siguiente(fecha("2025-06-01")),
+        fecha("2025-06-30")
+    );
+}
+
+#[test]
+fn los_bisiestos_tienen_29_de_febrero() {
+    let dia_29 = Recurrencia::Mensual(29);
+    assert_eq!(dia_29.siguiente(fecha("2024-02-01")), fecha("2024-02-29"));
+    assert_eq!(dia_29.siguiente(fecha("2023-02-01")), fecha("2023-02-28"));
+    assert_eq!(dia_29.siguiente(fecha("2023-02-28")), fecha("2023-03-29"));
+    // 2100 no es bisiesto y 2000 sí
+    assert_eq!(dia_29.siguiente(fecha("2100-02-01")), fecha("2100-02-28"));
+    assert_eq!(dia_29.siguiente(fecha("2000-02-01")), fecha("2000-02-29"));
+
+    assert_eq!(
+        Recurrencia::Diaria.siguiente(fecha("2024-02-28")),
+        fecha("2024-02-29")
+    );
+    assert_eq!(
+        Recurrencia::Diaria.siguiente(fecha("2024-02-29")),
+        fecha("2024-03-01")
+    );
+    assert_eq!(
+        Recurrencia::CadaNDias(366).siguiente(fecha("2024-01-01")),
+        fecha("2025-01-01")
+    );
+}
+
+#[test]
+fn semanal_pasa_a_la_semana_siguiente() {
+    // 2025-03-10 es lunes
+    let lunes_y_jueves = semanal(&[0, 3]);
+    assert_eq!(
+        lunes_y_jueves.siguiente(fecha("2025-03-10")),
+        fecha("2025-03-13")
+    );
+    assert_eq!(
+        lunes_y_jueves.siguiente(fecha("2025-03-13")),
+        fecha("2025-03-17")
+    );
+    // Un solo día: una semana justa
+    assert_eq!(
+        semanal(&[0]).siguiente(fecha("2025-03-10")),
+        fecha("2025-03-17")
+    );
+    // Del domingo 31 de diciembre al lunes del año siguiente
+    assert_eq!(
+        semanal(&[0]).siguiente(fecha("2023-12-31")),
+        fecha("2024-01-01")
+    );
+    // Sin días marcados, una semana después
+    assert_eq!(
+        semanal(&[]).siguiente(fecha("2025-03-10")),
+        fecha("2025-03-17")
+    );
+}
+
+#[test]
+fn la_primera_puede_ser_hoy() {
+    let hoy = fecha("2025-03-10");
+    assert_eq!(Recurrencia::Diaria.primera(hoy), hoy);
+    assert_eq!(semanal(&[0]).primera(hoy), hoy);
+    assert_eq!(semanal(&[1]).primera(hoy), fecha("2025-03-11"));
+    assert_eq!(Recurrencia::Mensual(10).primera(hoy), hoy);
+    assert_eq!(Recurrencia::Mensual(9).primera(hoy), fecha("2025-04-09"));
+    assert_eq!(Recurrencia::CadaNDias(5).primera(hoy), hoy);
+}
+
+#[test]
+fn tras_completar_no_se_acumulan_las_atrasadas() {
+    let hoy = fecha("2025-03-10");
+    let diaria = Recurrencia::Diaria;
+    assert_eq!(
+        diaria.tras_completar(Some(fecha("2025-03-01")), hoy),
+        fecha("2025-03-11")
+    );
+    assert_eq!(diaria.tras_completar(None, hoy), fecha("2025-03-11"));
+    // Adelantada, cuenta desde la prevista
+    assert_eq!(
+        diaria.tras_completar(Some(fecha("2025-03-15")), hoy),
+        fecha("2025-03-16")
+    );
+
+    // Cada N días cuenta desde que se completa
+    let cada_3 = Recurrencia::CadaNDias(3);
+    assert_eq!(
+        cada_3.tras_completar(Some(fecha("2025-03-01")), hoy),
+        fecha("2025-03-13")
+    );
+    assert_eq!(
+        cada_3.tras_completar(Some(fecha("2025-03-20")), hoy),
+        fecha("2025-03-13")
+    );
+    assert_eq!(
+        Recurrencia::CadaNDias(0).tras_completar(None, hoy),
+        fecha("2025-03-11")
+    );
+
+    // Tras un febrero ajustado vuelve al día pedido
+    assert_eq!(
+        Recurrencia::Mensual(31).tras_completar(Some(fecha("2024-02-29")), fecha("2024-02-29")),
+        fecha("2024-03-31")
+    );
+}
+
+#[test]
+fn codificar_y_decodificar() {
+    for regla in [
+        Recurrencia::Diaria,
+        semanal(&[0, 6]),
+        semanal(&[]),
+        Recurrencia::Mensual(31),
+        Recurrencia::CadaNDias(10),
+    ] {
+        assert_eq!(Recurrencia::decodificar(&regla.codificar()), Some(regla));
+    }
+    assert_eq!(Recurrencia::decodificar("semanal:7"), None);
+    assert_eq!(Recurrencia::decodificar("anual"), None);
+}
+
+#[test]
+fn desmarcar_deshace_la_siguiente_si_no_se_ha_tocado() {
+    let db = Db::new(":memory:").unwrap();
+    let id = db.cargar_tareas().unwrap()[3].id;
+    let nueva = db.actualizar_tarea(id, true).unwrap().unwrap();
+    assert_eq!(db.ocurrencia_siguiente(id).unwrap(), Some(nueva));
+
+    // La regla vuelve y la ocurrencia sin tocar se quita
+    assert_eq!(db.actualizar_tarea(id, false).unwrap(), None);
+    assert!(db.cargar_tarea(nueva).is_err());
+    assert_eq!(
+        db.cargar_tarea(id).unwrap().recurrencia,
+        Some(Recurrencia::Diaria)
+    );
+    assert_eq!(db.ocurrencia_siguiente(id).unwrap(), None);
+
+    // Editada, la ocurrencia se queda con la regla
+    let nueva = db.actualizar_tarea(id, true).unwrap().unwrap();
+    assert!(db.reservar_edicion(nueva, 0).unwrap());
+    db.actualizar_descripcion(nueva, "Ejercicio y estiramientos")
+        .unwrap();
+    db.actualizar_tarea(id, false).unwrap();
+    assert_eq!(
+        db.cargar_tarea(nueva).unwrap().recurrencia,
+        Some(Recurrencia::Diaria)
+    );
+    assert_eq!(db.cargar_tarea(id).unwrap().recurrencia, None);
+    assert_eq!(db.ocurrencia_siguiente(id).unwrap(), None);
+}
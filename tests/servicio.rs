@@ -0,0 +1,550 @@
+mod common;
+
+use common::{borrar, ruta_db};
+use pixi::{
+    Db, Edicion, Filtro, Orden, Prioridad, Recurrencia, ResultadoEdicion, ServicioTareas, Totales,
+};
+
+fn servicio() -> ServicioTareas {
+    ServicioTareas::new(Db::new(":memory:").unwrap())
+}
+
+#[test]
+fn carga_las_tareas_de_ejemplo() {
+    let servicio = servicio();
+    let guardadas = servicio.db().cargar_tareas().unwrap();
+
+    assert!(servicio.todo_cargado());
+    assert_eq!(servicio.tareas().len(), guardadas.len());
+    for (tarea, guardada) in servicio.tareas().iter().zip(&guardadas) {
+        assert_eq!(tarea.id, guardada.id);
+        assert_eq!(tarea.text, guardada.text);
+    }
+}
+
+#[test]
+fn agregar_ignora_descripciones_vacias() {
+    let mut servicio = servicio();
+    let n = servicio.tareas().len();
+
+    assert_eq!(servicio.agregar("   ").unwrap(), None);
+    assert_eq!(servicio.tareas().len(), n);
+
+    let id = servicio.agregar("  nueva tarea ").unwrap().unwrap();
+    assert_eq!(servicio.tareas().len(), n + 1);
+    assert_eq!(servicio.tareas().last().unwrap().id, id);
+    assert_eq!(servicio.tarea(id).unwrap().text, "nueva tarea");
+    assert_eq!(servicio.db().cargar_tarea(id).unwrap().text, "nueva tarea");
+}
+
+#[test]
+fn marcar_guarda_el_estado() {
+    let mut servicio = servicio();
+    let id = servicio.tareas()[0].id;
+
+    assert_eq!(servicio.marcar(id, true).unwrap(), None);
+    assert!(servicio.tarea(id).unwrap().checked);
+    assert!(servicio.db().cargar_tarea(id).unwrap().checked);
+
+    servicio.marcar(id, false).unwrap();
+    assert!(!servicio.tarea(id).unwrap().checked);
+    assert!(!servicio.db().cargar_tarea(id).unwrap().checked);
+}
+
+#[test]
+fn completar_una_recurrente_crea_la_siguiente() {
+    let mut servicio = servicio();
+    let id = servicio.tareas()[3].id;
+    assert_eq!(
+        servicio.tarea(id).unwrap().recurrencia,
+        Some(Recurrencia::Diaria)
+    );
+    let n = servicio.tareas().len();
+
+    let nueva = servicio.marcar(id, true).unwrap().unwrap();
+
+    // La regla pasa a la nueva ocurrencia, que se añade al final
+    assert_eq!(servicio.tarea(id).unwrap().recurrencia, None);
+    assert_eq!(servicio.tareas().len(), n + 1);
+    let siguiente = servicio.tarea(nueva).unwrap();
+    assert_eq!(siguiente.recurrencia, Some(Recurrencia::Diaria));
+    assert!(!siguiente.checked);
+    assert_eq!(siguiente.text, servicio.tarea(id).unwrap().text);
+}
+
+#[test]
+fn desmarcar_una_recurrente_deshace_la_siguiente() {
+    let mut servicio = servicio();
+    let id = servicio.tareas()[3].id;
+    let n = servicio.tareas().len();
+    let nueva = servicio.marcar(id, true).unwrap().unwrap();
+
+    // La ocurrencia sin tocar se va a la papelera y la regla vuelve
+    assert_eq!(servicio.marcar(id, false).unwrap(), None);
+    assert_eq!(servicio.tareas().len(), n);
+    assert!(servicio.tarea(nueva).is_none());
+    assert_eq!(servicio.db().cargar_papelera().unwrap()[0].id, nueva);
+    let tarea = servicio.tarea(id).unwrap();
+    assert!(!tarea.checked);
+    assert_eq!(tarea.recurrencia, Some(Recurrencia::Diaria));
+    assert_eq!(
+        servicio.db().cargar_tarea(id).unwrap().recurrencia,
+        Some(Recurrencia::Diaria)
+    );
+    assert_eq!(servicio.db().ocurrencia_siguiente(id).unwrap(), None);
+
+    // Y se puede volver a completar
+    let otra = servicio.marcar(id, true).unwrap().unwrap();
+    assert_ne!(otra, nueva);
+    assert_eq!(servicio.db().ocurrencia_siguiente(id).unwrap(), Some(otra));
+}
+
+#[test]
+fn desmarcar_no_quita_una_ocurrencia_ya_usada() {
+    let mut servicio = servicio();
+    let diaria = servicio.tareas()[3].id;
+    let mensual = servicio.tareas()[6].id;
+    let nuevas = servicio.marcar_varias(&[diaria, mensual], true).unwrap();
+
+    // Con el temporizador en marcha, aunque aún no haya guardado tiempo
+    servicio.alternar_temporizador(nuevas[0]);
+    servicio.marcar(diaria, false).unwrap();
+    let usada = servicio.tarea(nuevas[0]).unwrap();
+    assert!(usada.temporizador_activo());
+    assert_eq!(usada.recurrencia, Some(Recurrencia::Diaria));
+    assert_eq!(servicio.tarea(diaria).unwrap().recurrencia, None);
+    assert_eq!(servicio.db().ocurrencia_siguiente(diaria).unwrap(), None);
+
+    // O completada: entonces la regla ya pasó a otra más
+    servicio.marcar(nuevas[1], true).unwrap().unwrap();
+    servicio.marcar_varias(&[mensual], false).unwrap();
+    assert!(servicio.tarea(nuevas[1]).is_some());
+    assert_eq!(servicio.tarea(mensual).unwrap().recurrencia, None);
+    assert!(servicio.db().cargar_papelera().unwrap().is_empty());
+}
+
+#[test]
+fn eliminar_quita_la_tarea_de_la_lista_y_de_la_db() {
+    let mut servicio = servicio();
+    let id = servicio.tareas()[2].id;
+    let n = servicio.tareas().len();
+
+    servicio.eliminar(id).unwrap();
+
+    assert_eq!(servicio.tareas().len(), n - 1);
+    assert!(servicio.tarea(id).is_none());
+    assert!(servicio.db().cargar_tarea(id).is_err());
+}
+
+#[test]
+fn mover_reordena_por_id() {
+    let mut servicio = servicio();
+    let primera = servicio.tareas()[0].id;
+    let cuarta = servicio.tareas()[3].id;
+
+    assert!(servicio.mover(primera, cuarta));
+    assert_eq!(servicio.tareas().posicion(primera), Some(3));
+    assert_eq!(servicio.tareas().posicion(cuarta), Some(2));
+    assert!(!servicio.mover(-1, cuarta));
+}
+
+#[test]
+fn temporizadores() {
+    let mut servicio = servicio();
+    let id = servicio.tareas()[1].id;
+    assert!(!servicio.hay_temporizador_activo());
+
+    assert!(servicio.alternar_temporizador(id));
+    assert!(servicio.tarea(id).unwrap().temporizador_activo());
+    assert!(servicio.hay_temporizador_activo());
+
+    assert!(!servicio.alternar_temporizador(id));
+    assert!(!servicio.hay_temporizador_activo());
+
+    // Una tarea que no existe no arranca nada
+    assert!(!servicio.alternar_temporizador(-1));
+
+    servicio.alternar_temporizador(id);
+    servicio.pausar_todos();
+    assert!(!servicio.hay_temporizador_activo());
+}
+
+#[test]
+fn resetear_temporizador_borra_el_tiempo_guardado() {
+    let mut servicio = servicio();
+    let id = servicio.tareas()[1].id;
+    servicio.db().actualizar_tiempo(id, 600).unwrap();
+    servicio.recargar();
+    assert_eq!(servicio.tarea(id).unwrap().tiempo_total(), 600);
+
+    servicio.alternar_temporizador(id);
+    servicio.resetear_temporizador(id);
+
+    let tarea = servicio.tarea(id).unwrap();
+    assert!(!tarea.temporizador_activo());
+    assert_eq!(tarea.tiempo_total(), 0);
+    assert_eq!(servicio.db().cargar_tarea(id).unwrap().tiempo_total(), 0);
+}
+
+#[test]
+fn editar_guarda_todos_los_campos() {
+    let mut servicio = servicio();
+    let id = servicio.tareas()[0].id;
+    let edicion = Edicion {
+        descripcion: " editada ".to_string(),
+        notas: "con notas".to_string(),
+        estimacion: Some(1800),
+        prioridad: Prioridad::Alta,
+        recurrencia: Some(Recurrencia::Semanal([
+            true, false, true, false, false, false, false,
+        ])),
+    };
+
+    assert_eq!(
+        servicio.editar(id, &edicion).unwrap(),
+        ResultadoEdicion::Guardada
+    );
+
+    let esperada = Edicion {
+        descripcion: "editada".to_string(),
+        ..edicion
+    };
+    assert_eq!(Edicion::desde(servicio.tarea(id).unwrap()), esperada);
+    assert_eq!(
+        Edicion::desde(&servicio.db().cargar_tarea(id).unwrap()),
+        esperada
+    );
+    assert!(servicio.tarea(id).unwrap().fecha_proxima.is_some());
+}
+
+#[test]
+fn editar_rechaza_una_descripcion_vacia() {
+    let mut servicio = servicio();
+    let id = servicio.tareas()[0].id;
+    let antes = Edicion::desde(servicio.tarea(id).unwrap());
+    let edicion = Edicion {
+        descripcion: "  ".to_string(),
+        notas: "no se guarda".to_string(),
+        ..antes.clone()
+    };
+
+    assert_eq!(
+        servicio.editar(id, &edicion).unwrap(),
+        ResultadoEdicion::Vacia
+    );
+    assert_eq!(Edicion::desde(servicio.tarea(id).unwrap()), antes);
+    assert_eq!(
+        Edicion::desde(&servicio.db().cargar_tarea(id).unwrap()),
+        antes
+    );
+}
+
+#[test]
+fn carga_por_paginas() {
+    let db = Db::new(":memory:").unwrap();
+    let ejemplos = db.cargar_tareas().unwrap().len();
+    for i in 0..ServicioTareas::TAMANO_PAGINA {
+        db.agregar_tarea(&format!("tarea {}", i)).unwrap();
+    }
+    let total = ejemplos + ServicioTareas::TAMANO_PAGINA;
+    let mut servicio = ServicioTareas::new(db);
+
+    assert_eq!(servicio.tareas().len(), ServicioTareas::TAMANO_PAGINA);
+    assert!(!servicio.todo_cargado());
+
+    // Sin todo cargado, la nueva tarea llegará con su página
+    let nueva = servicio.agregar("al final").unwrap().unwrap();
+    assert!(servicio.tarea(nueva).is_none());
+
+    servicio.cargar_mas();
+    assert!(servicio.todo_cargado());
+    assert_eq!(servicio.tareas().len(), total + 1);
+    assert_eq!(servicio.tareas().last().unwrap().id, nueva);
+
+    // Con todo cargado se añade al momento
+    let otra = servicio.agregar("otra").unwrap().unwrap();
+    assert_eq!(servicio.tareas().last().unwrap().id, otra);
+}
+
+#[test]
+fn visibles_filtra_y_ordena() {
+    let mut servicio = servicio();
+    let baja = servicio.tareas()[0].id;
+    let alta = servicio.tareas()[4].id;
+    let media = servicio.tareas()[6].id;
+    for (id, prioridad) in [
+        (baja, Prioridad::Baja),
+        (alta, Prioridad::Alta),
+        (media, Prioridad::Media),
+    ] {
+        let edicion = Edicion {
+            prioridad,
+            ..Edicion::desde(servicio.tarea(id).unwrap())
+        };
+        servicio.editar(id, &edicion).unwrap();
+    }
+    let ids = |servicio: &mut ServicioTareas, filtro: &Filtro| -> Vec<i32> {
+        servicio
+            .visibles(filtro)
+            .into_iter()
+            .map(|idx| servicio.tareas()[idx].id)
+            .collect()
+    };
+
+    let todas = ids(&mut servicio, &Filtro::default());
+    assert_eq!(todas.len(), servicio.tareas().len());
+
+    let filtro = Filtro {
+        prioridad_minima: Prioridad::Baja,
+        ..Filtro::default()
+    };
+    assert_eq!(ids(&mut servicio, &filtro), [baja, alta, media]);
+
+    let filtro = Filtro {
+        orden: Orden::Prioridad,
+        ..filtro
+    };
+    assert_eq!(ids(&mut servicio, &filtro), [alta, media, baja]);
+
+    // Lo que cambia después se ve en el orden siguiente
+    let edicion = Edicion {
+        prioridad: Prioridad::Urgente,
+        ..Edicion::desde(servicio.tarea(baja).unwrap())
+    };
+    servicio.editar(baja, &edicion).unwrap();
+    assert_eq!(ids(&mut servicio, &filtro), [baja, alta, media]);
+
+    let filtro = Filtro {
+        coincidencias: Some([media, baja].into()),
+        ..filtro
+    };
+    assert_eq!(ids(&mut servicio, &filtro), [baja, media]);
+}
+
+#[test]
+fn recargar_conserva_los_temporizadores_en_marcha() {
+    let mut servicio = servicio();
+    let id = servicio.tareas()[5].id;
+    servicio.alternar_temporizador(id);
+    servicio
+        .db()
+        .actualizar_descripcion(id, "cambiada fuera")
+        .unwrap();
+
+    servicio.recargar();
+
+    let tarea = servicio.tarea(id).unwrap();
+    assert_eq!(tarea.text, "cambiada fuera");
+    assert!(tarea.temporizador_activo());
+}
+
+#[test]
+fn totales_incluyen_las_sesiones_en_curso() {
+    let servicio = servicio();
+    let guardados = servicio.db().totales().unwrap();
+    let totales = guardados.con_sesiones(servicio.tareas());
+
+    // Sin temporizadores en marcha no cambia nada
+    assert_eq!(totales.segundos, guardados.segundos);
+    assert_eq!(totales.real, guardados.real);
+    assert_eq!(guardados.tareas as usize, servicio.tareas().len());
+
+    let vacios = Totales::default().con_sesiones(&[]);
+    assert_eq!(vacios.segundos, 0);
+}
+
+#[test]
+fn archivar_completadas_las_quita_de_la_lista() {
+    let mut servicio = servicio();
+    let completada = servicio.tareas()[0].id;
+    let pendiente = servicio.tareas()[1].id;
+    servicio.marcar(completada, true).unwrap();
+    servicio.db().actualizar_tiempo(completada, 300).unwrap();
+    let antes = servicio.db().totales().unwrap();
+
+    assert_eq!(servicio.archivar_completadas().unwrap(), 1);
+
+    assert!(servicio.tarea(completada).is_none());
+    assert!(servicio.tarea(pendiente).is_some());
+    let archivadas = servicio.db().cargar_archivadas().unwrap();
+    assert_eq!(archivadas.len(), 1);
+    assert_eq!(archivadas[0].id, completada);
+
+    // Ni al recargar vuelve a la lista, ni cuenta como tarea, pero su
+    // tiempo sigue contando
+    servicio.recargar();
+    assert!(servicio.tarea(completada).is_none());
+    let despues = servicio.db().totales().unwrap();
+    assert_eq!(despues.segundos, antes.segundos);
+    assert_eq!(despues.tareas, antes.tareas - 1);
+    assert_eq!(despues.completadas, antes.completadas - 1);
+    assert_eq!(despues.tareas as usize, servicio.tareas().len());
+
+    // Archivar una pendiente tampoco cambia el tiempo
+    servicio.db().actualizar_tiempo(pendiente, 120).unwrap();
+    let antes = servicio.db().totales().unwrap();
+    servicio.archivar_varias(&[pendiente]).unwrap();
+    let despues = servicio.db().totales().unwrap();
+    assert_eq!(despues.segundos, antes.segundos);
+    assert_eq!(despues.tareas, antes.tareas - 1);
+    assert_eq!(despues.completadas, antes.completadas);
+
+    // Sin completadas no hay nada que archivar
+    assert_eq!(servicio.archivar_completadas().unwrap(), 0);
+}
+
+#[test]
+fn restaurar_una_archivada_la_devuelve_a_la_lista() {
+    let mut servicio = servicio();
+    let id = servicio.tareas()[2].id;
+    servicio.marcar(id, true).unwrap();
+    servicio.archivar_completadas().unwrap();
+
+    servicio.restaurar_archivada(id).unwrap();
+
+    assert!(servicio.tarea(id).unwrap().checked);
+    assert!(servicio.db().cargar_archivadas().unwrap().is_empty());
+    servicio.recargar();
+    assert!(servicio.tarea(id).is_some());
+}
+
+#[test]
+fn eliminar_lleva_a_la_papelera_y_se_puede_restaurar() {
+    let mut servicio = servicio();
+    let id = servicio.tareas()[4].id;
+    let texto = servicio.tarea(id).unwrap().text.clone();
+    let antes = servicio.db().totales().unwrap();
+
+    servicio.eliminar(id).unwrap();
+    assert!(
+        servicio
+            .db()
+            .cargar_tareas()
+            .unwrap()
+            .iter()
+            .all(|t| t.id != id)
+    );
+    let papelera = servicio.db().cargar_papelera().unwrap();
+    assert_eq!(papelera.len(), 1);
+    assert_eq!(papelera[0].text, texto);
+    assert_eq!(servicio.db().totales().unwrap().tareas, antes.tareas - 1);
+
+    servicio.restaurar_eliminada(id).unwrap();
+    assert_eq!(servicio.tarea(id).unwrap().text, texto);
+    assert!(servicio.db().cargar_papelera().unwrap().is_empty());
+    assert_eq!(servicio.db().totales().unwrap().tareas, antes.tareas);
+}
+
+#[test]
+fn eliminar_definitivamente_solo_borra_de_la_papelera() {
+    let mut servicio = servicio();
+    let activa = servicio.tareas()[0].id;
+    let eliminada = servicio.tareas()[1].id;
+    servicio.eliminar(eliminada).unwrap();
+
+    // Una tarea activa no se borra por esta vía
+    servicio.db().eliminar_definitivamente(activa).unwrap();
+    assert!(servicio.db().cargar_tarea(activa).is_ok());
+
+    servicio.db().eliminar_definitivamente(eliminada).unwrap();
+    assert!(servicio.db().cargar_papelera().unwrap().is_empty());
+    servicio.restaurar_eliminada(eliminada).unwrap();
+    assert!(servicio.tarea(eliminada).is_none());
+}
+
+#[test]
+fn la_papelera_se_purga_al_abrir() {
+    let ruta = ruta_db("papelera");
+    let texto = ruta.to_str().unwrap();
+
+    let mut servicio = ServicioTareas::new(Db::new(texto).unwrap());
+    let vieja = servicio.tareas()[0].id;
+    let reciente = servicio.tareas()[1].id;
+    servicio.eliminar(vieja).unwrap();
+    servicio.eliminar(reciente).unwrap();
+    drop(servicio);
+
+    let conn = rusqlite::Connection::open(&ruta).unwrap();
+    conn.execute(
+        "UPDATE tareas SET eliminada_en = datetime('now', 'localtime', '-40 days') WHERE id = ?1",
+        [vieja],
+    )
+    .unwrap();
+    drop(conn);
+
+    // Por defecto se guardan 30 días
+    let servicio = ServicioTareas::new(Db::new(texto).unwrap());
+    let papelera = servicio.db().cargar_papelera().unwrap();
+    assert_eq!(papelera.len(), 1);
+    assert_eq!(papelera[0].id, reciente);
+
+    // Con 0 días no se purga nunca
+    assert_eq!(servicio.db().purgar_papelera(0).unwrap(), 0);
+    drop(servicio);
+    borrar(ruta);
+}
+
+#[test]
+fn operaciones_en_lote() {
+    let mut servicio = servicio();
+    let ids: Vec<i32> = servicio.tareas()[..3].iter().map(|t| t.id).collect();
+    let otra = servicio.tareas()[5].id;
+
+    assert!(servicio.marcar_varias(&ids, true).unwrap().is_empty());
+    for &id in &ids {
+        assert!(servicio.tarea(id).unwrap().checked);
+        assert!(servicio.db().cargar_tarea(id).unwrap().checked);
+    }
+    assert!(!servicio.tarea(otra).unwrap().checked);
+
+    servicio.marcar_varias(&ids[1..], false).unwrap();
+    assert!(servicio.tarea(ids[0]).unwrap().checked);
+    assert!(!servicio.db().cargar_tarea(ids[2]).unwrap().checked);
+
+    for &id in &ids {
+        servicio.db().actualizar_tiempo(id, 120).unwrap();
+    }
+    servicio.recargar();
+    servicio.alternar_temporizador(ids[0]);
+    servicio.resetear_temporizadores(&ids[..2]).unwrap();
+    assert!(!servicio.hay_temporizador_activo());
+    assert_eq!(
+        servicio.db().cargar_tarea(ids[0]).unwrap().tiempo_total(),
+        0
+    );
+    assert_eq!(
+        servicio.db().cargar_tarea(ids[1]).unwrap().tiempo_total(),
+        0
+    );
+    assert_eq!(
+        servicio.db().cargar_tarea(ids[2]).unwrap().tiempo_total(),
+        120
+    );
+
+    servicio.archivar_varias(&ids[..1]).unwrap();
+    assert!(servicio.tarea(ids[0]).is_none());
+    assert_eq!(servicio.db().cargar_archivadas().unwrap()[0].id, ids[0]);
+
+    servicio.eliminar_varias(&ids[1..]).unwrap();
+    assert!(servicio.tarea(ids[1]).is_none());
+    assert_eq!(servicio.db().cargar_papelera().unwrap().len(), 2);
+    assert!(servicio.tarea(otra).is_some());
+}
+
+#[test]
+fn completar_en_lote_genera_las_recurrencias() {
+    let mut servicio = servicio();
+    let diaria = servicio.tareas()[3].id;
+    let mensual = servicio.tareas()[6].id;
+    let n = servicio.tareas().len();
+
+    let nuevas = servicio.marcar_varias(&[diaria, mensual], true).unwrap();
+
+    assert_eq!(nuevas.len(), 2);
+    assert_eq!(servicio.tareas().len(), n + 2);
+    assert_eq!(servicio.tarea(diaria).unwrap().recurrencia, None);
+    assert_eq!(
+        servicio.tarea(nuevas[1]).unwrap().recurrencia,
+        Some(Recurrencia::Mensual(1))
+    );
+}
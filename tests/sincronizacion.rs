@@ -0,0 +1,399 @@
+mod common;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use pixi::Db;
+use pixi::sincronizacion::{Conflicto, ErrorSincronizacion, Informe};
+
+// Dos equipos, cada uno con su base de datos, y la carpeta que comparten
+struct Equipos {
+    dir: PathBuf,
+    a: Db,
+    b: Db,
+}
+
+impl Equipos {
+    fn new(nombre: &str) -> Self {
+        Self::con_idiomas(nombre, [None, None])
+    }
+
+    // Con el idioma de cada base de datos elegido antes de crearla, para
+    // que sus tareas de ejemplo salgan en él
+    fn con_idiomas(nombre: &str, idiomas: [Option<&str>; 2]) -> Self {
+        let dir = common::carpeta(nombre);
+        let carpeta = dir.join("compartida");
+        let abrir = |nombre: &str, idioma: Option<&str>| {
+            let ruta = dir.join(nombre);
+            if let Some(idioma) = idioma {
+                rusqlite::Connection::open(&ruta)
+                    .unwrap()
+                    .execute_batch(&format!(
+                        "CREATE TABLE ajustes (clave TEXT PRIMARY KEY, valor TEXT NOT NULL);
+                         INSERT INTO ajustes VALUES ('idioma', '{idioma}');"
+                    ))
+                    .unwrap();
+            }
+            let db = Db::new(ruta.to_str().unwrap()).unwrap();
+            db.guardar_carpeta_sincronizacion(&carpeta).unwrap();
+            db
+        };
+        let (a, b) = (abrir("a.db", idiomas[0]), abrir("b.db", idiomas[1]));
+        Equipos { dir, a, b }
+    }
+
+    fn carpeta(&self) -> PathBuf {
+        self.dir.join("compartida")
+    }
+
+    // Cada equipo sincroniza una vez y el primero otra más, para que los
+    // dos hayan visto todo
+    fn sincronizar(&self) -> (Informe, Informe) {
+        self.a.sincronizar().unwrap();
+        let b = self.b.sincronizar().unwrap();
+        let a = self.a.sincronizar().unwrap();
+        (a, b)
+    }
+}
+
+impl Drop for Equipos {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn id_de(db: &Db, texto: &str) -> i32 {
+    db.cargar_tareas()
+        .unwrap()
+        .into_iter()
+        .find(|t| t.text == texto)
+        .unwrap_or_else(|| panic!("no está «{}»", texto))
+        .id
+}
+
+fn textos(db: &Db) -> Vec<String> {
+    let mut textos: Vec<String> = db
+        .cargar_tareas()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.text)
+        .collect();
+    textos.sort();
+    textos
+}
+
+// Para que el cambio siguiente lleve una marca posterior
+fn esperar() {
+    std::thread::sleep(Duration::from_millis(5));
+}
+
+#[test]
+fn sin_carpeta_no_se_sincroniza() {
+    let db = Db::new(":memory:").unwrap();
+    assert!(matches!(
+        db.sincronizar(),
+        Err(ErrorSincronizacion::SinCarpeta)
+    ));
+}
+
+#[test]
+fn las_dos_listas_acaban_siendo_la_misma() {
+    let equipos = Equipos::new("sync-union");
+    equipos.a.agregar_tarea("de A").unwrap();
+    equipos.b.agregar_tarea("de B").unwrap();
+
+    let ejemplos = equipos.a.cargar_tareas().unwrap().len() - 1;
+    equipos.sincronizar();
+
+    assert_eq!(textos(&equipos.a), textos(&equipos.b));
+    // Los ejemplos de cada equipo son las mismas tareas, no se duplican
+    assert_eq!(equipos.a.cargar_tareas().unwrap().len(), ejemplos + 2);
+    id_de(&equipos.b, "de A");
+    id_de(&equipos.a, "de B");
+
+    // Sin cambios nuevos no se envía ni se recibe nada
+    let (a, b) = equipos.sincronizar();
+    assert_eq!(
+        (a.enviados, a.recibidos, b.enviados, b.recibidos),
+        (0, 0, 0, 0)
+    );
+}
+
+#[test]
+fn los_cambios_a_los_ejemplos_de_antes_de_sincronizar_llegan_al_otro() {
+    let equipos = Equipos::new("sync-ejemplos");
+    let ejemplo = equipos.a.cargar_tareas().unwrap().remove(0);
+    equipos
+        .a
+        .actualizar_descripcion(ejemplo.id, "ejemplo cambiado")
+        .unwrap();
+    equipos.a.guardar_sesion(ejemplo.id, 60).unwrap();
+
+    let (a, b) = equipos.sincronizar();
+
+    let id_b = id_de(&equipos.b, "ejemplo cambiado");
+    assert_eq!(equipos.b.cargar_tarea(id_b).unwrap().tiempo_total(), 60);
+    assert_eq!(textos(&equipos.a), textos(&equipos.b));
+    assert!(a.conflictos.is_empty() && b.conflictos.is_empty());
+}
+
+#[test]
+fn los_ejemplos_en_otro_idioma_acaban_siendo_los_mismos() {
+    let equipos = Equipos::con_idiomas("sync-idiomas", [Some("es"), Some("en")]);
+    assert_ne!(textos(&equipos.a), textos(&equipos.b));
+
+    let (a, b) = equipos.sincronizar();
+
+    assert_eq!(textos(&equipos.a), textos(&equipos.b));
+    assert_eq!(
+        equipos.a.cargar_tareas().unwrap().len(),
+        equipos.b.cargar_tareas().unwrap().len()
+    );
+    assert!(a.conflictos.is_empty() && b.conflictos.is_empty());
+}
+
+#[test]
+fn dos_instancias_sobre_la_misma_base_no_aplican_dos_veces_un_cambio() {
+    let equipos = Equipos::new("sync-instancias");
+    let id_a = equipos.a.agregar_tarea("cronometrada").unwrap();
+    equipos.sincronizar();
+    equipos.a.guardar_sesion(id_a, 60).unwrap();
+    equipos.a.sincronizar().unwrap();
+
+    // Varias instancias de B reciben a la vez el mismo diario
+    let ruta_b = equipos.dir.join("b.db");
+    let salida = std::sync::Barrier::new(4);
+    std::thread::scope(|hilos| {
+        for _ in 0..4 {
+            hilos.spawn(|| {
+                let db = Db::new(ruta_b.to_str().unwrap()).unwrap();
+                salida.wait();
+                db.sincronizar().unwrap();
+            });
+        }
+    });
+
+    let id_b = id_de(&equipos.b, "cronometrada");
+    assert_eq!(equipos.b.cargar_tarea(id_b).unwrap().tiempo_total(), 60);
+}
+
+#[test]
+fn un_cambio_sobre_lo_ya_sincronizado_no_es_conflicto() {
+    let equipos = Equipos::new("sync-seguido");
+    let id_a = equipos.a.agregar_tarea("original").unwrap();
+    equipos.sincronizar();
+
+    equipos
+        .a
+        .actualizar_descripcion(id_a, "cambiada en A")
+        .unwrap();
+    let (a, b) = equipos.sincronizar();
+    let id_b = id_de(&equipos.b, "cambiada en A");
+
+    // Y de vuelta, partiendo del cambio recibido
+    equipos
+        .b
+        .actualizar_descripcion(id_b, "cambiada en B")
+        .unwrap();
+    let (a2, b2) = equipos.sincronizar();
+
+    assert_eq!(equipos.a.cargar_tarea(id_a).unwrap().text, "cambiada en B");
+    for informe in [a, b, a2, b2] {
+        assert!(informe.conflictos.is_empty());
+    }
+}
+
+#[test]
+fn en_la_descripcion_gana_el_cambio_mas_reciente_y_se_informa() {
+    let equipos = Equipos::new("sync-conflicto");
+    let id_a = equipos.a.agregar_tarea("original").unwrap();
+    equipos.sincronizar();
+    let id_b = id_de(&equipos.b, "original");
+
+    equipos
+        .a
+        .actualizar_descripcion(id_a, "versión de A")
+        .unwrap();
+    esperar();
+    equipos
+        .b
+        .actualizar_descripcion(id_b, "versión de B")
+        .unwrap();
+    let (a, b) = equipos.sincronizar();
+
+    assert_eq!(equipos.a.cargar_tarea(id_a).unwrap().text, "versión de B");
+    assert_eq!(equipos.b.cargar_tarea(id_b).unwrap().text, "versión de B");
+    let conflicto = |id| Conflicto {
+        id,
+        conservada: "versión de B".to_string(),
+        descartada: "versión de A".to_string(),
+    };
+    assert_eq!(b.conflictos, [conflicto(id_b)]);
+    assert_eq!(a.conflictos, [conflicto(id_a)]);
+}
+
+#[test]
+fn completarla_gana_aunque_el_otro_cambio_sea_posterior() {
+    let equipos = Equipos::new("sync-completada");
+    let id_a = equipos.a.agregar_tarea("tarea").unwrap();
+    equipos.sincronizar();
+    let id_b = id_de(&equipos.b, "tarea");
+
+    equipos.a.actualizar_tarea(id_a, true).unwrap();
+    esperar();
+    equipos.b.actualizar_tarea(id_b, true).unwrap();
+    equipos.b.actualizar_tarea(id_b, false).unwrap();
+    let (a, b) = equipos.sincronizar();
+
+    assert!(equipos.a.cargar_tarea(id_a).unwrap().checked);
+    assert!(equipos.b.cargar_tarea(id_b).unwrap().checked);
+    assert!(a.conflictos.is_empty() && b.conflictos.is_empty());
+}
+
+#[test]
+fn el_tiempo_de_los_dos_equipos_se_suma() {
+    let equipos = Equipos::new("sync-tiempo");
+    let id_a = equipos.a.agregar_tarea("cronometrada").unwrap();
+    equipos.sincronizar();
+    let id_b = id_de(&equipos.b, "cronometrada");
+
+    equipos.a.guardar_sesion(id_a, 60).unwrap();
+    equipos.b.guardar_sesion(id_b, 90).unwrap();
+    equipos.sincronizar();
+
+    assert_eq!(equipos.a.cargar_tarea(id_a).unwrap().tiempo_total(), 150);
+    assert_eq!(equipos.b.cargar_tarea(id_b).unwrap().tiempo_total(), 150);
+
+    // Un diario copiado dos veces no vuelve a sumar lo mismo
+    let diario = diario_de(&equipos.carpeta(), &equipos.a);
+    let contenido = std::fs::read_to_string(&diario).unwrap();
+    std::fs::write(&diario, contenido.repeat(2)).unwrap();
+    equipos.b.sincronizar().unwrap();
+    assert_eq!(equipos.b.cargar_tarea(id_b).unwrap().tiempo_total(), 150);
+}
+
+#[test]
+fn la_papelera_tambien_se_sincroniza() {
+    let equipos = Equipos::new("sync-papelera");
+    let id_a = equipos.a.agregar_tarea("a borrar").unwrap();
+    equipos.sincronizar();
+    let id_b = id_de(&equipos.b, "a borrar");
+
+    equipos.a.eliminar_tarea(id_a).unwrap();
+    equipos.sincronizar();
+    assert!(equipos.b.cargar_tarea(id_b).is_err());
+
+    equipos.b.restaurar_eliminada(id_b).unwrap();
+    equipos.sincronizar();
+    assert_eq!(equipos.a.cargar_tarea(id_a).unwrap().text, "a borrar");
+}
+
+#[test]
+fn lo_borrado_para_siempre_no_vuelve_del_otro_equipo() {
+    let equipos = Equipos::new("sync-vaciar");
+    let id_a = equipos.a.agregar_tarea("para siempre").unwrap();
+    equipos.sincronizar();
+    let id_b = id_de(&equipos.b, "para siempre");
+
+    equipos.a.eliminar_tarea(id_a).unwrap();
+    assert_eq!(equipos.a.vaciar_papelera().unwrap(), 1);
+    // Mientras, el otro equipo la sigue cambiando
+    equipos
+        .b
+        .actualizar_descripcion(id_b, "cambiada en B")
+        .unwrap();
+    equipos.sincronizar();
+    equipos.sincronizar();
+
+    for db in [&equipos.a, &equipos.b] {
+        assert!(
+            !textos(db)
+                .iter()
+                .any(|t| t.contains("para siempre") || t.contains("en B"))
+        );
+        assert!(db.cargar_papelera().unwrap().is_empty());
+    }
+}
+
+#[test]
+fn una_linea_a_medio_copiar_espera_a_la_siguiente_vez() {
+    let equipos = Equipos::new("sync-parcial");
+    equipos
+        .a
+        .agregar_tarea("con tab\ty salto\nde línea")
+        .unwrap();
+    equipos.a.sincronizar().unwrap();
+
+    let diario = diario_de(&equipos.carpeta(), &equipos.a);
+    let completo = std::fs::read_to_string(&diario).unwrap();
+    std::fs::write(&diario, &completo[..completo.len() - 1]).unwrap();
+    equipos.b.sincronizar().unwrap();
+    // Sin el final de la última línea falta la descripción de la tarea
+    assert!(!textos(&equipos.b).contains(&"con tab\ty salto\nde línea".to_string()));
+
+    std::fs::write(&diario, &completo).unwrap();
+    equipos.b.sincronizar().unwrap();
+    id_de(&equipos.b, "con tab\ty salto\nde línea");
+}
+
+#[test]
+fn los_bytes_que_no_son_utf8_no_desplazan_la_lectura() {
+    let equipos = Equipos::new("sync-bytes");
+    let diario = equipos.carpeta().join("otro.cambios");
+    std::fs::create_dir_all(equipos.carpeta()).unwrap();
+    let marca = "2030-01-01T00:00:00.000@otro";
+    let mut contenido = format!("1\t{marca}\t\totro-1\tcreada\t\n").into_bytes();
+    contenido.extend_from_slice(format!("2\t{marca}\t\totro-1\tdescripcion\tmal ").as_bytes());
+    contenido.extend_from_slice(b"\xff\xfe\n");
+    let ultima = format!("3\t{marca}\t{marca}\totro-1\tdescripcion\tbien\n");
+    // La última línea llega en dos veces
+    let (principio, fin) = ultima.as_bytes().split_at(4);
+    contenido.extend_from_slice(principio);
+    std::fs::write(&diario, &contenido).unwrap();
+    equipos.a.sincronizar().unwrap();
+
+    contenido.extend_from_slice(fin);
+    std::fs::write(&diario, &contenido).unwrap();
+    let informe = equipos.a.sincronizar().unwrap();
+
+    assert_eq!(informe.recibidos, 1);
+    id_de(&equipos.a, "bien");
+}
+
+#[test]
+fn un_reloj_que_retrocede_no_hace_perder_cambios() {
+    let equipos = Equipos::new("sync-reloj");
+    let diario = equipos.carpeta().join("otro.cambios");
+    std::fs::create_dir_all(equipos.carpeta()).unwrap();
+    let futuro = "2030-01-01T00:00:00.000@otro";
+    std::fs::write(
+        &diario,
+        format!(
+            "1\t{futuro}\t\totro-1\tcreada\t\n\
+             2\t{futuro}\t\totro-1\tdescripcion\tantes\n"
+        ),
+    )
+    .unwrap();
+    equipos.a.sincronizar().unwrap();
+    let id = id_de(&equipos.a, "antes");
+
+    // El equipo atrasa su reloj y sigue numerando sus cambios
+    let mut fichero = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&diario)
+        .unwrap();
+    writeln!(
+        fichero,
+        "3\t2000-01-01T00:00:00.000@otro\t{futuro}\totro-1\tdescripcion\tdespués"
+    )
+    .unwrap();
+    let informe = equipos.a.sincronizar().unwrap();
+
+    assert_eq!(informe.recibidos, 1);
+    assert_eq!(equipos.a.cargar_tarea(id).unwrap().text, "después");
+}
+
+fn diario_de(carpeta: &Path, db: &Db) -> PathBuf {
+    carpeta.join(format!("{}.cambios", db.dispositivo().unwrap().unwrap()))
+}
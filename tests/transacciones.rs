@@ -0,0 +1,178 @@
+mod common;
+
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::{Path, PathBuf};
+
+use common::{borrar, ruta_db};
+use pixi::{Db, ServicioTareas};
+use rusqlite::Connection;
+
+fn fallo() -> rusqlite::Error {
+    rusqlite::Error::InvalidQuery
+}
+
+fn descripciones(db: &Db) -> Vec<String> {
+    db.cargar_tareas()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.text)
+        .collect()
+}
+
+// Base de datos en una carpeta temporal, para poder sabotearla desde otra
+// conexión con un disparador que falla al tocar la tarea `id`
+fn db_con_fallo(nombre: &str, disparador: &str) -> (PathBuf, Db) {
+    let ruta = ruta_db(nombre);
+    let db = Db::new(ruta.to_str().unwrap()).unwrap();
+    Connection::open(&ruta)
+        .unwrap()
+        .execute_batch(disparador)
+        .unwrap();
+    (ruta, db)
+}
+
+fn sesiones(ruta: &Path) -> i64 {
+    Connection::open(ruta)
+        .unwrap()
+        .query_row("SELECT COUNT(*) FROM sesiones", [], |row| row.get(0))
+        .unwrap()
+}
+
+#[test]
+fn confirma_si_todo_va_bien() {
+    let db = Db::new(":memory:").unwrap();
+    let antes = descripciones(&db).len();
+
+    let id = db
+        .transaccion(|db| {
+            let id = db.agregar_tarea("una")?;
+            db.actualizar_descripcion(id, "cambiada")?;
+            Ok(id)
+        })
+        .unwrap();
+
+    assert_eq!(descripciones(&db).len(), antes + 1);
+    assert_eq!(db.cargar_tarea(id).unwrap().text, "cambiada");
+}
+
+#[test]
+fn deshace_todo_si_falla_a_medias() {
+    let db = Db::new(":memory:").unwrap();
+    let antes = descripciones(&db);
+
+    let resultado: rusqlite::Result<()> = db.transaccion(|db| {
+        db.agregar_tarea("primera")?;
+        db.actualizar_descripcion(1, "cambiada")?;
+        Err(fallo())
+    });
+
+    assert!(resultado.is_err());
+    assert_eq!(descripciones(&db), antes);
+}
+
+#[test]
+fn una_anidada_que_falla_solo_deshace_lo_suyo() {
+    let db = Db::new(":memory:").unwrap();
+    let antes = descripciones(&db).len();
+
+    db.transaccion(|db| {
+        db.agregar_tarea("de fuera")?;
+        let dentro: rusqlite::Result<()> = db.transaccion(|db| {
+            db.agregar_tarea("de dentro")?;
+            Err(fallo())
+        });
+        assert!(dentro.is_err());
+        db.agregar_tarea("después")?;
+        Ok(())
+    })
+    .unwrap();
+
+    let despues = descripciones(&db);
+    assert_eq!(despues.len(), antes + 2);
+    assert!(despues.contains(&"de fuera".to_string()));
+    assert!(despues.contains(&"después".to_string()));
+    assert!(!despues.contains(&"de dentro".to_string()));
+}
+
+#[test]
+fn un_fallo_fuera_deshace_tambien_las_anidadas() {
+    let db = Db::new(":memory:").unwrap();
+    let antes = descripciones(&db);
+
+    let resultado: rusqlite::Result<()> = db.transaccion(|db| {
+        db.transaccion(|db| db.agregar_tarea("confirmada dentro"))?;
+        Err(fallo())
+    });
+
+    assert!(resultado.is_err());
+    assert_eq!(descripciones(&db), antes);
+}
+
+#[test]
+fn un_panico_tambien_deshace() {
+    let db = Db::new(":memory:").unwrap();
+    let antes = descripciones(&db);
+
+    let resultado = catch_unwind(AssertUnwindSafe(|| {
+        db.transaccion::<()>(|db| {
+            db.agregar_tarea("a medias")?;
+            panic!("fallo simulado");
+        })
+    }));
+
+    assert!(resultado.is_err());
+    assert_eq!(descripciones(&db), antes);
+    // Y la conexión sigue sirviendo
+    db.transaccion(|db| db.agregar_tarea("otra")).unwrap();
+    assert_eq!(descripciones(&db).len(), antes.len() + 1);
+}
+
+#[test]
+fn marcar_varias_no_marca_ninguna_si_falla_una() {
+    let (ruta, db) = db_con_fallo(
+        "marcar",
+        "CREATE TRIGGER falla BEFORE UPDATE OF completada ON tareas WHEN NEW.id = 3
+         BEGIN SELECT RAISE(ABORT, 'fallo simulado'); END",
+    );
+    let mut servicio = ServicioTareas::new(db);
+    let ids = [1, 2, 3];
+
+    assert!(servicio.marcar_varias(&ids, true).is_err());
+
+    for id in ids {
+        assert!(!servicio.tarea(id).unwrap().checked);
+        assert!(!servicio.db().cargar_tarea(id).unwrap().checked);
+    }
+    drop(servicio);
+    borrar(ruta);
+}
+
+#[test]
+fn eliminar_no_guarda_la_sesion_si_falla_el_borrado() {
+    let (ruta, db) = db_con_fallo(
+        "eliminar",
+        "CREATE TRIGGER falla BEFORE UPDATE OF eliminada_en ON tareas
+         BEGIN SELECT RAISE(ABORT, 'fallo simulado'); END",
+    );
+    let mut servicio = ServicioTareas::new(db);
+    let id = servicio.tareas()[0].id;
+    servicio.alternar_temporizador(id);
+
+    assert!(servicio.eliminar(id).is_err());
+
+    // Ni la sesión ni el borrado: la tarea sigue en la lista, cronometrándose
+    assert_eq!(sesiones(&ruta), 0);
+    assert!(servicio.tarea(id).unwrap().temporizador_activo());
+    assert!(servicio.db().cargar_tarea(id).is_ok());
+
+    // Con el disparador quitado, la misma operación se completa entera
+    Connection::open(&ruta)
+        .unwrap()
+        .execute_batch("DROP TRIGGER falla")
+        .unwrap();
+    servicio.eliminar(id).unwrap();
+    assert_eq!(sesiones(&ruta), 1);
+    assert!(servicio.tarea(id).is_none());
+    drop(servicio);
+    borrar(ruta);
+}